@@ -0,0 +1,1217 @@
+//
+// build.rs
+//
+// Generates the OpenAPI document served at `/openapi.json`. Operations come
+// from the routes mounted in `web::build` and `web::versions` and the
+// handlers in `web::controllers`, and schemas from the serde types they take
+// and return, so the document follows the code on every build.
+//
+extern crate quote;
+#[macro_use]
+extern crate serde_json;
+extern crate syn;
+
+use quote::ToTokens;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use syn::punctuated::Punctuated;
+use syn::{
+    Attribute, Data, DeriveInput, Expr, Fields, FnArg, GenericArgument, Item, ItemFn, Lit, Meta,
+    NestedMeta, Pat, PathArguments, ReturnType, Stmt, Type,
+};
+
+const ROUTE_METHODS: [&str; 7] = ["get", "put", "post", "delete", "head", "patch", "options"];
+
+/// Mounted routes left out of the document on purpose: the CORS preflight
+/// answer lives outside `web::controllers` and isn't part of the API
+const UNDOCUMENTED: &[(&str, &str)] = &[("cors", "cors")];
+
+/// A type sent or received, as written
+struct TypeDef {
+    item: DeriveInput,
+    /// Values its `FromFormValue` impl accepts, when it has one
+    form_values: Option<Vec<String>>,
+}
+
+/// A route handler, as written
+struct Handler {
+    module: String,
+    item: ItemFn,
+}
+
+///
+/// Source
+///
+
+fn rust_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let mut entries = fs::read_dir(dir)
+        .expect("Failed to read source directory")
+        .map(|e| e.expect("Failed to read source directory").path())
+        .collect::<Vec<PathBuf>>();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            // Catches new files as well as changed ones
+            println!("cargo:rerun-if-changed={}", path.display());
+            rust_files(&path, files);
+        } else if path.extension().map_or(false, |e| e == "rs") {
+            files.push(path);
+        }
+    }
+}
+
+fn parse(path: &Path) -> syn::File {
+    println!("cargo:rerun-if-changed={}", path.display());
+    let source = fs::read_to_string(path).expect("Failed to read source file");
+    syn::parse_file(&source).unwrap_or_else(|e| panic!("{}: {}", path.display(), e))
+}
+
+/// Every type deriving `Serialize`, `Deserialize` or `FromForm`, or parsed
+/// from a query string by a `FromFormValue` impl, by name
+fn collect_types(files: &[syn::File]) -> BTreeMap<String, TypeDef> {
+    let mut described = BTreeMap::new();
+    let mut others = BTreeMap::new();
+    let mut form_values = BTreeMap::new();
+
+    for file in files {
+        for item in &file.items {
+            match *item {
+                Item::Struct(ref s) => {
+                    let types = if is_described(&s.attrs) {
+                        &mut described
+                    } else {
+                        &mut others
+                    };
+                    types.insert(s.ident.to_string(), derive_input(s));
+                }
+                Item::Enum(ref e) => {
+                    let types = if is_described(&e.attrs) {
+                        &mut described
+                    } else {
+                        &mut others
+                    };
+                    types.insert(e.ident.to_string(), derive_input(e));
+                }
+                Item::Impl(ref i) => {
+                    let is_form_value = i.trait_.as_ref().map_or(false, |t| {
+                        last_ident(&t.1).map_or(false, |n| n == "FromFormValue")
+                    });
+                    if let (true, Some(name)) = (is_form_value, type_name(&i.self_ty)) {
+                        form_values.insert(name, string_patterns(i));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    for name in form_values.keys() {
+        if let Some(item) = others.remove(name) {
+            described.insert(name.clone(), item);
+        }
+    }
+    described
+        .into_iter()
+        .map(|(name, item)| {
+            let values = form_values.get(&name).cloned();
+            (
+                name,
+                TypeDef {
+                    item,
+                    form_values: values,
+                },
+            )
+        })
+        .collect()
+}
+
+/// A struct or enum as a derive macro would see it
+fn derive_input<T: ToTokens>(item: &T) -> DeriveInput {
+    syn::parse2(item.into_token_stream()).expect("Failed to parse type")
+}
+
+/// String literals matched in an impl, which for `FromFormValue` are the
+/// values it accepts
+fn string_patterns(item: &syn::ItemImpl) -> Vec<String> {
+    let source = quote_tokens(item);
+    let mut values = Vec::new();
+    let mut rest = source.as_str();
+    while let Some(start) = rest.find('"') {
+        let after = &rest[start + 1..];
+        let end = match after.find('"') {
+            Some(end) => end,
+            None => break,
+        };
+        let literal = &after[..end];
+        rest = &after[end + 1..];
+        if rest.trim_start().starts_with("=>") {
+            values.push(literal.to_owned());
+        }
+    }
+    values
+}
+
+fn quote_tokens<T: ToTokens>(item: &T) -> String {
+    item.into_token_stream().to_string()
+}
+
+/// A handler as it's served
+struct Mounted {
+    base: String,
+    module: String,
+    name: String,
+    /// Served by a deprecated version
+    deprecated: bool,
+    /// Prefix for operation ids, so versions after the first don't clash
+    /// with it
+    prefix: String,
+}
+
+/// Every expression in a block, however deeply it's nested
+fn expressions<'a>(block: &'a syn::Block, found: &mut Vec<&'a Expr>) {
+    for stmt in &block.stmts {
+        match *stmt {
+            Stmt::Local(ref local) => {
+                if let Some((_, ref init)) = local.init {
+                    expression(init, found);
+                }
+            }
+            Stmt::Expr(ref e) | Stmt::Semi(ref e, _) => expression(e, found),
+            Stmt::Item(_) => {}
+        }
+    }
+}
+
+fn expression<'a>(expr: &'a Expr, found: &mut Vec<&'a Expr>) {
+    found.push(expr);
+    match *expr {
+        Expr::MethodCall(ref call) => {
+            expression(&call.receiver, found);
+            for arg in &call.args {
+                expression(arg, found);
+            }
+        }
+        Expr::Call(ref call) => {
+            expression(&call.func, found);
+            for arg in &call.args {
+                expression(arg, found);
+            }
+        }
+        Expr::Array(ref array) => {
+            for e in &array.elems {
+                expression(e, found);
+            }
+        }
+        Expr::Block(ref b) => expressions(&b.block, found),
+        Expr::Paren(ref p) => expression(&p.expr, found),
+        Expr::Reference(ref r) => expression(&r.expr, found),
+        Expr::Assign(ref a) => expression(&a.right, found),
+        Expr::Closure(ref c) => expression(&c.body, found),
+        Expr::Return(ref r) => {
+            if let Some(ref e) = r.expr {
+                expression(e, found);
+            }
+        }
+        Expr::If(ref i) => {
+            expressions(&i.then_branch, found);
+            if let Some((_, ref e)) = i.else_branch {
+                expression(e, found);
+            }
+        }
+        Expr::Match(ref m) => {
+            expression(&m.expr, found);
+            for arm in &m.arms {
+                expression(&arm.body, found);
+            }
+        }
+        Expr::ForLoop(ref f) => {
+            expression(&f.expr, found);
+            expressions(&f.body, found);
+        }
+        _ => {}
+    }
+}
+
+/// The `module::handler` paths a `routes!` call lists, or `None` for any
+/// other expression
+fn route_list(expr: &Expr) -> Option<Vec<(String, String)>> {
+    let mac = match *expr {
+        Expr::Macro(ref m) => &m.mac,
+        _ => return None,
+    };
+    if mac
+        .path
+        .segments
+        .last()
+        .map_or(true, |s| s.into_value().ident != "routes")
+    {
+        return None;
+    }
+
+    let paths = syn::parse::Parser::parse2(
+        Punctuated::<syn::Path, syn::token::Comma>::parse_terminated,
+        mac.tts.clone(),
+    )
+    .expect("Failed to parse routes!");
+    let routes = paths
+        .into_iter()
+        .filter_map(|path| {
+            let segments = path
+                .segments
+                .iter()
+                .map(|s| s.ident.to_string())
+                .collect::<Vec<String>>();
+            if segments.len() == 2 {
+                Some((segments[0].clone(), segments[1].clone()))
+            } else {
+                println!(
+                    "cargo:warning=Route {} isn't named as module::handler, so it's left out",
+                    quote_tokens(&path)
+                );
+                None
+            }
+        })
+        .collect();
+    Some(routes)
+}
+
+/// Handlers `web::build` mounts itself, outside any version
+fn unversioned_routes(web: &syn::File) -> Vec<Mounted> {
+    let mut exprs = Vec::new();
+    for item in &web.items {
+        if let Item::Fn(ref f) = *item {
+            expressions(&f.block, &mut exprs);
+        }
+    }
+
+    let mut mounted = Vec::new();
+    for expr in exprs {
+        let call = match *expr {
+            Expr::MethodCall(ref call) if call.method == "mount" => call,
+            _ => continue,
+        };
+        let base = match call.args.iter().next() {
+            Some(&Expr::Lit(ref l)) => match l.lit {
+                Lit::Str(ref s) => Some(s.value()),
+                _ => None,
+            },
+            _ => None,
+        };
+        let routes = call.args.iter().nth(1).and_then(route_list);
+        match (base, routes) {
+            (Some(base), Some(routes)) => {
+                for (module, name) in routes {
+                    mounted.push(Mounted {
+                        base: base.clone(),
+                        module,
+                        name,
+                        deprecated: false,
+                        prefix: String::new(),
+                    });
+                }
+            }
+            _ => println!(
+                "cargo:warning=Mount in web::build isn't a string and a routes! list, so \
+                 its routes are left out"
+            ),
+        }
+    }
+    mounted
+}
+
+/// Handlers each version in `web::versions` serves. A version's routes
+/// replace the earlier version's with the same method and path, as in
+/// `versions::revise`.
+fn versioned_routes(
+    versions: &syn::File,
+    handlers: &BTreeMap<(String, String), Handler>,
+) -> Vec<Mounted> {
+    let list = versions
+        .items
+        .iter()
+        .filter_map(|item| match *item {
+            Item::Const(ref c) if c.ident == "VERSIONS" => match *c.expr {
+                syn::Expr::Reference(ref r) => match *r.expr {
+                    syn::Expr::Array(ref a) => Some(a.elems.iter().cloned().collect::<Vec<_>>()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        })
+        .next()
+        .expect("VERSIONS should be an array");
+
+    let key = |route: &(String, String)| {
+        handlers
+            .get(route)
+            .and_then(|h| route_attr(&h.item.attrs))
+            .map(|(method, uri, _)| (method, uri.split('?').next().unwrap_or("").to_owned()))
+    };
+
+    let mut mounted = Vec::new();
+    let mut routes: Vec<(String, String)> = Vec::new();
+    for (i, version) in list.iter().enumerate() {
+        let fields = match *version {
+            syn::Expr::Struct(ref s) => s.fields.iter().cloned().collect::<Vec<_>>(),
+            _ => panic!("VERSIONS should hold Version structs"),
+        };
+        let field = |name: &str| {
+            fields
+                .iter()
+                .find(|f| match f.member {
+                    syn::Member::Named(ref ident) => ident == name,
+                    _ => false,
+                })
+                .map(|f| quote_tokens(&f.expr))
+                .unwrap_or_default()
+        };
+        let name = field("name").trim_matches('"').to_owned();
+        let changes = field("changes");
+        let deprecated = field("sunset").starts_with("Some");
+
+        let changed = versions
+            .items
+            .iter()
+            .filter_map(|item| match *item {
+                Item::Fn(ref f) if f.ident == changes => {
+                    let mut exprs = Vec::new();
+                    expressions(&f.block, &mut exprs);
+                    Some(
+                        exprs
+                            .into_iter()
+                            .filter_map(route_list)
+                            .flatten()
+                            .collect::<Vec<_>>(),
+                    )
+                }
+                _ => None,
+            })
+            .next()
+            .unwrap_or_default();
+        let replaced = changed.iter().filter_map(|r| key(r)).collect::<Vec<_>>();
+        routes.retain(|r| key(r).map_or(true, |k| !replaced.contains(&k)));
+        routes.extend(changed);
+
+        for &(ref module, ref handler) in &routes {
+            mounted.push(Mounted {
+                base: format!("/{}", name),
+                module: module.clone(),
+                name: handler.clone(),
+                deprecated,
+                prefix: if i == 0 {
+                    String::new()
+                } else {
+                    format!("{}_", name)
+                },
+            });
+        }
+    }
+    mounted
+}
+
+fn collect_handlers(dir: &Path) -> BTreeMap<(String, String), Handler> {
+    let mut files = Vec::new();
+    rust_files(dir, &mut files);
+
+    let mut handlers = BTreeMap::new();
+    for path in files {
+        let module = path.file_stem().unwrap().to_string_lossy().into_owned();
+        for item in parse(&path).items {
+            if let Item::Fn(f) = item {
+                if route_attr(&f.attrs).is_some() {
+                    handlers.insert(
+                        (module.clone(), f.ident.to_string()),
+                        Handler {
+                            module: module.clone(),
+                            item: f,
+                        },
+                    );
+                }
+            }
+        }
+    }
+    handlers
+}
+
+///
+/// Attributes
+///
+
+/// Whether a type is sent or received, as JSON or as a query string
+fn is_described(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|a| {
+        a.path.is_ident("derive")
+            && match a.parse_meta() {
+                Ok(Meta::List(list)) => list.nested.iter().any(|n| match *n {
+                    NestedMeta::Meta(ref m) => {
+                        m.name() == "Serialize"
+                            || m.name() == "Deserialize"
+                            || m.name() == "FromForm"
+                    }
+                    _ => false,
+                }),
+                _ => false,
+            }
+    })
+}
+
+/// Words inside `#[serde(...)]`, like `default` or `rename = "..."`
+fn serde_args(attrs: &[Attribute]) -> Vec<Meta> {
+    attrs
+        .iter()
+        .filter(|a| a.path.is_ident("serde"))
+        .filter_map(|a| match a.parse_meta() {
+            Ok(Meta::List(list)) => Some(list.nested.into_iter().filter_map(|n| match n {
+                NestedMeta::Meta(m) => Some(m),
+                _ => None,
+            })),
+            _ => None,
+        })
+        .flatten()
+        .collect()
+}
+
+fn has_serde_word(attrs: &[Attribute], word: &str) -> bool {
+    serde_args(attrs).iter().any(|m| match *m {
+        Meta::Word(ref w) => w == word,
+        _ => false,
+    })
+}
+
+fn serde_value(attrs: &[Attribute], name: &str) -> Option<String> {
+    serde_args(attrs)
+        .into_iter()
+        .filter_map(|m| match m {
+            Meta::NameValue(ref nv) if nv.ident == name => match nv.lit {
+                Lit::Str(ref s) => Some(s.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .next()
+}
+
+fn doc(attrs: &[Attribute]) -> Option<String> {
+    let lines = attrs
+        .iter()
+        .filter(|a| a.path.is_ident("doc"))
+        .filter_map(|a| match a.parse_meta() {
+            Ok(Meta::NameValue(nv)) => match nv.lit {
+                Lit::Str(s) => Some(s.value().trim().to_owned()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect::<Vec<String>>();
+
+    // Paragraphs become lines, and lines within one are joined
+    let text = lines
+        .split(|l| l.is_empty())
+        .map(|p| p.join(" "))
+        .filter(|p| !p.is_empty())
+        .collect::<Vec<String>>()
+        .join("\n\n");
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+/// The method, path and arguments of a route attribute
+fn route_attr(attrs: &[Attribute]) -> Option<(String, String, Option<String>)> {
+    attrs
+        .iter()
+        .filter_map(|a| {
+            let method = a.path.segments.last()?.value().ident.to_string();
+            if !ROUTE_METHODS.contains(&method.as_str()) {
+                return None;
+            }
+            let list = match a.parse_meta() {
+                Ok(Meta::List(list)) => list,
+                _ => return None,
+            };
+
+            let mut uri = None;
+            let mut data = None;
+            for nested in list.nested {
+                match nested {
+                    NestedMeta::Literal(Lit::Str(s)) => uri = Some(s.value()),
+                    NestedMeta::Meta(Meta::NameValue(ref nv)) if nv.ident == "data" => {
+                        if let Lit::Str(ref s) = nv.lit {
+                            data =
+                                Some(s.value().trim_matches(|c| c == '<' || c == '>').to_owned());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            uri.map(|uri| (method, uri, data))
+        })
+        .next()
+}
+
+///
+/// Types
+///
+
+fn last_ident(path: &syn::Path) -> Option<String> {
+    path.segments.last().map(|s| s.value().ident.to_string())
+}
+
+fn type_name(ty: &Type) -> Option<String> {
+    match *ty {
+        Type::Path(ref p) => last_ident(&p.path),
+        Type::Reference(ref r) => type_name(&r.elem),
+        _ => None,
+    }
+}
+
+/// The type arguments of a type like `Option<T>` or `HashMap<K, V>`
+fn type_args(ty: &Type) -> Vec<&Type> {
+    let segment = match *ty {
+        Type::Path(ref p) => p.path.segments.last().map(|s| s.into_value()),
+        Type::Reference(ref r) => return type_args(&r.elem),
+        _ => None,
+    };
+    match segment.map(|s| &s.arguments) {
+        Some(&PathArguments::AngleBracketed(ref args)) => args
+            .args
+            .iter()
+            .filter_map(|a| match *a {
+                GenericArgument::Type(ref t) => Some(t),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Whether no variant of an enum holds data
+fn is_unit_only(e: &syn::DataEnum) -> bool {
+    e.variants.iter().all(|v| v.fields.iter().count() == 0)
+}
+
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+fn nullable(schema: Value) -> Value {
+    if schema.get("$ref").is_some() {
+        json!({ "allOf": [schema], "nullable": true })
+    } else {
+        let mut schema = schema;
+        if let Some(map) = schema.as_object_mut() {
+            map.insert("nullable".to_owned(), Value::Bool(true));
+        }
+        schema
+    }
+}
+
+struct Schemas<'a> {
+    types: &'a BTreeMap<String, TypeDef>,
+    /// Names of type parameters in the type being described
+    generics: Vec<String>,
+}
+
+impl<'a> Schemas<'a> {
+    /// Schema of a field's or argument's type
+    fn of(&self, ty: &Type) -> Value {
+        let name = match type_name(ty) {
+            Some(name) => name,
+            None => match *ty {
+                Type::Tuple(ref t) if t.elems.is_empty() => return json!({ "nullable": true }),
+                _ => return json!({}),
+            },
+        };
+        let args = type_args(ty);
+        let arg = |i: usize| args.get(i).map_or(json!({}), |t| self.of(t));
+
+        match name.as_str() {
+            "i8" | "i16" | "i32" | "u8" | "u16" | "u32" => {
+                json!({ "type": "integer", "format": "int32" })
+            }
+            "i64" | "u64" | "isize" | "usize" => json!({ "type": "integer", "format": "int64" }),
+            "f32" => json!({ "type": "number", "format": "float" }),
+            "f64" => json!({ "type": "number", "format": "double" }),
+            "bool" => json!({ "type": "boolean" }),
+            "String" | "str" | "char" => json!({ "type": "string" }),
+            "NaiveDateTime" => json!({ "type": "string", "format": "date-time" }),
+            "NaiveDate" => json!({ "type": "string", "format": "date" }),
+            "Value" | "InputValue" => json!({}),
+            "Option" => nullable(arg(0)),
+            "Vec" | "HashSet" | "BTreeSet" => json!({ "type": "array", "items": arg(0) }),
+            "HashMap" | "BTreeMap" => json!({ "type": "object", "additionalProperties": arg(1) }),
+            "Box" | "Cow" | "Json" | "Form" => arg(0),
+            _ if self.generics.contains(&name) => json!({}),
+            _ if self.types.contains_key(&name) => schema_ref(&name),
+            _ => {
+                println!("cargo:warning=No schema for {}", name);
+                json!({})
+            }
+        }
+    }
+
+    /// Schema of a type as a query parameter, where custom types are
+    /// parsed from strings
+    fn of_form_value(&self, ty: &Type) -> Value {
+        let name = type_name(ty).unwrap_or_default();
+        if name == "Option" {
+            return type_args(ty)
+                .first()
+                .map_or(json!({}), |t| self.of_form_value(t));
+        }
+        let def = match self.types.get(&name) {
+            Some(def) if def.form_values.is_some() => def,
+            _ => return self.of(ty),
+        };
+
+        // The values an enum's impl matches are all it accepts, unless a
+        // variant holds something parsed from the rest
+        let values = def.form_values.clone().unwrap_or_default();
+        let schema = match def.item.data {
+            Data::Enum(ref e) if !values.is_empty() && is_unit_only(e) => {
+                json!({ "type": "string", "enum": values })
+            }
+            _ => json!({ "type": "string" }),
+        };
+        match doc(&def.item.attrs) {
+            Some(description) => describe(schema, description),
+            None => schema,
+        }
+    }
+
+    fn properties(&self, fields: &Fields) -> (Map<String, Value>, Vec<String>, Vec<Value>) {
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        let mut flattened = Vec::new();
+
+        for field in fields.iter() {
+            let attrs = &field.attrs;
+            if has_serde_word(attrs, "skip") || has_serde_word(attrs, "skip_serializing") {
+                continue;
+            }
+            if has_serde_word(attrs, "flatten") {
+                flattened.push(self.of(&field.ty));
+                continue;
+            }
+
+            let name = serde_value(attrs, "rename").unwrap_or_else(|| {
+                field
+                    .ident
+                    .as_ref()
+                    .map_or(String::new(), |i| i.to_string())
+            });
+            let mut schema = self.of(&field.ty);
+            if let Some(description) = doc(attrs) {
+                schema = describe(schema, description);
+            }
+            if type_name(&field.ty).map_or(true, |t| t != "Option")
+                && !has_serde_word(attrs, "default")
+            {
+                required.push(name.clone());
+            }
+            properties.insert(name, schema);
+        }
+        (properties, required, flattened)
+    }
+
+    fn object(&self, fields: &Fields) -> Value {
+        let (properties, required, flattened) = self.properties(fields);
+        let mut object = json!({ "type": "object", "properties": properties });
+        if !required.is_empty() {
+            object["required"] = json!(required);
+        }
+        if flattened.is_empty() {
+            object
+        } else {
+            let mut parts = flattened;
+            parts.push(object);
+            json!({ "allOf": parts })
+        }
+    }
+
+    /// Schema of a serde type, for `components`
+    fn definition(&self, def: &TypeDef) -> Value {
+        let item = &def.item;
+        let schema = match item.data {
+            Data::Struct(ref s) => match s.fields {
+                Fields::Unnamed(ref f) if f.unnamed.len() == 1 => self.of(&f.unnamed[0].ty),
+                ref fields => self.object(fields),
+            },
+            Data::Enum(ref e) => {
+                let tag = serde_value(&item.attrs, "tag");
+                let name_of = |v: &syn::Variant| {
+                    serde_value(&v.attrs, "rename").unwrap_or_else(|| v.ident.to_string())
+                };
+
+                if tag.is_none() && is_unit_only(e) {
+                    let names = e.variants.iter().map(name_of).collect::<Vec<String>>();
+                    json!({ "type": "string", "enum": names })
+                } else {
+                    let variants = e
+                        .variants
+                        .iter()
+                        .map(|v| {
+                            let mut schema = match tag {
+                                Some(ref tag) => {
+                                    let mut object = self.object(&v.fields);
+                                    object["properties"][tag] =
+                                        json!({ "type": "string", "enum": [name_of(v)] });
+                                    let mut required =
+                                        object["required"].as_array().cloned().unwrap_or_default();
+                                    required.push(json!(tag));
+                                    object["required"] = json!(required);
+                                    object
+                                }
+                                None if v.fields.iter().count() == 0 => {
+                                    json!({ "type": "string", "enum": [name_of(v)] })
+                                }
+                                None => json!({
+                                    "type": "object",
+                                    "properties": { name_of(v): self.object(&v.fields) },
+                                    "required": [name_of(v)],
+                                }),
+                            };
+                            if let Some(description) = doc(&v.attrs) {
+                                schema = describe(schema, description);
+                            }
+                            schema
+                        })
+                        .collect::<Vec<Value>>();
+                    json!({ "oneOf": variants })
+                }
+            }
+            Data::Union(_) => json!({}),
+        };
+
+        match doc(&item.attrs) {
+            Some(description) => describe(schema, description),
+            None => schema,
+        }
+    }
+}
+
+fn describe(schema: Value, description: String) -> Value {
+    let mut schema = if schema.get("$ref").is_some() {
+        json!({ "allOf": [schema] })
+    } else {
+        schema
+    };
+    if let Some(map) = schema.as_object_mut() {
+        map.insert("description".to_owned(), Value::String(description));
+    }
+    schema
+}
+
+/// Names of the component schemas a schema refers to
+fn refs(schema: &Value, found: &mut BTreeSet<String>) {
+    match *schema {
+        Value::Object(ref map) => {
+            if let Some(Value::String(r)) = map.get("$ref") {
+                found.insert(r.trim_start_matches("#/components/schemas/").to_owned());
+            }
+            for v in map.values() {
+                refs(v, found);
+            }
+        }
+        Value::Array(ref items) => {
+            for v in items {
+                refs(v, found);
+            }
+        }
+        _ => {}
+    }
+}
+
+///
+/// Operations
+///
+
+/// Rocket's `<param>` and `<param..>` as OpenAPI's `{param}`
+fn openapi_path(base: &str, uri: &str) -> String {
+    let path = uri.split('?').next().unwrap_or("");
+    let path = path
+        .split('/')
+        .map(|s| {
+            if s.starts_with('<') && s.ends_with('>') {
+                format!(
+                    "{{{}}}",
+                    s.trim_matches(|c| c == '<' || c == '>')
+                        .trim_end_matches("..")
+                )
+            } else {
+                s.to_owned()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("/");
+    let base = base.trim_end_matches('/');
+    format!("{}{}", base, path)
+}
+
+/// Names in a route's path and query, like `deal_id` in `/deals/<deal_id>`
+fn dynamic(part: &str) -> Vec<(String, bool)> {
+    part.split(|c| c == '/' || c == '&')
+        .filter(|s| s.starts_with('<') && s.ends_with('>'))
+        .map(|s| {
+            let inner = s.trim_matches(|c| c == '<' || c == '>');
+            (
+                inner.trim_end_matches("..").to_owned(),
+                inner.ends_with(".."),
+            )
+        })
+        .collect()
+}
+
+fn content_types(handler: &ItemFn) -> Vec<&'static str> {
+    let body = quote_tokens(&*handler.block);
+    let mut types = Vec::new();
+    if body.contains("ContentType :: PDF") {
+        types.push("application/pdf");
+    }
+    if body.contains("ContentType :: CSV") {
+        types.push("text/csv");
+    }
+    if types.is_empty() {
+        types.push("application/octet-stream");
+    }
+    types
+}
+
+fn operation(schemas: &Schemas, handler: &Handler, uri: &str, data: Option<String>) -> Value {
+    let item = &handler.item;
+    let args = item
+        .decl
+        .inputs
+        .iter()
+        .filter_map(|a| match *a {
+            FnArg::Captured(ref c) => match c.pat {
+                Pat::Ident(ref p) => Some((p.ident.to_string(), &c.ty)),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect::<Vec<(String, &Type)>>();
+    let arg = |name: &str| args.iter().find(|a| a.0 == name).map(|a| a.1);
+
+    let mut op = Map::new();
+    op.insert("operationId".to_owned(), json!(item.ident.to_string()));
+    op.insert("tags".to_owned(), json!([handler.module]));
+    if let Some(text) = doc(&item.attrs) {
+        let mut paragraphs = text.splitn(2, "\n\n");
+        op.insert("summary".to_owned(), json!(paragraphs.next()));
+        if let Some(rest) = paragraphs.next() {
+            op.insert("description".to_owned(), json!(rest));
+        }
+    }
+
+    // Parameters
+    let mut parameters = Vec::new();
+    let mut parts = uri.splitn(2, '?');
+    for (name, _) in dynamic(parts.next().unwrap_or("")) {
+        let schema = arg(&name).map_or(json!({ "type": "string" }), |t| schemas.of(t));
+        parameters.push(json!({
+            "name": name,
+            "in": "path",
+            "required": true,
+            "schema": schema,
+        }));
+    }
+    for (name, many) in dynamic(parts.next().unwrap_or("")) {
+        let ty = match arg(&name) {
+            Some(ty) => ty,
+            None => continue,
+        };
+        let optional = type_name(ty).map_or(false, |t| t == "Option");
+        if !many {
+            parameters.push(json!({
+                "name": name,
+                "in": "query",
+                "required": !optional,
+                "schema": schemas.of_form_value(ty),
+            }));
+            continue;
+        }
+
+        // A form's fields are each a parameter
+        let form = match type_name(ty).as_ref().map(|s| s.as_str()) {
+            Some("Option") => type_args(ty)
+                .first()
+                .and_then(|t| type_args(t).first().cloned()),
+            _ => type_args(ty).first().cloned(),
+        };
+        let def = form.and_then(type_name).and_then(|n| schemas.types.get(&n));
+        if let Some(&TypeDef {
+            item:
+                DeriveInput {
+                    data: Data::Struct(ref s),
+                    ..
+                },
+            ..
+        }) = def
+        {
+            for field in s.fields.iter() {
+                let field_optional =
+                    type_name(&field.ty).map_or(false, |t| t == "Option") || optional;
+                let mut parameter = json!({
+                    "name": field.ident.as_ref().map(|i| i.to_string()),
+                    "in": "query",
+                    "required": !field_optional,
+                    "schema": schemas.of_form_value(&field.ty),
+                });
+                if let Some(description) = doc(&field.attrs) {
+                    parameter["description"] = json!(description);
+                }
+                parameters.push(parameter);
+            }
+        }
+    }
+    if !parameters.is_empty() {
+        op.insert("parameters".to_owned(), json!(parameters));
+    }
+
+    // Body
+    if let Some(ty) = data.as_ref().and_then(|d| arg(d)) {
+        let name = type_name(ty).unwrap_or_default();
+        let (required, ty) = if name == "Option" {
+            (false, type_args(ty).first().cloned().unwrap_or(ty))
+        } else {
+            (true, ty)
+        };
+        let content = if type_name(ty).map_or(false, |t| t == "Data") {
+            json!({ "*/*": { "schema": { "type": "string", "format": "binary" } } })
+        } else {
+            json!({ "application/json": { "schema": schemas.of(ty) } })
+        };
+        op.insert(
+            "requestBody".to_owned(),
+            json!({ "required": required, "content": content }),
+        );
+    }
+
+    // Responses
+    let returns = match item.decl.output {
+        ReturnType::Type(_, ref ty) => match type_name(ty) {
+            Some(ref name) if name == "Result" => type_args(ty).first().and_then(|t| type_name(t)),
+            name => name,
+        },
+        ReturnType::Default => None,
+    }
+    .unwrap_or_default();
+    let success = match returns.as_str() {
+        "ApiResponse" => {
+            let data = match item.decl.output {
+                ReturnType::Type(_, ref ty) => type_args(ty).first().map(|t| schemas.of(t)),
+                ReturnType::Default => None,
+            };
+            json!({
+                "200": {
+                    "description": "Success",
+                    "content": { "application/json": { "schema": {
+                        "allOf": [
+                            schema_ref("Payload"),
+                            { "properties": { "data": data.unwrap_or(json!({})) } },
+                        ],
+                    } } },
+                },
+            })
+        }
+        "FileResponse" => {
+            let content = content_types(item)
+                .into_iter()
+                .map(|t| {
+                    (
+                        t.to_owned(),
+                        json!({ "schema": { "type": "string", "format": "binary" } }),
+                    )
+                })
+                .collect::<Map<String, Value>>();
+            json!({ "200": { "description": "The file", "content": content } })
+        }
+        "AttachmentResponse" => json!({
+            "200": {
+                "description": "The file, as an attachment",
+                "content": { "*/*": { "schema": { "type": "string", "format": "binary" } } },
+            },
+        }),
+        "EventStreamResponse" => json!({
+            "200": {
+                "description": "Server-sent events",
+                "content": { "text/event-stream": { "schema": { "type": "string" } } },
+            },
+        }),
+        "GraphQLResponse" => json!({
+            "200": {
+                "description": "The query's `data` and `errors`",
+                "content": { "application/json": { "schema": { "type": "object" } } },
+            },
+            "400": { "description": "The query could not be run" },
+        }),
+        "Redirect" => json!({ "303": { "description": "Redirect" } }),
+        _ => json!({ "200": { "description": "Success" } }),
+    };
+    let mut responses = success.as_object().cloned().unwrap_or_default();
+    if returns != "Content" {
+        responses.insert(
+            "default".to_owned(),
+            json!({ "$ref": "#/components/responses/Error" }),
+        );
+    }
+    op.insert("responses".to_owned(), Value::Object(responses));
+
+    // Security
+    let guards = args
+        .iter()
+        .filter_map(|a| type_name(a.1))
+        .collect::<Vec<String>>();
+    // Guards wrapped in an `Option` let requests through without a key
+    let optional = args
+        .iter()
+        .filter(|a| type_name(a.1).map_or(false, |n| n == "Option"))
+        .filter_map(|a| type_args(a.1).first().and_then(|t| type_name(t)))
+        .collect::<Vec<String>>();
+    let security = if guards.iter().any(|g| g == "StreamUser") {
+        json!([{ "ApiKey": [] }, { "StreamToken": [] }])
+    } else if guards.iter().any(|g| g == "CurrentUser" || g == "ApiKey") {
+        json!([{ "ApiKey": [] }])
+    } else if optional.iter().any(|g| g == "CurrentUser") {
+        json!([{}, { "ApiKey": [] }])
+    } else {
+        json!([])
+    };
+    op.insert("security".to_owned(), security);
+
+    Value::Object(op)
+}
+
+fn main() {
+    let root = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join("src");
+
+    let mut files = Vec::new();
+    rust_files(&root, &mut files);
+    let parsed = files.iter().map(|f| parse(f)).collect::<Vec<syn::File>>();
+    let types = collect_types(&parsed);
+    let handlers = collect_handlers(&root.join("web").join("controllers"));
+    let web = root.join("web");
+    let mut mounted = unversioned_routes(&parse(&web.join("mod.rs")));
+    mounted.extend(versioned_routes(
+        &parse(&web.join("versions.rs")),
+        &handlers,
+    ));
+
+    let mut paths = Map::new();
+    let mut used = BTreeSet::new();
+    let mut operation_ids = BTreeSet::new();
+    for route in mounted {
+        if UNDOCUMENTED.contains(&(route.module.as_str(), route.name.as_str())) {
+            continue;
+        }
+        let handler = match handlers.get(&(route.module.clone(), route.name.clone())) {
+            Some(h) => h,
+            None => {
+                println!(
+                    "cargo:warning=No handler {}::{} in web::controllers for a mounted route",
+                    route.module, route.name
+                );
+                continue;
+            }
+        };
+        let (method, uri, data) = route_attr(&handler.item.attrs).unwrap();
+        let generics = handler
+            .item
+            .decl
+            .generics
+            .type_params()
+            .map(|p| p.ident.to_string())
+            .collect();
+        let schemas = Schemas {
+            types: &types,
+            generics,
+        };
+
+        let mut op = operation(&schemas, handler, &uri, data);
+        let id = format!("{}{}", route.prefix, handler.item.ident);
+        op["operationId"] = if operation_ids.insert(id.clone()) {
+            json!(id)
+        } else {
+            json!(format!(
+                "{}{}_{}",
+                route.prefix, handler.module, handler.item.ident
+            ))
+        };
+        if route.deprecated {
+            op["deprecated"] = json!(true);
+        }
+        refs(&op, &mut used);
+        let entry = paths
+            .entry(openapi_path(&route.base, &uri))
+            .or_insert_with(|| json!({}));
+        entry[method] = op;
+    }
+
+    // Component schemas, following references from the operations
+    used.insert("Payload".to_owned());
+    let mut components = Map::new();
+    let mut pending = used.into_iter().collect::<Vec<String>>();
+    while let Some(name) = pending.pop() {
+        if components.contains_key(&name) {
+            continue;
+        }
+        let def = match types.get(&name) {
+            Some(def) => def,
+            None => continue,
+        };
+        let schemas = Schemas {
+            types: &types,
+            generics: def
+                .item
+                .generics
+                .type_params()
+                .map(|p| p.ident.to_string())
+                .collect(),
+        };
+        let schema = schemas.definition(def);
+        let mut found = BTreeSet::new();
+        refs(&schema, &mut found);
+        pending.extend(found);
+        components.insert(name, schema);
+    }
+
+    // Failed requests answer with the usual payload, holding no data
+    let error = json!({
+        "description": "The request failed",
+        "content": { "application/json": { "schema": {
+            "allOf": [
+                schema_ref("Payload"),
+                { "properties": { "data": { "type": "string", "nullable": true } } },
+            ],
+        } } },
+    });
+    let security = json!({
+        "ApiKey": { "type": "apiKey", "in": "header", "name": "X-API-KEY" },
+        // Only for event streams, as browsers can't set their headers
+        "StreamToken": { "type": "apiKey", "in": "query", "name": "token" },
+    });
+    let document = json!({
+        "openapi": "3.0.2",
+        "info": {
+            "title": "Dwello API",
+            "version": env::var("CARGO_PKG_VERSION").unwrap(),
+        },
+        "paths": paths,
+        "components": {
+            "schemas": components,
+            "responses": { "Error": error },
+            "securitySchemes": security,
+        },
+    });
+
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap()).join("openapi.json");
+    fs::write(&out, serde_json::to_string_pretty(&document).unwrap())
+        .expect("Failed to write openapi.json");
+}
@@ -1,5 +1,6 @@
-module Api exposing (ApiData(..), ApiResponse, ValidationError, decodeApiResponse)
+module Api exposing (ApiData(..), ApiResponse, ValidationError, decodeApiResponse, sendRequest)
 
+import Http
 import Json.Decode as JD
 import Json.Decode.Pipeline as JDP
 import RemoteData exposing (WebData)
@@ -47,3 +48,28 @@ decodeValidationErrors =
                 |> JDP.required "message" JD.string
     in
     JD.field "validation_errors" (JD.list dec)
+
+
+{-| Send a request. The API answers validation failures with a 422, and
+clashes with stored data like a taken email with a 409. Both carry errors for
+the form rather than meaning the request failed.
+-}
+sendRequest : Http.Request (ApiData a) -> Cmd (ApiResponse a)
+sendRequest =
+    Http.send (recoverValidationErrors >> RemoteData.fromResult)
+
+
+recoverValidationErrors : Result Http.Error (ApiData a) -> Result Http.Error (ApiData a)
+recoverValidationErrors result =
+    case result of
+        Err (Http.BadStatus response) ->
+            if response.status.code == 422 || response.status.code == 409 then
+                JD.decodeString decodeValidationErrors response.body
+                    |> Result.map ValidationErrors
+                    |> Result.mapError (\_ -> Http.BadStatus response)
+
+            else
+                result
+
+        _ ->
+            result
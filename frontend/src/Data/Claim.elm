@@ -0,0 +1,121 @@
+module Data.Claim exposing
+    ( ContactMethod(..)
+    , DealClaim
+    , ResponseType(..)
+    , SellerResponse
+    , contactMethodToString
+    , decodeDealClaim
+    , decodeSellerResponse
+    , responseTypeToString
+    , stringToContactMethod
+    , stringToResponseType
+    )
+
+import Json.Decode as JD exposing (Decoder)
+import Json.Decode.Pipeline as JDP
+
+
+{-| What a homeowner sees of a deal from the code on their letter
+-}
+type alias DealClaim =
+    { access_code : String
+    , address : String
+    , buyer_name : String
+    , claimed : Bool
+    }
+
+
+type alias SellerResponse =
+    { id : Int
+    , response : ResponseType
+    }
+
+
+type ResponseType
+    = Interested
+    | NotInterested
+    | CallMe
+
+
+type ContactMethod
+    = Phone
+    | Text
+    | Email
+    | Mail
+
+
+responseTypeToString : ResponseType -> String
+responseTypeToString responseType =
+    case responseType of
+        Interested ->
+            "Interested"
+
+        NotInterested ->
+            "NotInterested"
+
+        CallMe ->
+            "CallMe"
+
+
+stringToResponseType : String -> ResponseType
+stringToResponseType string =
+    case string of
+        "NotInterested" ->
+            NotInterested
+
+        "CallMe" ->
+            CallMe
+
+        _ ->
+            Interested
+
+
+contactMethodToString : ContactMethod -> String
+contactMethodToString contactMethod =
+    case contactMethod of
+        Phone ->
+            "Phone"
+
+        Text ->
+            "Text"
+
+        Email ->
+            "Email"
+
+        Mail ->
+            "Mail"
+
+
+stringToContactMethod : String -> Maybe ContactMethod
+stringToContactMethod string =
+    case string of
+        "Phone" ->
+            Just Phone
+
+        "Text" ->
+            Just Text
+
+        "Email" ->
+            Just Email
+
+        "Mail" ->
+            Just Mail
+
+        _ ->
+            Nothing
+
+
+decodeDealClaim : Decoder DealClaim
+decodeDealClaim =
+    JD.succeed DealClaim
+        |> JDP.required "access_code" JD.string
+        |> JDP.required "address" JD.string
+        |> JDP.required "buyer_name" JD.string
+        |> JDP.required "claimed" JD.bool
+
+
+decodeSellerResponse : Decoder SellerResponse
+decodeSellerResponse =
+    JD.succeed SellerResponse
+        |> JDP.required "id" JD.int
+        |> JDP.required "response" (JD.map stringToResponseType JD.string)
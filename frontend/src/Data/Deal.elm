@@ -18,6 +18,9 @@ type alias Deal =
 type DealStatus
     = Initialized
     | MailerSent
+    | SellerInterested
+    | SellerDeclined
+    | UnderContract
 
 
 statusToString : DealStatus -> String
@@ -29,6 +32,15 @@ statusToString dealStatus =
         MailerSent ->
             "MailerSent"
 
+        SellerInterested ->
+            "SellerInterested"
+
+        SellerDeclined ->
+            "SellerDeclined"
+
+        UnderContract ->
+            "UnderContract"
+
 
 stringToStatus : String -> DealStatus
 stringToStatus string =
@@ -39,6 +51,15 @@ stringToStatus string =
         "MailerSent" ->
             MailerSent
 
+        "SellerInterested" ->
+            SellerInterested
+
+        "SellerDeclined" ->
+            SellerDeclined
+
+        "UnderContract" ->
+            UnderContract
+
         _ ->
             Initialized
 
@@ -67,6 +88,15 @@ decodeDealStatus =
                     "MailerSent" ->
                         JD.succeed MailerSent
 
+                    "SellerInterested" ->
+                        JD.succeed SellerInterested
+
+                    "SellerDeclined" ->
+                        JD.succeed SellerDeclined
+
+                    "UnderContract" ->
+                        JD.succeed UnderContract
+
                     _ ->
                         JD.fail "Invalid DealStatus"
             )
@@ -10,6 +10,7 @@ import Browser.Navigation as BN exposing (Key)
 import Config exposing (Config)
 import Global exposing (Global)
 import Main.Msg exposing (Msg(..))
+import Page.Claim
 import Page.Index
 import Page.Login
 import Page.Register
@@ -28,6 +29,7 @@ type Page
     | Register Page.Register.Model
     | UserDetail Page.UserDetail.Model
     | UserProfileForm Page.UserProfileForm.Model
+    | Claim Page.Claim.Model
     | NotFound
 
 
@@ -82,6 +84,11 @@ initPage url model =
         ( Just Route.Logout, _ ) ->
             ( model, logout () )
 
+        -- Homeowners come here from their letter, signed in or not
+        ( Just (Route.Claim { code }), _ ) ->
+            Page.Claim.init model.global code
+                |> updatePage Claim ClaimMsg model
+
         -- Redirect to login
         ( _, False ) ->
             ( model, BN.load (UB.absolute [ "login" ] []) )
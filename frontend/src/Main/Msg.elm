@@ -2,6 +2,7 @@ module Main.Msg exposing (Msg(..))
 
 import Browser exposing (UrlRequest)
 import Global
+import Page.Claim
 import Page.Index
 import Page.Login
 import Page.Register
@@ -19,3 +20,4 @@ type Msg
     | RegisterMsg Page.Register.Msg
     | UserDetailMsg Page.UserDetail.Msg
     | UserProfileFormMsg Page.UserProfileForm.Msg
+    | ClaimMsg Page.Claim.Msg
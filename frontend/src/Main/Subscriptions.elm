@@ -2,6 +2,7 @@ module Main.Subscriptions exposing (subscriptions)
 
 import Main.Model exposing (Model, Page(..))
 import Main.Msg exposing (Msg(..))
+import Page.Claim
 import Page.Index
 import Page.Login
 import Page.Register
@@ -32,5 +33,9 @@ subscriptions model =
             Page.UserProfileForm.subscriptions model.global m
                 |> Sub.map UserProfileFormMsg
 
+        Claim m ->
+            Page.Claim.subscriptions model.global m
+                |> Sub.map ClaimMsg
+
         NotFound ->
             Sub.none
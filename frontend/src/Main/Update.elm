@@ -5,6 +5,7 @@ import Browser.Navigation as BN
 import Global
 import Main.Model exposing (Model, Page(..), initPage, updatePage)
 import Main.Msg exposing (Msg(..))
+import Page.Claim
 import Page.Index
 import Page.Login
 import Page.Register
@@ -44,6 +45,10 @@ update msg model =
             Page.Register.update model.global lmsg lmodel
                 |> updatePage Register RegisterMsg model
 
+        ( ClaimMsg cmsg, Claim cmodel ) ->
+            Page.Claim.update model.global cmsg cmodel
+                |> updatePage Claim ClaimMsg model
+
         -- Protected routes
         ( IndexMsg imsg, Index indexModel ) ->
             Page.Index.update model.global imsg indexModel
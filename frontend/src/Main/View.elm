@@ -6,6 +6,7 @@ import Html exposing (..)
 import Html.Attributes exposing (class, href, src, style, title, type_)
 import Main.Model exposing (Model, Page(..))
 import Main.Msg exposing (Msg(..))
+import Page.Claim
 import Page.Index
 import Page.Login
 import Page.Register
@@ -57,6 +58,9 @@ view model =
         UserProfileForm lmodel ->
             viewPage UserProfileFormMsg lmodel Page.UserProfileForm.view
 
+        Claim cmodel ->
+            viewPage ClaimMsg cmodel Page.Claim.view
+
         NotFound ->
             { title = "Not Found"
             , body = [ text ":(" ]
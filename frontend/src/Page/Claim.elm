@@ -0,0 +1,381 @@
+module Page.Claim exposing
+    ( Model
+    , Msg(..)
+    , init
+    , subscriptions
+    , update
+    , view
+    )
+
+import Api exposing (ApiData(..), ApiResponse)
+import Browser exposing (Document)
+import Config exposing (Config)
+import Data.Claim as Claim exposing (ContactMethod, DealClaim, ResponseType(..), SellerResponse)
+import Data.Deal exposing (Deal)
+import Global exposing (Global)
+import Html exposing (..)
+import Html.Attributes exposing (..)
+import Html.Events exposing (onClick, onInput, onSubmit)
+import Http
+import RemoteData as RD exposing (RemoteData(..))
+import Request.Claim exposing (SellerResponseInput)
+import Route
+import View exposing (Toast(..), toastFromApiResponse, viewToast)
+
+
+
+-- COMMANDS
+
+
+getClaim : Config -> String -> Cmd Msg
+getClaim config code =
+    Request.Claim.getClaim config code
+        |> Api.sendRequest
+        |> Cmd.map GotClaim
+
+
+claimDeal : Config -> String -> Cmd Msg
+claimDeal config code =
+    Request.Claim.claimDeal config code
+        |> Api.sendRequest
+        |> Cmd.map DealClaimed
+
+
+submitResponse : Config -> String -> SellerResponseInput -> Cmd Msg
+submitResponse config code input =
+    Request.Claim.submitResponse config code input
+        |> Api.sendRequest
+        |> Cmd.map ResponseSubmitted
+
+
+
+-- MODEL
+
+
+type alias Model =
+    { code : String
+    , claimResponse : ApiResponse DealClaim
+    , claimDealResponse : ApiResponse Deal
+    , submitResponse : ApiResponse SellerResponse
+    , response : ResponseType
+    , askingPrice : String
+    , contactMethod : Maybe ContactMethod
+    , contact : String
+    , message : String
+    , toast : Toast
+    }
+
+
+init : Global -> String -> ( Model, Cmd Msg, Global.Msg )
+init global code =
+    ( { code = code
+      , claimResponse = Loading
+      , claimDealResponse = NotAsked
+      , submitResponse = NotAsked
+      , response = Interested
+      , askingPrice = ""
+      , contactMethod = Nothing
+      , contact = ""
+      , message = ""
+      , toast = Empty
+      }
+    , getClaim (Global.getConfig global) code
+    , Global.none
+    )
+
+
+
+-- UPDATE
+
+
+type
+    Msg
+    -- Got stuff
+    = GotClaim (ApiResponse DealClaim)
+    | DealClaimed (ApiResponse Deal)
+    | ResponseSubmitted (ApiResponse SellerResponse)
+      -- Do stuff to server
+    | ClaimDeal
+    | SubmitResponse
+      -- Do things locally
+    | Response String
+    | AskingPrice String
+    | ContactMethod String
+    | Contact String
+    | Message String
+
+
+update : Global -> Msg -> Model -> ( Model, Cmd Msg, Global.Msg )
+update global msg model =
+    case msg of
+        -- Responses
+        GotClaim response ->
+            ( { model | claimResponse = response }, Cmd.none, Global.none )
+
+        DealClaimed response ->
+            let
+                claimResponse =
+                    case ( response, model.claimResponse ) of
+                        ( Success (Data _), Success (Data claim) ) ->
+                            Success (Data { claim | claimed = True })
+
+                        _ ->
+                            model.claimResponse
+            in
+            ( { model
+                | claimDealResponse = response
+                , claimResponse = claimResponse
+                , toast = toastFromSubmit response "This home is now linked to your account"
+              }
+            , Cmd.none
+            , Global.none
+            )
+
+        ResponseSubmitted response ->
+            ( { model | submitResponse = response, toast = toastFromSubmit response "" }
+            , Cmd.none
+            , Global.none
+            )
+
+        -- Trigger requests
+        ClaimDeal ->
+            ( { model | claimDealResponse = Loading, toast = Empty }
+            , claimDeal (Global.getConfig global) model.code
+            , Global.none
+            )
+
+        SubmitResponse ->
+            let
+                input =
+                    { response = model.response
+                    , asking_price = String.toInt (String.filter Char.isDigit model.askingPrice)
+                    , contact_method = model.contactMethod
+                    , contact = model.contact
+                    , message = model.message
+                    }
+            in
+            ( { model | submitResponse = Loading, toast = Empty }
+            , submitResponse (Global.getConfig global) model.code input
+            , Global.none
+            )
+
+        -- Response form
+        Response v ->
+            ( { model | response = Claim.stringToResponseType v }, Cmd.none, Global.none )
+
+        AskingPrice v ->
+            ( { model | askingPrice = v }, Cmd.none, Global.none )
+
+        ContactMethod v ->
+            ( { model | contactMethod = Claim.stringToContactMethod v }, Cmd.none, Global.none )
+
+        Contact v ->
+            ( { model | contact = v }, Cmd.none, Global.none )
+
+        Message v ->
+            ( { model | message = v }, Cmd.none, Global.none )
+
+
+{-| Explain the errors a homeowner can do something about
+-}
+toastFromSubmit : ApiResponse a -> String -> Toast
+toastFromSubmit response success =
+    case response of
+        Success (Data _) ->
+            if success == "" then
+                Empty
+
+            else
+                Good success
+
+        Failure (Http.BadStatus r) ->
+            case r.status.code of
+                401 ->
+                    Bad "This home has been claimed. Login as its owner to respond."
+
+                403 ->
+                    Bad "This home has been claimed by another account"
+
+                429 ->
+                    Bad "Too many responses for this home, try again later"
+
+                _ ->
+                    toastFromApiResponse response
+
+        _ ->
+            toastFromApiResponse response
+
+
+
+-- SUBSCRIPTIONS
+
+
+subscriptions : Global -> Model -> Sub Msg
+subscriptions _ _ =
+    Sub.none
+
+
+
+-- VIEW
+
+
+view : Global -> Model -> Document Msg
+view global model =
+    { title = "Your home"
+    , body =
+        [ div [ class "container mx-auto max-w-md" ]
+            [ viewContent global model
+            ]
+        , viewToast model.toast
+        ]
+    }
+
+
+viewContent : Global -> Model -> Html Msg
+viewContent global model =
+    case model.claimResponse of
+        Success (Data claim) ->
+            div [ class "bg-white shadow rounded p-6" ]
+                [ h1 [ class "text-grey-darkest pb-2" ] [ text claim.address ]
+                , p [ class "text-grey-darker pb-4" ]
+                    [ text (claim.buyer_name ++ " would like to buy your home.") ]
+                , viewClaim global claim model
+                , viewRespond global claim model
+                ]
+
+        Loading ->
+            div [ class "spinner" ] []
+
+        NotAsked ->
+            div [] []
+
+        _ ->
+            div [ class "bg-white shadow rounded p-6" ]
+                [ h1 [ class "text-grey-darkest pb-2" ] [ text "Code not found" ]
+                , p [ class "text-grey-darker" ]
+                    [ text ("No home matches the code " ++ model.code ++ ". Check the code printed on your letter.") ]
+                ]
+
+
+viewClaim : Global -> DealClaim -> Model -> Html Msg
+viewClaim global claim model =
+    if claim.claimed || not (loggedIn global) then
+        text ""
+
+    else
+        case model.claimDealResponse of
+            Loading ->
+                div [ class "spinner" ] []
+
+            _ ->
+                div [ class "pb-4" ]
+                    [ button
+                        [ class "bg-indigo hover:bg-indigo-dark text-white font-bold py-2 px-4 rounded focus:outline-none focus:shadow-outline"
+                        , type_ "button"
+                        , onClick ClaimDeal
+                        ]
+                        [ text "This is my home" ]
+                    , formatValidationErrors model.claimDealResponse "access_code"
+                    ]
+
+
+viewRespond : Global -> DealClaim -> Model -> Html Msg
+viewRespond global claim model =
+    case model.submitResponse of
+        Success (Data _) ->
+            p [ class "text-grey-darkest" ]
+                [ text ("Thanks! " ++ claim.buyer_name ++ " will hear from you soon.") ]
+
+        _ ->
+            if claim.claimed && not (loggedIn global) then
+                p [ class "text-grey-darker" ]
+                    [ text "This home has been claimed. "
+                    , a [ class "no-underline text-indigo", Route.href Route.Login ] [ text "Login" ]
+                    , text " as its owner to respond."
+                    ]
+
+            else
+                viewResponseForm model
+
+
+viewResponseForm : Model -> Html Msg
+viewResponseForm model =
+    let
+        submit =
+            case model.submitResponse of
+                Loading ->
+                    div [ class "spinner" ] []
+
+                _ ->
+                    input [ class "bg-indigo hover:bg-indigo-dark text-white font-bold py-2 px-4 rounded focus:outline-none focus:shadow-outline", type_ "submit", value "Send" ] []
+    in
+    Html.form [ onSubmit SubmitResponse ]
+        [ viewField "Are you interested in selling?" "response" model.submitResponse <|
+            select [ class fieldClass, id "response", onInput Response ]
+                [ option [ value "Interested" ] [ text "Yes, I'm interested" ]
+                , option [ value "CallMe" ] [ text "Maybe, call me" ]
+                , option [ value "NotInterested" ] [ text "No, not now" ]
+                ]
+        , viewField "Asking price" "asking_price" model.submitResponse <|
+            input [ class fieldClass, id "asking_price", type_ "text", placeholder "Optional", value model.askingPrice, onInput AskingPrice ] []
+        , viewField "How should we reach you?" "contact_method" model.submitResponse <|
+            select [ class fieldClass, id "contact_method", onInput ContactMethod ]
+                [ option [ value "" ] [ text "Choose one" ]
+                , option [ value "Phone" ] [ text "Phone call" ]
+                , option [ value "Text" ] [ text "Text message" ]
+                , option [ value "Email" ] [ text "Email" ]
+                , option [ value "Mail" ] [ text "Mail" ]
+                ]
+        , viewField "Phone number or email" "contact" model.submitResponse <|
+            input [ class fieldClass, id "contact", type_ "text", value model.contact, onInput Contact ] []
+        , viewField "Message" "message" model.submitResponse <|
+            textarea [ class fieldClass, id "message", value model.message, onInput Message ] []
+        , div [ class "pt-2" ] [ submit ]
+        ]
+
+
+viewField : String -> String -> ApiResponse a -> Html Msg -> Html Msg
+viewField caption field response control =
+    div [ class "mb-4" ]
+        [ label [ class "block text-grey-darker text-sm font-bold mb-2", for field ]
+            [ text caption ]
+        , control
+        , formatValidationErrors response field
+        ]
+
+
+fieldClass : String
+fieldClass =
+    "shadow appearance-none border rounded w-full py-2 px-3 text-grey-darker leading-tight focus:outline-none focus:shadow-outline"
+
+
+loggedIn : Global -> Bool
+loggedIn global =
+    Global.getToken global /= ""
+
+
+getValidationErrors : ApiResponse a -> String -> String
+getValidationErrors response f =
+    case response of
+        Success (ValidationErrors validationErrors) ->
+            validationErrors
+                |> List.filter (\{ field } -> field == f)
+                |> List.map .message
+                |> String.join ", "
+
+        _ ->
+            ""
+
+
+formatValidationErrors : ApiResponse a -> String -> Html msg
+formatValidationErrors response field =
+    let
+        errs =
+            getValidationErrors response field
+    in
+    if errs /= "" then
+        p [ class "text-red text-xs italic pt-2" ]
+            [ text errs ]
+
+    else
+        text ""
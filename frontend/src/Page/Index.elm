@@ -30,14 +30,14 @@ import View exposing (Toast(..), toastFromHttpError, viewToast)
 getUsers : Config -> Cmd Msg
 getUsers config =
     Request.User.getUsers config
-        |> RD.sendRequest
+        |> Api.sendRequest
         |> Cmd.map GetUsersResponse
 
 
 createUser : Config -> CreateUserInput -> Cmd Msg
 createUser config input =
     Request.User.createUser config input
-        |> RD.sendRequest
+        |> Api.sendRequest
         |> Cmd.map GotCreateUserResponse
 
 
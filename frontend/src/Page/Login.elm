@@ -36,7 +36,7 @@ import View exposing (Toast(..), viewToast)
 login : Config -> Token -> LoginInput -> Cmd Msg
 login config token input =
     Request.Login.login config token input
-        |> RD.sendRequest
+        |> Api.sendRequest
         |> Cmd.map GotLogin
 
 
@@ -32,7 +32,7 @@ import Route
 register : Config -> Token -> RegisterInput -> Cmd Msg
 register config token input =
     Request.Register.register config token input
-        |> RD.sendRequest
+        |> Api.sendRequest
         |> Cmd.map GotRegister
 
 
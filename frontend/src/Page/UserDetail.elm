@@ -36,28 +36,28 @@ import View exposing (Toast(..), toastFromApiResponse, viewToast)
 getUser : Config -> String -> Cmd Msg
 getUser config id =
     Request.User.getUser config id
-        |> RD.sendRequest
+        |> Api.sendRequest
         |> Cmd.map GotUser
 
 
 createDeal : Config -> CreateDealInput -> Cmd Msg
 createDeal config input =
     Request.Deal.createDeal config input
-        |> RD.sendRequest
+        |> Api.sendRequest
         |> Cmd.map DealCreated
 
 
 updateDeal : Config -> UpdateDealInput -> Int -> Cmd Msg
 updateDeal config input id =
     Request.Deal.updateDeal config input id
-        |> RD.sendRequest
+        |> Api.sendRequest
         |> Cmd.map DealUpdated
 
 
 getDeals : Config -> String -> Cmd Msg
 getDeals config id =
     Request.Deal.getDeals config id
-        |> RD.sendRequest
+        |> Api.sendRequest
         |> Cmd.map GotDeals
 
 
@@ -397,6 +397,9 @@ viewDeal model deal =
                     select [ onInput Status, autofocus True ]
                         [ option [ value "Initialized" ] [ text "Initialized" ]
                         , option [ value "MailerSent" ] [ text "MailerSent" ]
+                        , option [ value "SellerInterested" ] [ text "SellerInterested" ]
+                        , option [ value "SellerDeclined" ] [ text "SellerDeclined" ]
+                        , option [ value "UnderContract" ] [ text "UnderContract" ]
                         ]
 
                 False ->
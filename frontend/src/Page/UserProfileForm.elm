@@ -37,21 +37,21 @@ import View exposing (Toast(..), toastFromHttpError, viewToast)
 getProfile : Config -> String -> Cmd Msg
 getProfile config id =
     Request.User.getProfile config id
-        |> RD.sendRequest
+        |> Api.sendRequest
         |> Cmd.map GotProfile
 
 
 createProfile : Config -> String -> ProfileInput -> Cmd Msg
 createProfile config id input =
     Request.User.createProfile config id input
-        |> RD.sendRequest
+        |> Api.sendRequest
         |> Cmd.map CreatedProfile
 
 
 updateProfile : Config -> String -> ProfileInput -> Cmd Msg
 updateProfile config id input =
     Request.User.updateProfile config id input
-        |> RD.sendRequest
+        |> Api.sendRequest
         |> Cmd.map UpdatedProfile
 
 
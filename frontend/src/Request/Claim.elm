@@ -0,0 +1,94 @@
+module Request.Claim exposing (SellerResponseInput, claimDeal, getClaim, submitResponse)
+
+import Api exposing (ApiData, decodeApiResponse)
+import Config exposing (Config)
+import Data.Claim as Claim exposing (ContactMethod, DealClaim, ResponseType, SellerResponse, decodeDealClaim, decodeSellerResponse)
+import Data.Deal exposing (Deal, decodeDeal)
+import Http exposing (Request)
+import HttpBuilder as HB exposing (RequestBuilder)
+import Json.Encode as JE
+import Url.Builder as UB
+
+
+
+-- Types
+
+
+type alias SellerResponseInput =
+    { response : ResponseType
+    , asking_price : Maybe Int
+    , contact_method : Maybe ContactMethod
+    , contact : String
+    , message : String
+    }
+
+
+
+-- Requests
+
+
+getClaim : Config -> String -> Request (ApiData DealClaim)
+getClaim config code =
+    UB.crossOrigin config.api [ "v1", "claim", code ] []
+        |> HB.get
+        |> HB.withExpect (Http.expectJson (decodeApiResponse decodeDealClaim))
+        |> HB.toRequest
+
+
+claimDeal : Config -> String -> Request (ApiData Deal)
+claimDeal config code =
+    UB.crossOrigin config.api [ "v1", "claim", code ] []
+        |> HB.post
+        |> HB.withExpect (Http.expectJson (decodeApiResponse decodeDeal))
+        |> HB.withHeader "X-API-KEY" config.token
+        |> HB.toRequest
+
+
+{-| Anyone with the code can respond until the deal is claimed, so the key is
+only sent when signed in
+-}
+submitResponse : Config -> String -> SellerResponseInput -> Request (ApiData SellerResponse)
+submitResponse config code input =
+    UB.crossOrigin config.api [ "v1", "claim", code, "response" ] []
+        |> HB.post
+        |> HB.withJsonBody (input |> encodeSellerResponseInput)
+        |> HB.withExpect (Http.expectJson (decodeApiResponse decodeSellerResponse))
+        |> withToken config
+        |> HB.toRequest
+
+
+withToken : Config -> RequestBuilder a -> RequestBuilder a
+withToken config builder =
+    if config.token == "" then
+        builder
+
+    else
+        HB.withHeader "X-API-KEY" config.token builder
+
+
+
+-- Encoders
+
+
+encodeSellerResponseInput : SellerResponseInput -> JE.Value
+encodeSellerResponseInput v =
+    JE.object
+        [ ( "response", JE.string (v.response |> Claim.responseTypeToString) )
+        , ( "asking_price", v.asking_price |> Maybe.map JE.int |> Maybe.withDefault JE.null )
+        , ( "contact_method"
+          , v.contact_method
+                |> Maybe.map (Claim.contactMethodToString >> JE.string)
+                |> Maybe.withDefault JE.null
+          )
+        , ( "contact", optionalString v.contact )
+        , ( "message", optionalString v.message )
+        ]
+
+
+optionalString : String -> JE.Value
+optionalString v =
+    if String.trim v == "" then
+        JE.null
+
+    else
+        JE.string v
@@ -36,7 +36,7 @@ type alias UpdateDealInput =
 
 createDeal : Config -> CreateDealInput -> Request (ApiData Deal)
 createDeal config input =
-    UB.crossOrigin config.api [ "deals" ] []
+    UB.crossOrigin config.api [ "v1", "deals" ] []
         |> HB.post
         |> HB.withJsonBody (input |> encodeCreateDealInput)
         |> HB.withExpect (Http.expectJson (decodeApiResponse decodeDeal))
@@ -46,7 +46,7 @@ createDeal config input =
 
 updateDeal : Config -> UpdateDealInput -> Int -> Request (ApiData Deal)
 updateDeal config input id =
-    UB.crossOrigin config.api [ "deals", id |> String.fromInt, "update" ] []
+    UB.crossOrigin config.api [ "v1", "deals", id |> String.fromInt, "update" ] []
         |> HB.post
         |> HB.withJsonBody (input |> encodeUpdateDealInput)
         |> HB.withExpect (Http.expectJson (decodeApiResponse decodeDeal))
@@ -56,7 +56,7 @@ updateDeal config input id =
 
 getDeals : Config -> String -> Request (ApiData (List Deal))
 getDeals config id =
-    UB.crossOrigin config.api [ "deals" ] [ UB.string "buyer_id" id ]
+    UB.crossOrigin config.api [ "v1", "deals" ] [ UB.string "buyer_id" id ]
         |> HB.get
         |> HB.withExpect (Http.expectJson (decodeApiResponse (JD.list decodeDeal)))
         |> HB.withHeader "X-API-KEY" config.token
@@ -29,7 +29,7 @@ type alias LoginInput =
 
 login : Config -> Token -> LoginInput -> Request (ApiData AuthPayload)
 login config token input =
-    UB.crossOrigin config.api [ "login" ] []
+    UB.crossOrigin config.api [ "v1", "login" ] []
         |> HB.post
         |> HB.withJsonBody (input |> encodeLoginInput)
         |> HB.withExpect (Http.expectJson (decodeApiResponse decodeAuthPayload))
@@ -15,7 +15,7 @@ import Url.Builder as UB
 
 register : Config -> Token -> RegisterInput -> Request (ApiData AuthPayload)
 register config token input =
-    UB.crossOrigin config.api [ "register" ] []
+    UB.crossOrigin config.api [ "v1", "register" ] []
         |> HB.post
         |> HB.withJsonBody (input |> encodeRegisterInput)
         |> HB.withExpect (Http.expectJson decode)
@@ -13,7 +13,7 @@ import Url.Builder as UB
 
 getUser : Config -> String -> Request (ApiData User)
 getUser config id =
-    UB.crossOrigin config.api [ "users", id ] []
+    UB.crossOrigin config.api [ "v1", "users", id ] []
         |> HB.get
         |> HB.withExpect (Http.expectJson (decodeApiResponse User.userDecoder))
         |> HB.withHeader "X-API-KEY" config.token
@@ -22,7 +22,7 @@ getUser config id =
 
 getProfile : Config -> String -> Request (ApiData Profile)
 getProfile config id =
-    UB.crossOrigin config.api [ "users", id, "profile" ] []
+    UB.crossOrigin config.api [ "v1", "users", id, "profile" ] []
         |> HB.get
         |> HB.withExpect (Http.expectJson (decodeApiResponse User.profileDecoder))
         |> HB.withHeader "X-API-KEY" config.token
@@ -31,7 +31,7 @@ getProfile config id =
 
 createProfile : Config -> String -> ProfileInput -> Request (ApiData Profile)
 createProfile config id input =
-    UB.crossOrigin config.api [ "users", id, "profile" ] []
+    UB.crossOrigin config.api [ "v1", "users", id, "profile" ] []
         |> HB.post
         |> HB.withExpect (Http.expectJson (decodeApiResponse User.profileDecoder))
         |> HB.withJsonBody (encodeProfileInput input)
@@ -41,7 +41,7 @@ createProfile config id input =
 
 updateProfile : Config -> String -> ProfileInput -> Request (ApiData Profile)
 updateProfile config id input =
-    UB.crossOrigin config.api [ "users", id, "profile" ] []
+    UB.crossOrigin config.api [ "v1", "users", id, "profile" ] []
         |> HB.put
         |> HB.withExpect (Http.expectJson (decodeApiResponse User.profileDecoder))
         |> HB.withJsonBody (encodeProfileInput input)
@@ -51,7 +51,7 @@ updateProfile config id input =
 
 createUser : Config -> CreateUserInput -> Request (ApiData User)
 createUser config input =
-    UB.crossOrigin config.api [ "users" ] []
+    UB.crossOrigin config.api [ "v1", "users" ] []
         |> HB.post
         |> HB.withExpect (Http.expectJson (decodeApiResponse User.userDecoder))
         |> HB.withJsonBody (encodeCreateUserInput input)
@@ -61,7 +61,7 @@ createUser config input =
 
 getUsers : Config -> Request (ApiData (List User))
 getUsers config =
-    UB.crossOrigin config.api [ "users" ] []
+    UB.crossOrigin config.api [ "v1", "users" ] []
         |> HB.get
         |> HB.withExpect (Http.expectJson decodeAllUsersResponse)
         |> HB.withHeader "X-API-KEY" config.token
@@ -22,6 +22,7 @@ type Route
     | Logout
     | UserDetail { id : String }
     | UserProfileForm { id : String }
+    | Claim { code : String }
 
 
 parser : Parser (Route -> a) a
@@ -33,6 +34,7 @@ parser =
         , UP.map Logout <| UP.s "logout"
         , UP.map (\id -> UserDetail { id = id }) <| UP.s "user" </> UP.string
         , UP.map (\id -> UserProfileForm { id = id }) <| UP.s "user" </> UP.string </> UP.s "profile"
+        , UP.map (\code -> Claim { code = code }) <| UP.s "claim" </> UP.string
         ]
 
 
@@ -57,6 +59,9 @@ toString route =
         UserProfileForm { id } ->
             UB.absolute [ "user", id, "profile" ] []
 
+        Claim { code } ->
+            UB.absolute [ "claim", code ] []
+
 
 newUrl : Key -> Route -> Cmd msg
 newUrl key =
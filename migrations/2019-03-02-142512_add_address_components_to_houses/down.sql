@@ -0,0 +1,13 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX houses_canonical_address_idx;
+DROP INDEX houses_place_id_idx;
+
+ALTER TABLE houses
+DROP COLUMN street_number,
+DROP COLUMN route,
+DROP COLUMN city,
+DROP COLUMN state,
+DROP COLUMN postal_code,
+DROP COLUMN country,
+DROP COLUMN place_id,
+DROP COLUMN canonical_address;
@@ -0,0 +1,69 @@
+-- Your SQL goes here
+ALTER TABLE houses
+ADD COLUMN street_number VARCHAR(255),
+ADD COLUMN route VARCHAR(255),
+ADD COLUMN city VARCHAR(255),
+ADD COLUMN state VARCHAR(255),
+ADD COLUMN postal_code VARCHAR(255),
+ADD COLUMN country VARCHAR(255),
+ADD COLUMN place_id VARCHAR(255),
+ADD COLUMN canonical_address VARCHAR(255);
+
+CREATE INDEX houses_place_id_idx ON houses (place_id);
+CREATE INDEX houses_canonical_address_idx ON houses (canonical_address);
+
+-- Backfill existing rows from the stored google address.
+-- Component types are accepted both in the frontend's encoding
+-- (StreetNumber) and in google's own (street_number).
+CREATE FUNCTION pg_temp.address_component(address jsonb, component_types text[], field text)
+RETURNS VARCHAR AS $$
+    SELECT NULLIF(c->>field, '')
+    FROM jsonb_array_elements(
+        CASE jsonb_typeof(address->'address_components')
+            WHEN 'array' THEN address->'address_components'
+            ELSE '[]'::jsonb
+        END
+    ) AS c
+    WHERE c->'types' ?| component_types
+    LIMIT 1
+$$ LANGUAGE sql IMMUTABLE;
+
+-- Must stay in sync with houses::address::canonicalize
+CREATE FUNCTION pg_temp.canonical_address(raw text) RETURNS VARCHAR AS $$
+    SELECT string_agg(COALESCE(a.short, w.word), ' ' ORDER BY w.n)
+    FROM regexp_split_to_table(
+        trim(regexp_replace(lower(raw), '[^a-z0-9]+', ' ', 'g')),
+        ' '
+    ) WITH ORDINALITY AS w(word, n)
+    LEFT JOIN (VALUES
+        ('street', 'st'), ('avenue', 'ave'), ('road', 'rd'), ('drive', 'dr'),
+        ('lane', 'ln'), ('boulevard', 'blvd'), ('court', 'ct'), ('place', 'pl'),
+        ('terrace', 'ter'), ('circle', 'cir'), ('highway', 'hwy'),
+        ('parkway', 'pkwy'), ('square', 'sq'), ('trail', 'trl'),
+        ('north', 'n'), ('south', 's'), ('east', 'e'), ('west', 'w'),
+        ('northeast', 'ne'), ('northwest', 'nw'), ('southeast', 'se'),
+        ('southwest', 'sw'), ('apartment', 'apt'), ('suite', 'ste')
+    ) AS a(long, short) ON a.long = w.word
+$$ LANGUAGE sql IMMUTABLE;
+
+UPDATE houses SET
+    street_number = pg_temp.address_component(google_address, '{StreetNumber,street_number}', 'long_name'),
+    route = pg_temp.address_component(google_address, '{Route,route}', 'long_name'),
+    city = COALESCE(
+        pg_temp.address_component(google_address, '{Locality,locality}', 'long_name'),
+        pg_temp.address_component(google_address, '{PostalTown,postal_town}', 'long_name'),
+        pg_temp.address_component(google_address, '{Sublocality,sublocality}', 'long_name')
+    ),
+    state = pg_temp.address_component(google_address, '{AdministrativeAreaLevel1,administrative_area_level_1}', 'short_name'),
+    postal_code = pg_temp.address_component(google_address, '{PostalCode,postal_code}', 'long_name'),
+    country = pg_temp.address_component(google_address, '{Country,country}', 'short_name'),
+    place_id = NULLIF(google_address->>'place_id', '')
+WHERE jsonb_typeof(google_address) = 'object';
+
+UPDATE houses SET canonical_address = pg_temp.canonical_address(
+    CASE
+        WHEN street_number IS NOT NULL AND route IS NOT NULL
+            THEN concat_ws(' ', street_number, route, city, state, postal_code)
+        ELSE address
+    END
+);
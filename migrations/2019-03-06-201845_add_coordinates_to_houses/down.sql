@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX houses_lat_lng_idx;
+
+ALTER TABLE houses
+DROP COLUMN lat,
+DROP COLUMN lng;
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+ALTER TABLE houses
+ADD COLUMN lat DOUBLE PRECISION,
+ADD COLUMN lng DOUBLE PRECISION;
+
+CREATE INDEX houses_lat_lng_idx ON houses (lat, lng);
+
+UPDATE houses SET
+    lat = (google_address->'geometry'->'location'->>'latitude')::DOUBLE PRECISION,
+    lng = (google_address->'geometry'->'location'->>'longitude')::DOUBLE PRECISION
+WHERE jsonb_typeof(google_address->'geometry'->'location'->'latitude') = 'number'
+AND jsonb_typeof(google_address->'geometry'->'location'->'longitude') = 'number';
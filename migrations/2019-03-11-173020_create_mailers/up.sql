@@ -0,0 +1,9 @@
+-- Your SQL goes here
+CREATE TABLE mailers (
+  id SERIAL PRIMARY KEY,
+  deal_id INTEGER NOT NULL REFERENCES deals(id) ON DELETE CASCADE,
+  sent_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+  created TIMESTAMP NOT NULL
+);
+
+CREATE INDEX mailers_deal_id_idx ON mailers (deal_id);
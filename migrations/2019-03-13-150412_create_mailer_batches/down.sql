@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE mailers
+DROP COLUMN batch_id;
+
+DROP TABLE mailer_batches;
@@ -0,0 +1,15 @@
+-- Your SQL goes here
+CREATE TABLE mailer_batches (
+  id SERIAL PRIMARY KEY,
+  created_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+  deal_count INTEGER NOT NULL,
+  skipped_count INTEGER NOT NULL,
+  created TIMESTAMP NOT NULL,
+  pdf BYTEA NOT NULL,
+  manifest TEXT NOT NULL
+);
+
+ALTER TABLE mailers
+ADD COLUMN batch_id INTEGER REFERENCES mailer_batches(id) ON DELETE SET NULL;
+
+CREATE INDEX mailers_batch_id_idx ON mailers (batch_id);
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE mailers
+DROP COLUMN template_version_id;
+
+DROP TABLE mailer_template_versions;
+DROP TABLE mailer_templates;
@@ -0,0 +1,29 @@
+-- Your SQL goes here
+CREATE TABLE mailer_templates (
+  id SERIAL PRIMARY KEY,
+  name VARCHAR(255) NOT NULL UNIQUE,
+  is_default BOOL NOT NULL DEFAULT false,
+  archived BOOL NOT NULL DEFAULT false,
+  created_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+  created TIMESTAMP NOT NULL,
+  updated TIMESTAMP NOT NULL
+);
+
+-- Only one template can be the default
+CREATE UNIQUE INDEX mailer_templates_is_default_idx ON mailer_templates (is_default)
+WHERE is_default;
+
+-- Versions are never updated or deleted, so a mailer always points at the
+-- exact copy it was printed with
+CREATE TABLE mailer_template_versions (
+  id SERIAL PRIMARY KEY,
+  template_id INTEGER NOT NULL REFERENCES mailer_templates(id) ON DELETE RESTRICT,
+  version INTEGER NOT NULL,
+  body TEXT NOT NULL,
+  created_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+  created TIMESTAMP NOT NULL,
+  UNIQUE (template_id, version)
+);
+
+ALTER TABLE mailers
+ADD COLUMN template_version_id INTEGER REFERENCES mailer_template_versions(id) ON DELETE RESTRICT;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE mailer_scans;
+DROP INDEX deals_access_code_idx;
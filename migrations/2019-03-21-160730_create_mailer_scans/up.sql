@@ -0,0 +1,20 @@
+-- Your SQL goes here
+
+-- Deals were created with a placeholder code, so give every deal that
+-- shares one its own before codes are made unique
+UPDATE deals SET access_code = upper(substr(md5(random()::text || id::text), 1, 8))
+WHERE access_code IN (
+  SELECT access_code FROM deals GROUP BY access_code HAVING count(*) > 1
+);
+
+CREATE UNIQUE INDEX deals_access_code_idx ON deals (access_code);
+
+CREATE TABLE mailer_scans (
+  id SERIAL PRIMARY KEY,
+  deal_id INTEGER NOT NULL REFERENCES deals(id) ON DELETE CASCADE,
+  mailer_id INTEGER REFERENCES mailers(id) ON DELETE SET NULL,
+  created TIMESTAMP NOT NULL
+);
+
+CREATE INDEX mailer_scans_deal_id_idx ON mailer_scans (deal_id);
+CREATE INDEX mailer_scans_mailer_id_idx ON mailer_scans (mailer_id);
@@ -0,0 +1,15 @@
+-- Your SQL goes here
+CREATE TABLE seller_responses (
+  id SERIAL PRIMARY KEY,
+  deal_id INTEGER NOT NULL REFERENCES deals(id) ON DELETE CASCADE,
+  response VARCHAR NOT NULL,
+  asking_price INTEGER,
+  contact_method VARCHAR,
+  contact VARCHAR(255),
+  message TEXT,
+  -- The admin who entered the response, null when the seller submitted it
+  entered_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+  created TIMESTAMP NOT NULL
+);
+
+CREATE INDEX seller_responses_deal_id_idx ON seller_responses (deal_id);
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+CREATE TABLE offers (
+  id SERIAL PRIMARY KEY,
+  deal_id INTEGER NOT NULL REFERENCES deals(id) ON DELETE CASCADE,
+  -- Offer this one counters
+  counter_to INTEGER REFERENCES offers(id) ON DELETE SET NULL,
+  author_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+  -- Side the offer was made for, buyer or seller
+  party VARCHAR NOT NULL,
+  amount INTEGER NOT NULL CHECK (amount > 0),
+  contingencies TEXT[] NOT NULL DEFAULT '{}',
+  expires TIMESTAMP,
+  status VARCHAR NOT NULL,
+  created TIMESTAMP NOT NULL,
+  updated TIMESTAMP NOT NULL
+);
+
+CREATE INDEX offers_deal_id_idx ON offers (deal_id);
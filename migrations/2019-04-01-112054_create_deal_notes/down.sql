@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE deal_note_revisions;
+DROP TABLE deal_notes;
@@ -0,0 +1,27 @@
+-- Your SQL goes here
+CREATE TABLE deal_notes (
+  id SERIAL PRIMARY KEY,
+  deal_id INTEGER NOT NULL REFERENCES deals(id) ON DELETE CASCADE,
+  -- Note this one replies to
+  parent_id INTEGER REFERENCES deal_notes(id) ON DELETE CASCADE,
+  author_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+  body TEXT NOT NULL,
+  visibility VARCHAR NOT NULL,
+  created TIMESTAMP NOT NULL,
+  updated TIMESTAMP NOT NULL,
+  deleted TIMESTAMP
+);
+
+CREATE INDEX deal_notes_deal_id_idx ON deal_notes (deal_id);
+
+-- Earlier versions of a note, saved on every edit and on delete
+CREATE TABLE deal_note_revisions (
+  id SERIAL PRIMARY KEY,
+  note_id INTEGER NOT NULL REFERENCES deal_notes(id) ON DELETE CASCADE,
+  body TEXT NOT NULL,
+  visibility VARCHAR NOT NULL,
+  edited_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+  created TIMESTAMP NOT NULL
+);
+
+CREATE INDEX deal_note_revisions_note_id_idx ON deal_note_revisions (note_id);
@@ -0,0 +1,15 @@
+-- Your SQL goes here
+CREATE TABLE deal_documents (
+  id SERIAL PRIMARY KEY,
+  deal_id INTEGER NOT NULL REFERENCES deals(id) ON DELETE CASCADE,
+  uploaded_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+  filename VARCHAR(255) NOT NULL,
+  content_type VARCHAR NOT NULL,
+  size_bytes INTEGER NOT NULL,
+  sha256 VARCHAR(64) NOT NULL,
+  -- Where the file lives in storage
+  storage_key VARCHAR NOT NULL UNIQUE,
+  created TIMESTAMP NOT NULL
+);
+
+CREATE INDEX deal_documents_deal_id_idx ON deal_documents (deal_id);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE deal_tasks;
+DROP TABLE checklist_template_items;
+DROP TABLE checklist_templates;
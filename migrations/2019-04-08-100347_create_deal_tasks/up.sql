@@ -0,0 +1,44 @@
+-- Your SQL goes here
+CREATE TABLE checklist_templates (
+  id SERIAL PRIMARY KEY,
+  name VARCHAR(255) NOT NULL,
+  -- Deal status that applies the checklist
+  status VARCHAR NOT NULL,
+  created_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+  created TIMESTAMP NOT NULL,
+  updated TIMESTAMP NOT NULL
+);
+
+CREATE INDEX checklist_templates_status_idx ON checklist_templates (status);
+
+CREATE TABLE checklist_template_items (
+  id SERIAL PRIMARY KEY,
+  template_id INTEGER NOT NULL REFERENCES checklist_templates(id) ON DELETE CASCADE,
+  position INTEGER NOT NULL,
+  title VARCHAR(255) NOT NULL,
+  -- Days after the checklist is applied that the task is due
+  due_in_days INTEGER,
+  -- Null assigns the task to the deal's buyer
+  assignee_id INTEGER REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE INDEX checklist_template_items_template_id_idx ON checklist_template_items (template_id);
+
+CREATE TABLE deal_tasks (
+  id SERIAL PRIMARY KEY,
+  deal_id INTEGER NOT NULL REFERENCES deals(id) ON DELETE CASCADE,
+  -- Checklist item the task came from
+  template_item_id INTEGER REFERENCES checklist_template_items(id) ON DELETE SET NULL,
+  title VARCHAR(255) NOT NULL,
+  assignee_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+  due_date DATE,
+  completed TIMESTAMP,
+  completed_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+  created_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+  created TIMESTAMP NOT NULL,
+  updated TIMESTAMP NOT NULL,
+  -- A checklist item is only applied to a deal once
+  UNIQUE (deal_id, template_item_id)
+);
+
+CREATE INDEX deal_tasks_assignee_id_idx ON deal_tasks (assignee_id, due_date);
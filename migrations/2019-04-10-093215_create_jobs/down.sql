@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM mailer_batches WHERE pdf IS NULL;
+ALTER TABLE mailer_batches DROP COLUMN rendered;
+ALTER TABLE mailer_batches ALTER COLUMN pdf SET NOT NULL;
+
+DROP TABLE jobs;
@@ -0,0 +1,23 @@
+-- Your SQL goes here
+CREATE TABLE jobs (
+  id SERIAL PRIMARY KEY,
+  kind VARCHAR NOT NULL,
+  payload JSONB NOT NULL,
+  status VARCHAR NOT NULL DEFAULT 'queued',
+  attempts INTEGER NOT NULL DEFAULT 0,
+  max_attempts INTEGER NOT NULL,
+  run_at TIMESTAMP NOT NULL,
+  locked_at TIMESTAMP,
+  last_error TEXT,
+  created TIMESTAMP NOT NULL,
+  updated TIMESTAMP NOT NULL
+);
+
+-- Workers only ever look for queued jobs that are due
+CREATE INDEX jobs_queued_run_at_idx ON jobs (run_at) WHERE status = 'queued';
+CREATE INDEX jobs_status_idx ON jobs (status);
+
+-- Batch PDFs are rendered by a job after the batch is created
+ALTER TABLE mailer_batches ALTER COLUMN pdf DROP NOT NULL;
+ALTER TABLE mailer_batches ADD COLUMN rendered TIMESTAMP;
+UPDATE mailer_batches SET rendered = created;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE daily_stats;
+DROP TABLE scheduled_runs;
@@ -0,0 +1,17 @@
+-- Your SQL goes here
+CREATE TABLE scheduled_runs (
+  name VARCHAR PRIMARY KEY,
+  last_run TIMESTAMP NOT NULL,
+  last_error TEXT,
+  updated TIMESTAMP NOT NULL
+);
+
+CREATE TABLE daily_stats (
+  day DATE PRIMARY KEY,
+  deals_created INTEGER NOT NULL,
+  mailers_sent INTEGER NOT NULL,
+  mailer_scans INTEGER NOT NULL,
+  seller_responses INTEGER NOT NULL,
+  offers_accepted INTEGER NOT NULL,
+  created TIMESTAMP NOT NULL
+);
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+CREATE TABLE emails (
+  id SERIAL PRIMARY KEY,
+  user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+  to_address VARCHAR NOT NULL,
+  template VARCHAR NOT NULL,
+  subject VARCHAR NOT NULL,
+  html_body TEXT NOT NULL,
+  text_body TEXT NOT NULL,
+  status VARCHAR NOT NULL DEFAULT 'queued',
+  error TEXT,
+  sent TIMESTAMP,
+  created TIMESTAMP NOT NULL,
+  updated TIMESTAMP NOT NULL
+);
+
+CREATE INDEX emails_user_id_idx ON emails (user_id);
+CREATE INDEX emails_status_idx ON emails (status);
@@ -0,0 +1,21 @@
+-- Your SQL goes here
+CREATE TABLE notifications (
+  id SERIAL PRIMARY KEY,
+  user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  deal_id INTEGER REFERENCES deals(id) ON DELETE CASCADE,
+  kind VARCHAR NOT NULL,
+  message TEXT NOT NULL,
+  read TIMESTAMP,
+  created TIMESTAMP NOT NULL
+);
+
+CREATE INDEX notifications_user_id_idx ON notifications (user_id, created);
+CREATE INDEX notifications_unread_idx ON notifications (user_id) WHERE read IS NULL;
+
+CREATE TABLE notification_preferences (
+  user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  kind VARCHAR NOT NULL,
+  email BOOLEAN NOT NULL,
+  updated TIMESTAMP NOT NULL,
+  PRIMARY KEY (user_id, kind)
+);
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+ALTER TABLE emails ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0;
+
+-- Failed used to mean a retry was pending; retries now stay queued
+UPDATE emails SET status = 'queued' WHERE status = 'failed';
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX houses_canonical_address_idx;
+CREATE INDEX houses_canonical_address_idx ON houses (canonical_address);
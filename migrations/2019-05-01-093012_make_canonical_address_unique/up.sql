@@ -0,0 +1,112 @@
+-- Your SQL goes here
+
+-- Recompute canonical addresses now that free text drops its country, the
+-- way addresses built from google's components always have, units are kept
+-- apart and accented letters are folded instead of dropped.
+CREATE FUNCTION pg_temp.address_component(address jsonb, component_types text[], field text)
+RETURNS VARCHAR AS $$
+    SELECT NULLIF(c->>field, '')
+    FROM jsonb_array_elements(
+        CASE jsonb_typeof(address->'address_components')
+            WHEN 'array' THEN address->'address_components'
+            ELSE '[]'::jsonb
+        END
+    ) AS c
+    WHERE c->'types' ?| component_types
+    LIMIT 1
+$$ LANGUAGE sql IMMUTABLE;
+
+-- Must stay in sync with houses::address::canonicalize
+CREATE FUNCTION pg_temp.canonical_address(raw text) RETURNS VARCHAR AS $$
+    SELECT COALESCE(string_agg(COALESCE(a.short, w.word), ' ' ORDER BY w.n), '')
+    FROM regexp_split_to_table(
+        trim(regexp_replace(
+            translate(
+                raw,
+                'ABCDEFGHIJKLMNOPQRSTUVWXYZÀÁÂÃÄÅÇÈÉÊËÌÍÎÏÑÒÓÔÕÖØÙÚÛÜÝàáâãäåçèéêëìíîïñòóôõöøùúûüýÿ',
+                'abcdefghijklmnopqrstuvwxyzaaaaaaceeeeiiiinoooooouuuuyaaaaaaceeeeiiiinoooooouuuuyy'
+            ),
+            '[\x01-\x2f\x3a-\x40\x5b-\x60\x7b-\x7f\u00a0‘’“”–—]+',
+            ' ',
+            'g'
+        )),
+        ' '
+    ) WITH ORDINALITY AS w(word, n)
+    LEFT JOIN (VALUES
+        ('street', 'st'), ('avenue', 'ave'), ('road', 'rd'), ('drive', 'dr'),
+        ('lane', 'ln'), ('boulevard', 'blvd'), ('court', 'ct'), ('place', 'pl'),
+        ('terrace', 'ter'), ('circle', 'cir'), ('highway', 'hwy'),
+        ('parkway', 'pkwy'), ('square', 'sq'), ('trail', 'trl'),
+        ('north', 'n'), ('south', 's'), ('east', 'e'), ('west', 'w'),
+        ('northeast', 'ne'), ('northwest', 'nw'), ('southeast', 'se'),
+        ('southwest', 'sw'), ('apartment', 'apt'), ('suite', 'ste')
+    ) AS a(long, short) ON a.long = w.word
+    WHERE w.word <> ''
+$$ LANGUAGE sql IMMUTABLE;
+
+-- Drop the longest of the countries that ends a canonical address.
+-- Must stay in sync with houses::address::without_country
+CREATE FUNCTION pg_temp.without_country(canonical text, countries text[])
+RETURNS VARCHAR AS $$
+    SELECT COALESCE(
+        (
+            SELECT left(canonical, length(canonical) - length(c) - 1)
+            FROM unnest(countries) AS c
+            WHERE c <> '' AND canonical LIKE '% ' || c
+            ORDER BY length(c) DESC
+            LIMIT 1
+        ),
+        canonical
+    )
+$$ LANGUAGE sql IMMUTABLE;
+
+UPDATE houses SET canonical_address = CASE
+    WHEN street_number IS NOT NULL AND route IS NOT NULL
+        THEN pg_temp.canonical_address(
+            concat_ws(
+                ' ',
+                street_number,
+                route,
+                pg_temp.address_component(google_address, '{Subpremise,subpremise}', 'long_name'),
+                city,
+                state,
+                postal_code
+            )
+        )
+    ELSE pg_temp.without_country(
+        pg_temp.canonical_address(address),
+        ARRAY['usa', 'us', 'united states', 'united states of america']
+            || ARRAY[
+                pg_temp.canonical_address(COALESCE(
+                    pg_temp.address_component(google_address, '{Country,country}', 'long_name'),
+                    ''
+                )),
+                pg_temp.canonical_address(COALESCE(country, ''))
+            ]
+    )
+END;
+
+-- Houses that now share a canonical address may be different units or
+-- genuine duplicates, so they're reported for an admin to merge through the
+-- merge houses endpoint instead. All but the one with google data, or else
+-- the oldest, are left without a canonical address so it can be unique.
+DO $$
+DECLARE
+    conflict record;
+BEGIN
+    FOR conflict IN
+        SELECT canonical_address, array_agg(id ORDER BY place_id IS NULL, id) AS ids
+        FROM houses
+        WHERE canonical_address IS NOT NULL
+        GROUP BY canonical_address
+        HAVING count(*) > 1
+    LOOP
+        RAISE WARNING 'Houses % share the address "%"; merge duplicates into house %',
+            conflict.ids, conflict.canonical_address, conflict.ids[1];
+        UPDATE houses SET canonical_address = NULL
+        WHERE id = ANY(conflict.ids[2:array_length(conflict.ids, 1)]);
+    END LOOP;
+END $$;
+
+DROP INDEX houses_canonical_address_idx;
+CREATE UNIQUE INDEX houses_canonical_address_idx ON houses (canonical_address);
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE mailer_batches ADD COLUMN incomplete jsonb NOT NULL DEFAULT '[]';
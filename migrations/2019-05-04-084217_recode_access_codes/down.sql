@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+-- Old access codes aren't kept, so there's nothing to undo
+SELECT 1;
@@ -0,0 +1,25 @@
+-- Your SQL goes here
+
+-- Codes made before access codes were generated by the app, such as the old
+-- default and the placeholder backfill, can hold characters that are easy
+-- to misread. Give each of them a new code from the characters the app
+-- uses, the way deals::new_access_code does.
+DO $$
+DECLARE
+  chars TEXT := '23456789ABCDEFGHJKMNPQRSTUVWXYZ';
+  deal RECORD;
+  code TEXT;
+BEGIN
+  FOR deal IN
+    SELECT id FROM deals WHERE access_code !~ '^[23456789ABCDEFGHJKMNPQRSTUVWXYZ]{8}$'
+  LOOP
+    LOOP
+      SELECT string_agg(substr(chars, 1 + floor(random() * length(chars))::int, 1), '')
+      INTO code
+      FROM generate_series(1, 8);
+      EXIT WHEN NOT EXISTS (SELECT 1 FROM deals WHERE access_code = code);
+    END LOOP;
+    UPDATE deals SET access_code = code WHERE id = deal.id;
+  END LOOP;
+END
+$$;
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE deals DROP COLUMN access_code_rotated;
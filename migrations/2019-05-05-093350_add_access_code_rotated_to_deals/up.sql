@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE deals ADD COLUMN access_code_rotated TIMESTAMP;
@@ -6,13 +6,18 @@ pub mod types;
 use self::types::*;
 use accounts::types::CurrentUser::*;
 use db::{Conn, PooledConnection};
+use diesel::pg::PgConnection;
 use diesel::prelude::*;
 use diesel::result::DatabaseErrorKind;
 use diesel::result::Error::DatabaseError;
+use email::types::EmailTemplate;
 use result::Error;
 use result::{Payload, Response};
 use validator::Validate;
 
+/// Sessions unused for this long are deleted
+const SESSION_MAX_AGE_DAYS: i64 = 30;
+
 ///
 /// Helpers
 ///
@@ -64,6 +69,16 @@ pub fn user_from_key(conn: PooledConnection, key: String) -> Option<User> {
     user
 }
 
+/// Delete sessions that were replaced by a newer login or went unused
+pub fn purge_sessions(conn: &PgConnection) -> Result<usize, Error> {
+    use schema::sessions::dsl::*;
+
+    let cutoff = chrono::Utc::now().naive_utc() - chrono::Duration::days(SESSION_MAX_AGE_DAYS);
+    diesel::delete(sessions.filter(active.eq(false).or(updated.lt(cutoff))))
+        .execute(conn)
+        .map_err(|e| Error::from(e))
+}
+
 /// Check if the user is an admin
 pub fn user_is_admin(user: &User) -> bool {
     user.roles.iter().any(|r| match r {
@@ -173,21 +188,34 @@ pub fn register(conn: Conn, input: RegistrationInput) -> Response<AuthPayload> {
 
     let Conn(conn) = conn;
 
-    // Create user
-    let user = diesel::insert_into(users)
-        .values(&NewUser {
-            name: input.name,
-            email: input.email,
-            password_hash: bcrypt::hash(&input.password, bcrypt::DEFAULT_COST)?,
-            roles: vec![Role::Authenticated],
-        })
-        .get_result::<User>(&conn)
-        .map_err(|e| match e {
-            DatabaseError(DatabaseErrorKind::UniqueViolation, _info) => {
-                Error::from_custom_validation("email_taken", "email", "Email is taken")
-            }
-            _ => Error::from(e),
-        })?;
+    // Create user, and welcome them if it's saved
+    let user = conn.transaction::<_, Error, _>(|| {
+        let user = diesel::insert_into(users)
+            .values(&NewUser {
+                name: input.name,
+                email: input.email,
+                password_hash: bcrypt::hash(&input.password, bcrypt::DEFAULT_COST)?,
+                roles: vec![Role::Authenticated],
+            })
+            .get_result::<User>(&conn)
+            .map_err(|e| match e {
+                DatabaseError(DatabaseErrorKind::UniqueViolation, _info) => {
+                    Error::from_conflict("email_taken", "email", "Email is taken")
+                }
+                _ => Error::from(e),
+            })?;
+
+        ::email::queue(
+            &conn,
+            &user.email,
+            Some(user.id),
+            &EmailTemplate::Welcome {
+                name: user.name.clone(),
+            },
+        )?;
+
+        Ok(user)
+    })?;
 
     let session = self::create_session(conn, &user)?;
 
@@ -225,7 +253,7 @@ pub fn create_user(user: CurrentUser, conn: Conn, input: CreateUserInput) -> Res
         .get_result::<User>(&conn.0)
         .map_err(|e| match e {
             DatabaseError(DatabaseErrorKind::UniqueViolation, _info) => {
-                Error::from_custom_validation("email_taken", "email", "Email is taken")
+                Error::from_conflict("email_taken", "email", "Email is taken")
             }
             _ => Error::from(e),
         })?;
@@ -267,7 +295,7 @@ pub fn create_profile(
         .get_result::<Profile>(&conn.0)
         .map_err(|e| match e {
             DatabaseError(DatabaseErrorKind::UniqueViolation, _info) => {
-                Error::from_custom_validation("profile_exists", "profile", "Profile exists")
+                Error::from_conflict("profile_exists", "profile", "Profile exists")
             }
             _ => Error::from(e),
         })?;
@@ -44,6 +44,16 @@ pub enum CurrentUser {
     Admin(User),
 }
 
+impl CurrentUser {
+    /// The signed in user's id
+    pub fn id(&self) -> Option<i32> {
+        match *self {
+            CurrentUser::Admin(ref u) | CurrentUser::Authenticated(ref u) => Some(u.id),
+            CurrentUser::Anonymous => None,
+        }
+    }
+}
+
 #[derive(Insertable)]
 #[table_name = "users"]
 pub struct NewUser {
@@ -0,0 +1,38 @@
+//
+// csv.rs
+//
+
+/// Builds a CSV document row by row
+#[derive(Default)]
+pub struct Csv {
+    out: String,
+}
+
+impl Csv {
+    pub fn new() -> Self {
+        Csv::default()
+    }
+
+    pub fn row<S: AsRef<str>>(&mut self, fields: &[S]) {
+        let line = fields
+            .iter()
+            .map(|f| escape(f.as_ref()))
+            .collect::<Vec<String>>()
+            .join(",");
+        self.out.push_str(&line);
+        self.out.push_str("\r\n");
+    }
+
+    pub fn into_string(self) -> String {
+        self.out
+    }
+}
+
+/// Quote a field when it contains a separator, quote or line break
+fn escape(field: &str) -> String {
+    if field.contains(|c: char| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
@@ -44,6 +44,19 @@ pub fn create_pool() -> ConnectionPool {
         .expect("Failed to create pool.")
 }
 
+/// Create a pool for background threads that need fewer connections than
+/// the web server
+pub fn create_sized_pool(size: u32) -> ConnectionPool {
+    dotenv().ok();
+
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let manager = ConnectionManager::new(database_url);
+    r2d2::Pool::builder()
+        .max_size(size)
+        .build(manager)
+        .expect("Failed to create pool.")
+}
+
 /// Get single connection
 pub fn single_connection() -> PgConnection {
     dotenv().ok();
@@ -7,9 +7,153 @@ use accounts::types::{CurrentUser, CurrentUser::*};
 use db::Conn;
 use deals::types::Deal;
 use deals::types::*;
+use diesel::pg::PgConnection;
 use diesel::prelude::*;
+use events;
+use events::types::{Audience, Event};
+use geocoding::Geocoder;
+use houses;
+use houses::address;
+use houses::geo::SearchArea;
+use houses::types::House;
+use notifications;
+use notifications::types::NotificationKind;
+use rand::Rng;
 use result::{Error, Payload, Response};
+use serde_json::json;
+use tasks;
 use validator::Validate;
+use webhooks;
+use webhooks::types::WebhookEvent;
+
+/// Characters used in access codes. Ones that are easy to misread on
+/// paper (0 and O, 1, I and L) are left out.
+const ACCESS_CODE_CHARS: &[u8] = b"23456789ABCDEFGHJKMNPQRSTUVWXYZ";
+const ACCESS_CODE_LENGTH: usize = 8;
+
+/// Unclaimed deals left alone this long after being mailed get a new
+/// access code, so the one on the old letter stops working
+const ACCESS_CODE_MAX_AGE_DAYS: i64 = 90;
+
+///
+/// Helpers
+///
+
+/// Generate an access code no other deal is using
+fn new_access_code(conn: &PgConnection) -> Result<String, Error> {
+    use schema::deals::dsl::*;
+
+    let mut rng = rand::thread_rng();
+    loop {
+        let code = (0..ACCESS_CODE_LENGTH)
+            .map(|_| ACCESS_CODE_CHARS[rng.gen_range(0, ACCESS_CODE_CHARS.len())] as char)
+            .collect::<String>();
+        let taken = diesel::select(diesel::dsl::exists(deals.filter(access_code.eq(&code))))
+            .get_result::<bool>(conn)?;
+        if !taken {
+            return Ok(code);
+        }
+    }
+}
+
+/// Find a deal by its access code, as typed or scanned
+pub fn find_by_access_code(conn: &PgConnection, code: &str) -> Result<Deal, Error> {
+    use schema::deals::dsl::*;
+
+    deals
+        .filter(access_code.eq(code.trim().to_uppercase()))
+        .first::<Deal>(conn)
+        .map_err(|e| Error::from(e))
+}
+
+/// Replace the access codes of mailed deals nobody claimed in time.
+///
+/// Rotating a code leaves `updated` alone, since other housekeeping goes
+/// by how long a deal has been left.
+pub fn expire_access_codes(conn: &PgConnection) -> Result<usize, Error> {
+    use schema::{deals, mailers};
+
+    let now = chrono::Utc::now().naive_utc();
+    let cutoff = now - chrono::Duration::days(ACCESS_CODE_MAX_AGE_DAYS);
+    let mailed = mailers::table.select(mailers::deal_id);
+    let stale = deals::table
+        .select(deals::id)
+        .filter(deals::seller_id.is_null())
+        .filter(deals::updated.lt(cutoff))
+        .filter(
+            deals::access_code_rotated
+                .is_null()
+                .or(deals::access_code_rotated.lt(cutoff)),
+        )
+        .filter(deals::id.eq_any(mailed))
+        .load::<i32>(conn)?;
+
+    for deal_id in &stale {
+        let code = new_access_code(conn)?;
+        diesel::update(deals::table.find(deal_id))
+            .set((
+                deals::access_code.eq(code),
+                deals::access_code_rotated.eq(Some(now)),
+            ))
+            .execute(conn)?;
+    }
+    Ok(stale.len())
+}
+
+/// Move a deal to a new status.
+///
+/// Every status change goes through here, so anything that should happen
+/// when a deal enters a status belongs here too. Its writes only make sense
+/// together, so call it inside a transaction. `actor` made the change, and
+/// isn't notified of it.
+pub fn set_status(
+    conn: &PgConnection,
+    deal: &Deal,
+    new_status: DealStatus,
+    actor: Option<i32>,
+) -> Result<Deal, Error> {
+    use schema::deals::dsl::*;
+
+    let updated_deal = diesel::update(deal)
+        .set((
+            status.eq(new_status),
+            updated.eq(chrono::Utc::now().naive_utc()),
+        ))
+        .get_result::<Deal>(conn)?;
+    events::publish(
+        conn,
+        Audience::deal(&updated_deal),
+        Event::DealUpdated {
+            deal: updated_deal.clone(),
+        },
+    )?;
+
+    if deal.status != new_status {
+        tasks::apply_checklists(conn, &updated_deal)?;
+        let recipients = notifications::parties(&updated_deal)
+            .into_iter()
+            .filter(|p| Some(*p) != actor)
+            .collect::<Vec<i32>>();
+        notifications::notify(
+            conn,
+            &updated_deal,
+            &recipients,
+            NotificationKind::StatusChanged,
+            format!("{} is now {}", updated_deal.title, new_status.label()),
+        )?;
+        webhooks::trigger(
+            conn,
+            WebhookEvent::DealStatusChanged,
+            json!({ "deal": updated_deal, "previous_status": deal.status }),
+        )?;
+    }
+
+    Ok(updated_deal)
+}
+
+///
+/// Public API
+///
 
 /// Get deals
 pub fn get_deals(query: Option<DealsQuery>, user: CurrentUser, conn: Conn) -> Response<Vec<Deal>> {
@@ -43,13 +187,16 @@ pub fn get_deals(query: Option<DealsQuery>, user: CurrentUser, conn: Conn) -> Re
 }
 
 /// Create deal
+///
+/// When the client doesn't send a google address, the address is geocoded
+/// here and must resolve to a street address.
 pub fn create_deal(
     user: CurrentUser,
     conn: Conn,
+    geocoder: &dyn Geocoder,
     input: CreateDealAndHouseInput,
 ) -> Response<Deal> {
     use schema::deals::dsl::*;
-    use schema::houses;
 
     // Currently only admins can create deals
     let _ = match user {
@@ -62,57 +209,80 @@ pub fn create_deal(
 
     input.validate()?;
 
-    // Look for a house with address
-    let hid = match houses::table
-        .select(houses::dsl::id)
-        .filter(houses::dsl::address.eq(&formatted_address))
-        .first::<i32>(&conn)
-    {
-        Ok(house) => house,
-        Err(diesel::NotFound) => diesel::insert_into(houses::table)
-            .values(&NewHouse {
-                address: formatted_address.clone(),
-                created: chrono::Utc::now().naive_utc(),
-                updated: chrono::Utc::now().naive_utc(),
-                google_address: Some(serde_json::to_value(input.google_address)?),
-            })
-            .returning(houses::dsl::id)
-            .get_result::<i32>(&conn)?,
-        Err(e) => return Err(Error::from(e)),
-    };
-
-    // Create a deal and link it to the house and buyer
-    // Make sure one doesn't exist already
-    let deal = match deals
-        .filter(house_id.eq(&hid))
-        .filter(buyer_id.eq(&input.buyer_id))
-        .first::<Deal>(&conn)
-    {
-        Ok(_) => {
-            return Err(Error::from_custom_validation(
-                "deal_exists",
-                "address",
-                "Existing deal for address",
-            ));
+    let (formatted_address, google_address) = match input.google_address {
+        Some(g) => {
+            g.validate()?;
+            (formatted_address, g)
+        }
+        None => {
+            let g = match geocoder.geocode(&formatted_address)? {
+                Some(g) => g,
+                None => {
+                    return Err(Error::from_custom_validation(
+                        "address_not_found",
+                        "address",
+                        "Address could not be found",
+                    ));
+                }
+            };
+            let fields = address::fields_from_google(&g.formatted_address, Some(&g));
+            if fields.street_number.is_none() || fields.route.is_none() {
+                return Err(Error::from_custom_validation(
+                    "address_not_specific",
+                    "address",
+                    "Address must include a street number and street",
+                ));
+            }
+            (g.formatted_address.clone(), g)
         }
-        Err(diesel::NotFound) => diesel::insert_into(deals)
-            .values(&NewDeal {
-                buyer_id: Some(input.buyer_id),
-                seller_id: None,
-                house_id: Some(hid),
-                access_code: "CODE".to_string(),
-                status: DealStatus::Initialized,
-                created: chrono::Utc::now().naive_utc(),
-                updated: chrono::Utc::now().naive_utc(),
-                title: formatted_address.clone(),
-            })
-            .get_result::<Deal>(&conn)?,
-        Err(e) => return Err(Error::from(e)),
     };
 
+    let deal = conn.transaction::<_, Error, _>(|| {
+        // Look for a house with address
+        let hid = houses::find_or_create(&conn, &formatted_address, Some(google_address))?;
+
+        // Create a deal and link it to the house and buyer
+        // Make sure one doesn't exist already
+        let deal = match deals
+            .filter(house_id.eq(&hid))
+            .filter(buyer_id.eq(&input.buyer_id))
+            .first::<Deal>(&conn)
+        {
+            Ok(_) => {
+                return Err(Error::from_conflict(
+                    "deal_exists",
+                    "address",
+                    "Existing deal for address",
+                ));
+            }
+            Err(diesel::NotFound) => diesel::insert_into(deals)
+                .values(&NewDeal {
+                    buyer_id: Some(input.buyer_id),
+                    seller_id: None,
+                    house_id: Some(hid),
+                    access_code: new_access_code(&conn)?,
+                    status: DealStatus::Initialized,
+                    created: chrono::Utc::now().naive_utc(),
+                    updated: chrono::Utc::now().naive_utc(),
+                    title: formatted_address.clone(),
+                })
+                .get_result::<Deal>(&conn)?,
+            Err(e) => return Err(Error::from(e)),
+        };
+        tasks::apply_checklists(&conn, &deal)?;
+        events::publish(
+            &conn,
+            Audience::deal(&deal),
+            Event::DealCreated { deal: deal.clone() },
+        )?;
+        webhooks::trigger(&conn, WebhookEvent::DealCreated, json!({ "deal": deal }))?;
+        Ok(deal)
+    })?;
+
     Ok(Payload {
         data: deal,
         success: true,
+        error_code: None,
         error_message: None,
         validation_errors: None,
         page_info: None,
@@ -129,78 +299,193 @@ pub fn update_deal(
     use schema::deals::dsl::*;
 
     // Currently only admins can create deals
-    let _ = match user {
+    let user = match user {
         Admin(user) => user,
         _ => return Err(Error::AccessDenied),
     };
     let Conn(conn) = conn;
 
-    let deal = deals.filter(id.eq(deal_id)).first::<Deal>(&conn)?;
+    let deal = conn.transaction::<_, Error, _>(|| {
+        let deal = deals
+            .filter(id.eq(deal_id))
+            .for_update()
+            .first::<Deal>(&conn)?;
 
-    // If the field is set, use the value
-    // If it is not set, ignore.
-    let deal = diesel::update(&deal)
-        .set((
-            status.eq(input.status.unwrap_or(deal.status)),
-            updated.eq(chrono::Utc::now().naive_utc()),
-        ))
-        .get_result(&conn)?;
+        // If the field is set, use the value
+        // If it is not set, ignore.
+        set_status(
+            &conn,
+            &deal,
+            input.status.unwrap_or(deal.status),
+            Some(user.id),
+        )
+    })?;
 
     Ok(Payload {
         data: deal,
         success: true,
+        error_code: None,
         error_message: None,
         validation_errors: None,
         page_info: None,
     })
 }
 
-// /// Deals with houses
-// pub fn deals_with_houses(
-//     query: Option<ViewDealsWithHousesQuery>,
-//     user: CurrentUser,
-//     conn: Conn,
-// ) -> Response<Vec<DealWithHouse>> {
-//     use schema::deals;
-//     use schema::houses;
-//
-//     let user = match user {
-//         Admin(user) => user,
-//         _ => return Err(Error::AccessDenied),
-//     };
-//     let Conn(conn) = conn;
-//
-//     let bid = match query {
-//         Some(q) => match q.buyer_id {
-//             Some(b) => b,
-//             None => user.id,
-//         },
-//         None => user.id,
-//     };
-//
-//     let d = deals::table
-//         .inner_join(houses::table)
-//         .select((
-//             deals::id,
-//             deals::buyer_id,
-//             deals::seller_id,
-//             deals::house_id,
-//             deals::access_code,
-//             deals::status,
-//             houses::address,
-//             houses::lat,
-//             houses::lon,
-//         ))
-//         .filter(deals::dsl::buyer_id.eq(bid))
-//         .limit(10)
-//         .order_by(deals::created.desc())
-//         .load::<DealWithHouse>(&conn)?;
-//
-//     Ok(Payload {
-//         data: d,
-//         success: true,
-//         error_message: None,
-//         validation_errors: None,
-//         page_info: None,
-//     })
-// }
+/// Deals with houses
+///
+/// With `near` (and optionally `radius_m`) or `bbox`, only deals on houses
+/// in that area are returned, nearest first. Those are not limited to the
+/// current user's deals unless `buyer_id` is given.
+pub fn deals_with_houses(
+    query: Option<ViewDealsWithHousesQuery>,
+    user: CurrentUser,
+    conn: Conn,
+) -> Response<Vec<DealWithHouse>> {
+    use schema::deals;
+    use schema::houses;
+
+    let user = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let query = query.unwrap_or_default();
+    let area = SearchArea::from_query(query.near, query.radius_m, query.bbox)?;
+
+    let bid = match (query.buyer_id, &area) {
+        (Some(b), _) => Some(b),
+        (None, &None) => Some(user.id),
+        (None, &Some(_)) => None,
+    };
+
+    let mut q = deals::table.left_join(houses::table).into_boxed();
+    if let Some(b) = bid {
+        q = q.filter(deals::buyer_id.eq(b));
+    }
+
+    let d = match area {
+        Some(area) => {
+            let b = area.bbox;
+            q = q.filter(houses::lat.between(b.south, b.north));
+            q = if area.crosses_antimeridian() {
+                q.filter(houses::lng.ge(b.west).or(houses::lng.le(b.east)))
+            } else {
+                q.filter(houses::lng.between(b.west, b.east))
+            };
+            let in_box = q
+                .order_by(area.distance_order("houses"))
+                .limit(1000)
+                .load::<(Deal, Option<House>)>(&conn)?;
+
+            area.nearest_first(in_box, |(_, house)| {
+                house.as_ref().and_then(|h| h.coordinates())
+            })
+            .into_iter()
+            .take(30)
+            .map(|((deal, house), distance_m)| DealWithHouse::new(deal, house, distance_m))
+            .collect()
+        }
+        None => q
+            .limit(30)
+            .order_by(deals::created.desc())
+            .load::<(Deal, Option<House>)>(&conn)?
+            .into_iter()
+            .map(|(deal, house)| DealWithHouse::new(deal, house, None))
+            .collect(),
+    };
+
+    Ok(Payload {
+        data: d,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Get what a homeowner sees after following a mailer's link
+///
+/// This is public, since the homeowner may not have an account yet.
+pub fn get_claim(code: String, conn: Conn) -> Response<DealClaim> {
+    use schema::{houses, users};
+
+    let Conn(conn) = conn;
+
+    let deal = find_by_access_code(&conn, &code)?;
+    let address = match deal.house_id {
+        Some(hid) => houses::table
+            .find(hid)
+            .select(houses::address)
+            .first::<String>(&conn)
+            .optional()?,
+        None => None,
+    };
+    let buyer_name = match deal.buyer_id {
+        Some(bid) => users::table
+            .find(bid)
+            .select(users::name)
+            .first::<String>(&conn)
+            .optional()?,
+        None => None,
+    };
+
+    Ok(Payload {
+        data: DealClaim {
+            access_code: deal.access_code,
+            address: address.unwrap_or_default(),
+            buyer_name: buyer_name.unwrap_or_default(),
+            claimed: deal.seller_id.is_some(),
+        },
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Claim a deal as its seller
+///
+/// Claiming again as the same seller is a no-op.
+pub fn claim_deal(code: String, user: CurrentUser, conn: Conn) -> Response<Deal> {
+    use schema::deals::dsl::*;
+
+    let user = match user {
+        Admin(user) => user,
+        Authenticated(user) => user,
+        Anonymous => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let deal = conn.transaction::<_, Error, _>(|| {
+        let deal = deals
+            .filter(access_code.eq(code.trim().to_uppercase()))
+            .for_update()
+            .first::<Deal>(&conn)?;
+
+        if deal.buyer_id == Some(user.id) {
+            return Err(Error::from_custom_validation(
+                "own_deal",
+                "access_code",
+                "You are the buyer on this deal",
+            ));
+        }
+        match deal.seller_id {
+            Some(s) if s == user.id => Ok(deal),
+            Some(_) => Err(Error::from_conflict(
+                "deal_claimed",
+                "access_code",
+                "Deal has already been claimed",
+            )),
+            None => diesel::update(&deal)
+                .set((
+                    seller_id.eq(user.id),
+                    updated.eq(chrono::Utc::now().naive_utc()),
+                ))
+                .get_result::<Deal>(&conn)
+                .map_err(|e| Error::from(e)),
+        }
+    })?;
+
+    Ok(Payload {
+        data: deal,
+        success: true,
+        ..Default::default()
+    })
+}
@@ -1,19 +1,26 @@
 //
 // deal/types.rs
 //
+use accounts::types::{CurrentUser, CurrentUser::*};
 use diesel::deserialize::{self, FromSql};
 use diesel::pg::Pg;
 use diesel::serialize::{self, IsNull, Output, ToSql};
 use diesel::sql_types::Varchar;
-use schema::{deals, houses};
+use houses::types::{BoundingBox, Coordinates, GoogleAddress, House, Location};
+use rocket::http::RawStr;
+use rocket::request::FromFormValue;
+use schema::deals;
 use std::io::Write;
 use validator::Validate;
 
-#[derive(Deserialize, Serialize, Debug, Copy, Clone, AsExpression, FromSqlRow)]
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, AsExpression, FromSqlRow)]
 #[sql_type = "Varchar"]
 pub enum DealStatus {
     Initialized,
     MailerSent,
+    SellerInterested,
+    SellerDeclined,
+    UnderContract,
 }
 
 impl ToSql<Varchar, Pg> for DealStatus {
@@ -21,6 +28,9 @@ impl ToSql<Varchar, Pg> for DealStatus {
         match *self {
             DealStatus::Initialized => out.write_all(b"initialized")?,
             DealStatus::MailerSent => out.write_all(b"mailer_sent")?,
+            DealStatus::SellerInterested => out.write_all(b"seller_interested")?,
+            DealStatus::SellerDeclined => out.write_all(b"seller_declined")?,
+            DealStatus::UnderContract => out.write_all(b"under_contract")?,
         }
 
         Ok(IsNull::No)
@@ -32,11 +42,53 @@ impl FromSql<Varchar, Pg> for DealStatus {
         match not_none!(bytes) {
             b"initialized" => Ok(DealStatus::Initialized),
             b"mailer_sent" => Ok(DealStatus::MailerSent),
+            b"seller_interested" => Ok(DealStatus::SellerInterested),
+            b"seller_declined" => Ok(DealStatus::SellerDeclined),
+            b"under_contract" => Ok(DealStatus::UnderContract),
             _ => Err("Unrecognized enum variant".into()),
         }
     }
 }
 
+impl<'v> FromFormValue<'v> for DealStatus {
+    type Error = &'v RawStr;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, &'v RawStr> {
+        match form_value.as_str() {
+            "initialized" => Ok(DealStatus::Initialized),
+            "mailer_sent" => Ok(DealStatus::MailerSent),
+            "seller_interested" => Ok(DealStatus::SellerInterested),
+            "seller_declined" => Ok(DealStatus::SellerDeclined),
+            "under_contract" => Ok(DealStatus::UnderContract),
+            _ => Err(form_value),
+        }
+    }
+}
+
+impl DealStatus {
+    /// Whether the deal is still being worked
+    pub fn is_active(&self) -> bool {
+        match *self {
+            DealStatus::Initialized => true,
+            DealStatus::MailerSent => true,
+            DealStatus::SellerInterested => true,
+            DealStatus::SellerDeclined => false,
+            DealStatus::UnderContract => true,
+        }
+    }
+
+    /// How the status reads in messages
+    pub fn label(&self) -> &'static str {
+        match *self {
+            DealStatus::Initialized => "new",
+            DealStatus::MailerSent => "mailed",
+            DealStatus::SellerInterested => "seller interested",
+            DealStatus::SellerDeclined => "seller declined",
+            DealStatus::UnderContract => "under contract",
+        }
+    }
+}
+
 impl Default for DealStatus {
     fn default() -> Self {
         DealStatus::Initialized
@@ -55,6 +107,38 @@ pub struct Deal {
     pub created: chrono::NaiveDateTime,
     pub updated: chrono::NaiveDateTime,
     pub title: String,
+    /// When the access code was last replaced for going unclaimed
+    pub access_code_rotated: Option<chrono::NaiveDateTime>,
+}
+
+/// How a user takes part in a deal
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DealRole {
+    Buyer,
+    Seller,
+    /// An admin who is neither the buyer nor the seller
+    Admin,
+}
+
+impl Deal {
+    /// The user's role on this deal, if they have one
+    pub fn role_of(&self, user: &CurrentUser) -> Option<DealRole> {
+        let (user, is_admin) = match *user {
+            Admin(ref user) => (user, true),
+            Authenticated(ref user) => (user, false),
+            Anonymous => return None,
+        };
+
+        if self.buyer_id == Some(user.id) {
+            Some(DealRole::Buyer)
+        } else if self.seller_id == Some(user.id) {
+            Some(DealRole::Seller)
+        } else if is_admin {
+            Some(DealRole::Admin)
+        } else {
+            None
+        }
+    }
 }
 
 #[derive(Insertable)]
@@ -75,31 +159,6 @@ pub struct UpdateDeal {
     pub status: Option<DealStatus>,
 }
 
-#[derive(Serialize, Identifiable, Clone, Queryable, Debug)]
-#[table_name = "houses"]
-pub struct House {
-    pub id: i32,
-    pub address: String,
-    pub created: chrono::NaiveDateTime,
-    pub updated: chrono::NaiveDateTime,
-    pub google_address: Option<serde_json::Value>,
-}
-
-#[derive(Insertable)]
-#[table_name = "houses"]
-pub struct NewHouse {
-    pub address: String,
-    pub created: chrono::NaiveDateTime,
-    pub updated: chrono::NaiveDateTime,
-    pub google_address: Option<serde_json::Value>,
-}
-
-#[derive(Clone)]
-pub struct HouseInput {
-    pub address: String,
-    pub google_address: Option<serde_json::Value>,
-}
-
 #[derive(Deserialize, Validate)]
 pub struct CreateDealAndHouseInput {
     pub buyer_id: i32,
@@ -108,48 +167,63 @@ pub struct CreateDealAndHouseInput {
     pub google_address: Option<GoogleAddress>,
 }
 
-#[derive(Deserialize, Serialize, Validate, Debug)]
-pub struct GoogleAddress {
-    pub address_components: Vec<AddressComponents>,
-    #[validate(length(min = "0", max = "500", message = "Too long"))]
-    pub formatted_address: String,
-    pub geometry: Geometry,
-    #[validate(length(min = "0", max = "500", message = "Too long"))]
-    pub place_id: String,
-    pub types: Vec<String>,
-}
-
-#[derive(Deserialize, Serialize, Validate, Debug)]
-pub struct Geometry {
-    pub location: Location,
-    #[validate(length(min = "0", max = "500", message = "Too long"))]
-    pub location_type: String,
-    pub viewport: Viewport,
-}
-
-#[derive(Deserialize, Serialize, Debug)]
-pub struct Viewport {
-    pub northeast: Location,
-    pub southwest: Location,
-}
-
-#[derive(Deserialize, Serialize, Debug)]
-pub struct Location {
-    pub latitude: f32,
-    pub longitude: f32,
+#[derive(FromForm, Deserialize, Debug)]
+pub struct DealsQuery {
+    pub buyer_id: Option<i32>,
 }
 
-#[derive(Deserialize, Serialize, Validate, Debug)]
-pub struct AddressComponents {
-    #[validate(length(min = "0", max = "500", message = "Too long"))]
-    long_name: String,
-    #[validate(length(min = "0", max = "500", message = "Too long"))]
-    short_name: String,
-    #[validate(length(min = "0", max = "500", message = "Too long"))]
-    types: Vec<String>,
+#[derive(FromForm, Deserialize, Debug, Default)]
+pub struct ViewDealsWithHousesQuery {
+    pub buyer_id: Option<i32>,
+    /// Center point as `lat,lng`
+    pub near: Option<Coordinates>,
+    pub radius_m: Option<f64>,
+    /// Bounding box as `south,west,north,east`
+    pub bbox: Option<BoundingBox>,
+}
+
+/// A deal joined with its house and the address data parsed out of
+/// `google_address`
+#[derive(Serialize)]
+pub struct DealWithHouse {
+    pub deal: Deal,
+    pub house: Option<House>,
+    pub formatted_address: Option<String>,
+    pub location: Option<Location>,
+    /// Meters from the `near` point, when one was given
+    pub distance_m: Option<f64>,
+}
+
+impl DealWithHouse {
+    pub fn new(deal: Deal, house: Option<House>, distance_m: Option<f64>) -> Self {
+        let formatted_address = house
+            .as_ref()
+            .and_then(|h| h.parsed_google_address())
+            .map(|a| a.formatted_address);
+        let location = house
+            .as_ref()
+            .and_then(|h| h.coordinates())
+            .map(|c| Location {
+                latitude: c.lat,
+                longitude: c.lng,
+            });
+
+        DealWithHouse {
+            deal,
+            house,
+            formatted_address,
+            location,
+            distance_m,
+        }
+    }
 }
 
-#[derive(FromForm, Deserialize, Debug)]
-pub struct DealsQuery {
-    pub buyer_id: Option<i32>,
+/// Public view of a deal, shown to a homeowner who followed a mailer
+#[derive(Serialize)]
+pub struct DealClaim {
+    pub access_code: String,
+    pub address: String,
+    pub buyer_name: String,
+    /// Whether a seller has claimed the deal already
+    pub claimed: bool,
 }
@@ -0,0 +1,249 @@
+//
+// documents/mod.rs
+//
+pub mod types;
+
+use self::types::*;
+use accounts::types::{CurrentUser, CurrentUser::*};
+use db::Conn;
+use deals::types::Deal;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use rand::Rng;
+use result::{Error, Payload, Response};
+use sha2::{Digest, Sha256};
+use storage::Storage;
+
+/// Largest document accepted, in bytes
+pub const MAX_DOCUMENT_SIZE: usize = 20 * 1024 * 1024;
+
+/// Content types that can be uploaded, with the bytes files of each type
+/// start with
+const ALLOWED_TYPES: &[(&str, &[u8])] = &[
+    ("application/pdf", b"%PDF-"),
+    ("image/jpeg", b"\xFF\xD8\xFF"),
+    ("image/png", b"\x89PNG"),
+    ("application/msword", b"\xD0\xCF\x11\xE0"),
+    (
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        b"PK\x03\x04",
+    ),
+];
+
+///
+/// Helpers
+///
+
+/// Load a deal the user takes part in
+fn load_deal(conn: &PgConnection, deal_id: i32, user: &CurrentUser) -> Result<Deal, Error> {
+    use schema::deals::dsl::*;
+
+    let deal = deals.find(deal_id).first::<Deal>(conn)?;
+    match deal.role_of(user) {
+        Some(_) => Ok(deal),
+        None => Err(Error::AccessDenied),
+    }
+}
+
+fn load_document(
+    conn: &PgConnection,
+    deal: &Deal,
+    document_id: i32,
+) -> Result<DealDocument, Error> {
+    use schema::deal_documents::dsl::*;
+
+    deal_documents
+        .find(document_id)
+        .filter(deal_id.eq(deal.id))
+        .first::<DealDocument>(conn)
+        .map_err(|e| Error::from(e))
+}
+
+/// Check the declared content type is allowed and matches the file,
+/// returning it without parameters
+fn check_content_type(content_type: &str, bytes: &[u8]) -> Result<String, Error> {
+    let content_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_lowercase();
+
+    match ALLOWED_TYPES.iter().find(|(t, _)| *t == content_type) {
+        Some((_, signature)) if bytes.starts_with(signature) => Ok(content_type),
+        Some(_) => Err(Error::from_custom_validation(
+            "content_mismatch",
+            "content_type",
+            "File contents don't match its type",
+        )),
+        None => Err(Error::from_custom_validation(
+            "unsupported_type",
+            "content_type",
+            "Only PDF, JPEG, PNG and Word documents can be uploaded",
+        )),
+    }
+}
+
+/// Strip any directory and characters that don't belong in a filename
+fn clean_filename(filename: &str) -> String {
+    filename
+        .rsplit(|c| c == '/' || c == '\\')
+        .next()
+        .unwrap_or("")
+        .chars()
+        .filter(|c| !c.is_control() && *c != '"')
+        .take(255)
+        .collect::<String>()
+        .trim()
+        .to_owned()
+}
+
+/// Random storage key under the deal's prefix
+fn storage_key(deal: &Deal) -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill(&mut bytes);
+    format!("deals/{}/{}", deal.id, hex::encode(bytes))
+}
+
+///
+/// Public API
+///
+
+/// Get a deal's documents, newest first
+pub fn get_documents(deal_id: i32, user: CurrentUser, conn: Conn) -> Response<Vec<DealDocument>> {
+    use schema::deal_documents;
+
+    let Conn(conn) = conn;
+
+    let deal = load_deal(&conn, deal_id, &user)?;
+    let d = deal_documents::table
+        .filter(deal_documents::deal_id.eq(deal.id))
+        .order_by(deal_documents::created.desc())
+        .load::<DealDocument>(&conn)?;
+
+    Ok(Payload {
+        data: d,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Upload a document to a deal
+pub fn upload_document(
+    deal_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    storage: &dyn Storage,
+    input: UploadDocumentInput,
+) -> Response<DealDocument> {
+    use schema::deal_documents;
+
+    let Conn(conn) = conn;
+
+    let deal = load_deal(&conn, deal_id, &user)?;
+
+    let filename = clean_filename(&input.filename);
+    if filename.is_empty() {
+        return Err(Error::from_custom_validation(
+            "filename_required",
+            "filename",
+            "Filename is required",
+        ));
+    }
+    if input.bytes.is_empty() {
+        return Err(Error::from_custom_validation(
+            "empty_file",
+            "file",
+            "File is empty",
+        ));
+    }
+    if input.bytes.len() > MAX_DOCUMENT_SIZE {
+        return Err(Error::from_custom_validation(
+            "file_too_large",
+            "file",
+            "Documents can be at most 20 MB",
+        ));
+    }
+    let content_type = check_content_type(&input.content_type, &input.bytes)?;
+
+    let key = storage_key(&deal);
+    storage.put(&key, &content_type, &input.bytes)?;
+
+    let d = diesel::insert_into(deal_documents::table)
+        .values(&NewDealDocument {
+            deal_id: deal.id,
+            uploaded_by: match user {
+                Admin(ref u) | Authenticated(ref u) => Some(u.id),
+                Anonymous => None,
+            },
+            filename,
+            content_type,
+            size_bytes: input.bytes.len() as i32,
+            sha256: hex::encode(Sha256::digest(&input.bytes)),
+            storage_key: key.clone(),
+            created: chrono::Utc::now().naive_utc(),
+        })
+        .get_result::<DealDocument>(&conn);
+
+    // Don't leave the file behind when the record couldn't be saved
+    let d = match d {
+        Ok(d) => d,
+        Err(e) => {
+            if let Err(e) = storage.delete(&key) {
+                println!("{:?}", e);
+            }
+            return Err(Error::from(e));
+        }
+    };
+
+    Ok(Payload {
+        data: d,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Download a document, along with its record
+pub fn download_document(
+    deal_id: i32,
+    document_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    storage: &dyn Storage,
+) -> Result<(DealDocument, Vec<u8>), Error> {
+    let Conn(conn) = conn;
+
+    let deal = load_deal(&conn, deal_id, &user)?;
+    let document = load_document(&conn, &deal, document_id)?;
+    let bytes = storage.get(&document.storage_key)?;
+
+    Ok((document, bytes))
+}
+
+/// Delete a document. Only admins and the uploader can.
+pub fn delete_document(
+    deal_id: i32,
+    document_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    storage: &dyn Storage,
+) -> Response<DealDocument> {
+    let Conn(conn) = conn;
+
+    let deal = load_deal(&conn, deal_id, &user)?;
+    let document = load_document(&conn, &deal, document_id)?;
+    match user {
+        Admin(_) => (),
+        Authenticated(ref u) if document.uploaded_by == Some(u.id) => (),
+        _ => return Err(Error::AccessDenied),
+    }
+
+    diesel::delete(&document).execute(&conn)?;
+    storage.delete(&document.storage_key)?;
+
+    Ok(Payload {
+        data: document,
+        success: true,
+        ..Default::default()
+    })
+}
@@ -0,0 +1,45 @@
+//
+// documents/types.rs
+//
+use schema::deal_documents;
+
+#[derive(Serialize, Identifiable, Clone, Queryable, Debug)]
+#[table_name = "deal_documents"]
+pub struct DealDocument {
+    pub id: i32,
+    pub deal_id: i32,
+    pub uploaded_by: Option<i32>,
+    pub filename: String,
+    pub content_type: String,
+    pub size_bytes: i32,
+    /// Hex SHA-256 of the contents
+    pub sha256: String,
+    #[serde(skip_serializing)]
+    pub storage_key: String,
+    pub created: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "deal_documents"]
+pub struct NewDealDocument {
+    pub deal_id: i32,
+    pub uploaded_by: Option<i32>,
+    pub filename: String,
+    pub content_type: String,
+    pub size_bytes: i32,
+    pub sha256: String,
+    pub storage_key: String,
+    pub created: chrono::NaiveDateTime,
+}
+
+/// A file as uploaded
+pub struct UploadDocumentInput {
+    pub filename: String,
+    pub content_type: String,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(FromForm, Debug)]
+pub struct UploadDocumentQuery {
+    pub filename: String,
+}
@@ -0,0 +1,204 @@
+//
+// email/mod.rs
+//
+pub mod log;
+pub mod outbox;
+pub mod smtp;
+pub mod templates;
+pub mod types;
+
+use self::log::LogMailer;
+use self::outbox::OutboxMailer;
+use self::smtp::SmtpMailer;
+use self::types::*;
+use accounts::types::{CurrentUser, CurrentUser::*};
+use db::Conn;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use dotenv::dotenv;
+use jobs;
+use jobs::types::JobPayload;
+use result::{Error, Payload, Response};
+use rocket::config::Environment;
+use std::env;
+
+/// Most emails a single listing returns
+const MAX_EMAILS: i64 = 200;
+
+/// Times an email is tried before it's marked failed
+const MAX_ATTEMPTS: i32 = 8;
+
+/// Delivers rendered emails
+pub trait Mailer: Send + Sync {
+    fn send(&self, email: &Email) -> Result<(), Error>;
+}
+
+/// Build the mailer selected by `MAILER`.
+///
+/// `smtp` needs `SMTP_HOST`, plus `SMTP_USERNAME` and `SMTP_PASSWORD` if the
+/// server wants them. `outbox` writes emails under `OUTBOX_PATH`, `outbox`
+/// by default. `log` only prints who each email is for. Without `MAILER`,
+/// the outbox is used in development, and anywhere else emails are logged,
+/// with a warning at startup, rather than quietly written to disk. Mail
+/// comes from `MAIL_FROM`.
+pub fn from_env() -> Result<Box<dyn Mailer>, String> {
+    dotenv().ok();
+
+    let development = Environment::active().map_or(false, |e| e.is_dev());
+    let kind = match env::var("MAILER") {
+        Ok(kind) => kind,
+        Err(_) if development => "outbox".to_owned(),
+        Err(_) => {
+            println!("MAILER is not set, so emails will be logged instead of sent");
+            "log".to_owned()
+        }
+    };
+
+    match kind.as_str() {
+        "outbox" => Ok(Box::new(OutboxMailer::new(
+            env::var("OUTBOX_PATH").unwrap_or_else(|_| "outbox".to_owned()),
+        ))),
+        "smtp" => {
+            let host = env::var("SMTP_HOST")
+                .map_err(|_| "SMTP_HOST must be set to use smtp".to_owned())?;
+            let credentials = match (env::var("SMTP_USERNAME"), env::var("SMTP_PASSWORD")) {
+                (Ok(username), Ok(password)) => Some((username, password)),
+                _ => None,
+            };
+            Ok(Box::new(SmtpMailer::new(
+                host,
+                credentials,
+                env::var("MAIL_FROM").unwrap_or_else(|_| "no-reply@dwelloapp.com".to_owned()),
+                "Dwello".to_owned(),
+            )))
+        }
+        "log" => Ok(Box::new(LogMailer)),
+        other => Err(format!("Unknown mailer {}", other)),
+    }
+}
+
+///
+/// Helpers
+///
+
+/// Render an email, record it and queue its delivery. Uses the caller's
+/// connection, so nothing is sent if the caller's transaction rolls back.
+pub fn queue(
+    conn: &PgConnection,
+    to: &str,
+    user_id: Option<i32>,
+    message: &EmailTemplate,
+) -> Result<Email, Error> {
+    use schema::emails;
+
+    let rendered = templates::render(message);
+    let now = chrono::Utc::now().naive_utc();
+    let email = diesel::insert_into(emails::table)
+        .values(&NewEmail {
+            user_id,
+            to_address: to.to_owned(),
+            template: rendered.template.to_owned(),
+            subject: rendered.subject,
+            html_body: rendered.html_body,
+            text_body: rendered.text_body,
+            status: EmailStatus::Queued,
+            created: now,
+            updated: now,
+        })
+        .get_result::<Email>(conn)?;
+
+    jobs::enqueue(conn, &JobPayload::SendEmail { email_id: email.id })?;
+    Ok(email)
+}
+
+/// Send a recorded email and note the outcome. Failed attempts are retried
+/// with the job queue's backoff until `MAX_ATTEMPTS`. Like webhook
+/// deliveries, the email schedules its own retries rather than failing its
+/// job, so the outcome is kept instead of being rolled back with the job.
+pub fn deliver(conn: &PgConnection, mailer: &dyn Mailer, email_id: i32) -> Result<(), Error> {
+    use schema::emails::dsl::*;
+
+    let email = emails.find(email_id).first::<Email>(conn)?;
+    if email.status != EmailStatus::Queued {
+        return Ok(());
+    }
+
+    let failure = mailer.send(&email).err().map(|e| format!("{:?}", e));
+    let now = chrono::Utc::now().naive_utc();
+    let tries = email.attempts + 1;
+    let next = match failure {
+        None => EmailStatus::Sent,
+        Some(_) if tries < MAX_ATTEMPTS => EmailStatus::Queued,
+        Some(_) => EmailStatus::Failed,
+    };
+    diesel::update(emails.find(email_id))
+        .set((
+            status.eq(next),
+            attempts.eq(tries),
+            error.eq(&failure),
+            sent.eq(failure.as_ref().map_or(Some(now), |_| None)),
+            updated.eq(now),
+        ))
+        .execute(conn)?;
+
+    if next == EmailStatus::Queued {
+        jobs::enqueue_at(
+            conn,
+            &JobPayload::SendEmail { email_id },
+            now + jobs::backoff(tries),
+        )?;
+    }
+    Ok(())
+}
+
+///
+/// Public API
+///
+
+/// Get sent and pending emails, newest first
+pub fn get_emails(query: EmailsQuery, user: CurrentUser, conn: Conn) -> Response<Vec<Email>> {
+    use schema::emails::dsl::*;
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let mut q = emails.into_boxed();
+    if let Some(s) = query.status {
+        q = q.filter(status.eq(s));
+    }
+    if let Some(u) = query.user_id {
+        q = q.filter(user_id.eq(u));
+    }
+    let e = q
+        .order_by(created.desc())
+        .limit(MAX_EMAILS)
+        .load::<Email>(&conn)?;
+
+    Ok(Payload {
+        data: e,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Get an email
+pub fn get_email(email_id: i32, user: CurrentUser, conn: Conn) -> Response<Email> {
+    use schema::emails::dsl::*;
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let e = emails.find(email_id).first::<Email>(&conn)?;
+
+    Ok(Payload {
+        data: e,
+        success: true,
+        ..Default::default()
+    })
+}
@@ -0,0 +1,33 @@
+//
+// email/outbox.rs
+//
+use email::types::Email;
+use email::Mailer;
+use result::Error;
+use std::fs;
+use std::path::PathBuf;
+
+/// Writes each email to a file instead of sending it, for development and
+/// tests. The `emails` table keeps a copy either way.
+pub struct OutboxMailer {
+    root: PathBuf,
+}
+
+impl OutboxMailer {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        OutboxMailer { root: root.into() }
+    }
+}
+
+impl Mailer for OutboxMailer {
+    fn send(&self, email: &Email) -> Result<(), Error> {
+        fs::create_dir_all(&self.root).map_err(|e| Error::MailError(e.to_string()))?;
+
+        let contents = format!(
+            "To: {}\nSubject: {}\nTemplate: {}\n\n{}\n\n---\n\n{}",
+            email.to_address, email.subject, email.template, email.text_body, email.html_body,
+        );
+        let path = self.root.join(format!("{}.txt", email.id));
+        fs::write(&path, contents).map_err(|e| Error::MailError(e.to_string()))
+    }
+}
@@ -0,0 +1,57 @@
+//
+// email/smtp.rs
+//
+use email::types::Email;
+use email::Mailer;
+use lettre::smtp::authentication::Credentials;
+use lettre::{SmtpClient, Transport};
+use lettre_email::EmailBuilder;
+use result::Error;
+
+/// Delivery through an SMTP server, over STARTTLS on the submission port
+pub struct SmtpMailer {
+    host: String,
+    credentials: Option<(String, String)>,
+    from: String,
+    from_name: String,
+}
+
+impl SmtpMailer {
+    pub fn new(
+        host: String,
+        credentials: Option<(String, String)>,
+        from: String,
+        from_name: String,
+    ) -> Self {
+        SmtpMailer {
+            host,
+            credentials,
+            from,
+            from_name,
+        }
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send(&self, email: &Email) -> Result<(), Error> {
+        let message = EmailBuilder::new()
+            .to(email.to_address.as_str())
+            .from((self.from.as_str(), self.from_name.as_str()))
+            .subject(email.subject.as_str())
+            .alternative(email.html_body.as_str(), email.text_body.as_str())
+            .build()
+            .map_err(|e| Error::MailError(e.to_string()))?;
+
+        let mut client =
+            SmtpClient::new_simple(&self.host).map_err(|e| Error::MailError(e.to_string()))?;
+        if let Some((ref username, ref password)) = self.credentials {
+            client = client.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        client
+            .transport()
+            .send(message.into())
+            .map(|_| ())
+            .map_err(|e| Error::MailError(e.to_string()))
+    }
+}
@@ -0,0 +1,106 @@
+//
+// email/templates.rs
+//
+// Every email has a subject, an HTML body and a text body, each filled in
+// with `template::render`. HTML bodies are wrapped in `LAYOUT` and their
+// values are escaped.
+//
+use email::types::*;
+use std::collections::HashMap;
+use template;
+
+/// Shell every HTML body is placed in
+const LAYOUT: &str = "<!DOCTYPE html>
+<html>
+<body style=\"margin: 0; padding: 24px; background: #f4f4f4; font-family: Helvetica, Arial, sans-serif;\">
+<div style=\"max-width: 560px; margin: 0 auto; padding: 24px; background: #ffffff; color: #333333;\">
+{{content}}
+</div>
+<p style=\"max-width: 560px; margin: 12px auto; color: #999999; font-size: 12px;\">Dwello</p>
+</body>
+</html>
+";
+
+const WELCOME_SUBJECT: &str = "Welcome to Dwello";
+
+const WELCOME_HTML: &str = "<p>Hi {{name}},</p>
+<p>Thanks for signing up for Dwello. Your account is ready to use.</p>
+";
+
+const WELCOME_TEXT: &str = "Hi {{name}},
+
+Thanks for signing up for Dwello. Your account is ready to use.
+";
+
+const DEAL_ACTIVITY_SUBJECT: &str = "Update on {{deal_title}}";
+
+const DEAL_ACTIVITY_HTML: &str = "<p>Hi {{name}},</p>
+<p>{{summary}}</p>
+<p><a href=\"{{deal_url}}\">View the deal</a></p>
+";
+
+const DEAL_ACTIVITY_TEXT: &str = "Hi {{name}},
+
+{{summary}}
+
+View the deal: {{deal_url}}
+";
+
+///
+/// Helpers
+///
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+///
+/// Public API
+///
+
+/// Fill in an email's subject and bodies
+pub fn render(email: &EmailTemplate) -> RenderedEmail {
+    let mut values = HashMap::new();
+    let (name, subject, html, text) = match *email {
+        EmailTemplate::Welcome { ref name } => {
+            values.insert("name", name.clone());
+            ("welcome", WELCOME_SUBJECT, WELCOME_HTML, WELCOME_TEXT)
+        }
+        EmailTemplate::DealActivity {
+            ref name,
+            ref deal_title,
+            ref summary,
+            ref deal_url,
+        } => {
+            values.insert("name", name.clone());
+            values.insert("deal_title", deal_title.clone());
+            values.insert("summary", summary.clone());
+            values.insert("deal_url", deal_url.clone());
+            (
+                "deal_activity",
+                DEAL_ACTIVITY_SUBJECT,
+                DEAL_ACTIVITY_HTML,
+                DEAL_ACTIVITY_TEXT,
+            )
+        }
+    };
+
+    let escaped = values
+        .iter()
+        .map(|(k, v)| (*k, escape_html(v)))
+        .collect::<HashMap<&str, String>>();
+    let mut layout = HashMap::new();
+    layout.insert("content", template::render(html, &escaped));
+
+    RenderedEmail {
+        template: name,
+        subject: template::render(subject, &values),
+        html_body: template::render(LAYOUT, &layout),
+        text_body: template::render(text, &values),
+    }
+}
@@ -0,0 +1,124 @@
+//
+// email/types.rs
+//
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Varchar;
+use rocket::http::RawStr;
+use rocket::request::FromFormValue;
+use schema::emails;
+use std::io::Write;
+
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, AsExpression, FromSqlRow)]
+#[sql_type = "Varchar"]
+pub enum EmailStatus {
+    /// Waiting for its job to deliver it, maybe after failed attempts
+    Queued,
+    Sent,
+    /// Every attempt failed
+    Failed,
+}
+
+impl ToSql<Varchar, Pg> for EmailStatus {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        match *self {
+            EmailStatus::Queued => out.write_all(b"queued")?,
+            EmailStatus::Sent => out.write_all(b"sent")?,
+            EmailStatus::Failed => out.write_all(b"failed")?,
+        }
+
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Pg> for EmailStatus {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match not_none!(bytes) {
+            b"queued" => Ok(EmailStatus::Queued),
+            b"sent" => Ok(EmailStatus::Sent),
+            b"failed" => Ok(EmailStatus::Failed),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
+
+impl<'v> FromFormValue<'v> for EmailStatus {
+    type Error = &'v RawStr;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, &'v RawStr> {
+        match form_value.as_str() {
+            "queued" => Ok(EmailStatus::Queued),
+            "sent" => Ok(EmailStatus::Sent),
+            "failed" => Ok(EmailStatus::Failed),
+            _ => Err(form_value),
+        }
+    }
+}
+
+#[derive(Serialize, Identifiable, Clone, Queryable, Debug)]
+#[table_name = "emails"]
+pub struct Email {
+    pub id: i32,
+    /// Recipient's account, if they have one
+    pub user_id: Option<i32>,
+    pub to_address: String,
+    /// Name of the template the email was rendered from
+    pub template: String,
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+    pub status: EmailStatus,
+    /// Why the last attempt failed
+    pub error: Option<String>,
+    pub sent: Option<chrono::NaiveDateTime>,
+    pub created: chrono::NaiveDateTime,
+    pub updated: chrono::NaiveDateTime,
+    /// Times delivery has been tried
+    pub attempts: i32,
+}
+
+#[derive(Insertable)]
+#[table_name = "emails"]
+pub struct NewEmail {
+    pub user_id: Option<i32>,
+    pub to_address: String,
+    pub template: String,
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+    pub status: EmailStatus,
+    pub created: chrono::NaiveDateTime,
+    pub updated: chrono::NaiveDateTime,
+}
+
+/// An email to send, with what fills in its template.
+///
+/// Only mail the app sends has a template. Password resets, invites and
+/// email verification aren't features yet, and get theirs along with them.
+#[derive(Debug, Clone)]
+pub enum EmailTemplate {
+    /// Sent when someone registers
+    Welcome { name: String },
+    /// Something happened on one of the recipient's deals
+    DealActivity {
+        name: String,
+        deal_title: String,
+        summary: String,
+        deal_url: String,
+    },
+}
+
+/// A template filled in and ready to store
+pub struct RenderedEmail {
+    pub template: &'static str,
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+}
+
+#[derive(FromForm, Default)]
+pub struct EmailsQuery {
+    pub status: Option<EmailStatus>,
+    pub user_id: Option<i32>,
+}
@@ -0,0 +1,295 @@
+//
+// events/mod.rs
+//
+// Events are published with Postgres `NOTIFY`, so they're only sent once the
+// publishing transaction commits, and reach clients connected to any web
+// process. Each process has an `EventHub` that `LISTEN`s and hands events
+// to the streams open on it.
+//
+pub mod stream;
+pub mod types;
+
+use self::stream::EventStream;
+use self::types::*;
+use accounts::types::{CurrentUser, Session, User};
+use db::Conn;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use dotenv::dotenv;
+use fallible_iterator::FallibleIterator;
+use hmac::{Hmac, Mac};
+use postgres::tls::openssl::OpenSsl;
+use postgres::{Connection, TlsMode};
+use result::{Error, Payload, Response};
+use sha2::Sha256;
+use std::collections::VecDeque;
+use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+/// Postgres channel events go through
+const CHANNEL: &str = "app_events";
+
+/// Postgres refuses `NOTIFY` payloads of 8000 bytes or more
+const MAX_PAYLOAD: usize = 7999;
+
+/// Wait before listening again after losing the connection
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Events kept for streams that reconnect
+const RECENT_EVENTS: usize = 500;
+
+/// Streams served at once by a process, unless `EVENT_STREAMS` says
+/// otherwise. Each holds one of Rocket's workers while it waits.
+const DEFAULT_MAX_STREAMS: usize = 4;
+
+/// How long a stream token can open streams for
+const TOKEN_MINUTES: i64 = 10;
+
+/// A stream open on this process
+struct Subscriber {
+    id: u64,
+    user_id: i32,
+    is_admin: bool,
+    sender: Sender<Delivery>,
+}
+
+/// What the listener shares with the streams
+#[derive(Default)]
+struct Shared {
+    subscribers: Vec<Subscriber>,
+    recent: VecDeque<(Audience, Delivery)>,
+    last_id: i64,
+    last_subscriber: u64,
+}
+
+/// State container for this process's open event streams
+pub struct EventHub {
+    shared: Arc<Mutex<Shared>>,
+    open: Arc<AtomicUsize>,
+    max_streams: usize,
+}
+
+/// A stream's place among those this process serves, given up along with
+/// its subscription when the stream is dropped
+pub struct StreamSlot {
+    open: Arc<AtomicUsize>,
+    shared: Arc<Mutex<Shared>>,
+    subscriber: Option<u64>,
+}
+
+impl Drop for StreamSlot {
+    fn drop(&mut self) {
+        self.open.fetch_sub(1, Ordering::SeqCst);
+        if let Some(id) = self.subscriber {
+            lock(&self.shared).subscribers.retain(|s| s.id != id);
+        }
+    }
+}
+
+///
+/// Helpers
+///
+
+fn lock(shared: &Mutex<Shared>) -> MutexGuard<Shared> {
+    match shared.lock() {
+        Ok(s) => s,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+/// Hand an event to the streams it's meant for, dropping streams that have
+/// closed, and keep it for streams that reconnect
+fn dispatch(shared: &Mutex<Shared>, broadcast: Broadcast) {
+    let mut shared = lock(shared);
+    let now = chrono::Utc::now().timestamp_nanos();
+    let id = if now > shared.last_id {
+        now
+    } else {
+        shared.last_id + 1
+    };
+    shared.last_id = id;
+
+    let audience = broadcast.audience;
+    let delivery = Delivery {
+        id,
+        event: broadcast.event,
+    };
+    shared.subscribers.retain(|s| {
+        !audience.hears(s.user_id, s.is_admin) || s.sender.send(delivery.clone()).is_ok()
+    });
+
+    if shared.recent.len() >= RECENT_EVENTS {
+        shared.recent.pop_front();
+    }
+    shared.recent.push_back((audience, delivery));
+}
+
+fn listen_once(shared: &Mutex<Shared>) -> Result<(), String> {
+    dotenv().ok();
+
+    let database_url = env::var("DATABASE_URL").map_err(|e| e.to_string())?;
+    let negotiator = OpenSsl::new().map_err(|e| e.to_string())?;
+    let conn = Connection::connect(database_url.as_str(), TlsMode::Prefer(&negotiator))
+        .map_err(|e| e.to_string())?;
+    conn.execute(&format!("LISTEN {}", CHANNEL), &[])
+        .map_err(|e| e.to_string())?;
+
+    let notifications = conn.notifications();
+    let mut incoming = notifications.blocking_iter();
+    while let Some(n) = incoming.next().map_err(|e| e.to_string())? {
+        match serde_json::from_str::<Broadcast>(&n.payload) {
+            Ok(broadcast) => dispatch(shared, broadcast),
+            Err(e) => println!("Skipping unreadable event: {}", e),
+        }
+    }
+    Ok(())
+}
+
+/// Signature of a stream token, keyed by the session it was made from so it
+/// stops working with the session
+fn token_mac(session: &Session, expires: i64) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_varkey(session.token.as_bytes())
+        .expect("HMAC takes keys of any length");
+    mac.input(format!("{}.{}", session.id, expires).as_bytes());
+    mac
+}
+
+///
+/// Public API
+///
+
+/// Send an event to everyone in the audience once the transaction commits
+pub fn publish(conn: &PgConnection, audience: Audience, event: Event) -> Result<(), Error> {
+    let payload = serde_json::to_string(&Broadcast { audience, event })?;
+
+    // Too big to send, so it's dropped rather than failing the caller
+    if payload.len() > MAX_PAYLOAD {
+        println!("Event too large to publish: {} bytes", payload.len());
+        return Ok(());
+    }
+
+    diesel::sql_query("SELECT pg_notify($1, $2)")
+        .bind::<Text, _>(CHANNEL)
+        .bind::<Text, _>(payload)
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Make a stream token from the session an API key belongs to
+pub fn create_token(key: String, conn: Conn) -> Response<StreamToken> {
+    use schema::sessions::dsl::*;
+
+    let Conn(conn) = conn;
+    let session = sessions
+        .filter(token.eq(key))
+        .first::<Session>(&conn)
+        .map_err(|_| Error::AccessDenied)?;
+
+    let expires = chrono::Utc::now().naive_utc() + chrono::Duration::minutes(TOKEN_MINUTES);
+    let stamp = expires.timestamp();
+    let signature = hex::encode(token_mac(&session, stamp).result().code());
+
+    Ok(Payload {
+        data: StreamToken {
+            token: format!("{}.{}.{}", session.id, stamp, signature),
+            expires,
+        },
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// The user a stream token was made for, if it's genuine and hasn't expired
+pub fn user_from_token(conn: &PgConnection, stream_token: &str) -> Option<User> {
+    use schema::sessions::dsl::*;
+    use schema::users::dsl::users;
+
+    let parts = stream_token.splitn(3, '.').collect::<Vec<&str>>();
+    if parts.len() != 3 {
+        return None;
+    }
+    let session_id = parts[0].parse::<i32>().ok()?;
+    let expires = parts[1].parse::<i64>().ok()?;
+    let signature = hex::decode(parts[2]).ok()?;
+    if expires < chrono::Utc::now().timestamp() {
+        return None;
+    }
+
+    let session = sessions.find(session_id).first::<Session>(conn).ok()?;
+    token_mac(&session, expires).verify(&signature).ok()?;
+    users.find(session.uid).first::<User>(conn).ok()
+}
+
+impl EventHub {
+    /// Start listening for events in the background
+    pub fn start() -> Self {
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let listening = shared.clone();
+
+        thread::Builder::new()
+            .name("event-hub".to_owned())
+            .spawn(move || loop {
+                if let Err(e) = listen_once(&listening) {
+                    println!("Event listener error: {}", e);
+                }
+                thread::sleep(RECONNECT_DELAY);
+            })
+            .expect("Failed to start event hub");
+
+        let max_streams = env::var("EVENT_STREAMS")
+            .ok()
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(DEFAULT_MAX_STREAMS);
+
+        EventHub {
+            shared,
+            open: Arc::new(AtomicUsize::new(0)),
+            max_streams,
+        }
+    }
+
+    /// Stream the events meant for a user, starting after `since` if the
+    /// client has seen some already
+    pub fn subscribe(&self, user: &CurrentUser, since: Option<i64>) -> Result<EventStream, Error> {
+        let (user_id, is_admin) = match *user {
+            CurrentUser::Admin(ref u) => (u.id, true),
+            CurrentUser::Authenticated(ref u) => (u.id, false),
+            CurrentUser::Anonymous => return Err(Error::AccessDenied),
+        };
+
+        // Counted before checking, so two streams can't both take the last
+        // place. The slot gives it back if the stream is refused.
+        let mut slot = StreamSlot {
+            open: self.open.clone(),
+            shared: self.shared.clone(),
+            subscriber: None,
+        };
+        if self.open.fetch_add(1, Ordering::SeqCst) >= self.max_streams {
+            return Err(Error::ServiceUnavailable);
+        }
+
+        let (sender, receiver) = channel();
+        let mut shared = lock(&self.shared);
+        let seen = since.unwrap_or(shared.last_id);
+        for &(ref audience, ref delivery) in shared.recent.iter() {
+            if delivery.id > seen && audience.hears(user_id, is_admin) {
+                let _ = sender.send(delivery.clone());
+            }
+        }
+        shared.last_subscriber += 1;
+        let id = shared.last_subscriber;
+        shared.subscribers.push(Subscriber {
+            id,
+            user_id,
+            is_admin,
+            sender,
+        });
+        slot.subscriber = Some(id);
+        Ok(EventStream::new(receiver, seen, slot))
+    }
+}
@@ -0,0 +1,84 @@
+//
+// events/stream.rs
+//
+// Rocket 0.4 only sends a streamed body once it has read a whole chunk, and
+// hyper holds writes until its 8 KiB buffer fills or the response ends, so
+// an open-ended stream can't push events out as they happen. Each response
+// instead ends once it has events to send, or after a wait, and browsers'
+// `EventSource` reconnects with the last id it saw to pick up from there.
+//
+use events::types::Delivery;
+use events::StreamSlot;
+use std::io::{self, Cursor, Read};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+/// Size of the chunks the body is read in
+pub const CHUNK_SIZE: usize = 8192;
+
+/// Longest a response waits for an event. Short enough that proxies don't
+/// close it for being idle.
+const WAIT: Duration = Duration::from_secs(25);
+
+/// How soon browsers reconnect once a response ends, in milliseconds
+const RETRY: u64 = 500;
+
+/// An SSE body made of the events sent to a subscriber
+pub struct EventStream {
+    events: Receiver<Delivery>,
+    /// Id of the last event the client has, or has no need of
+    seen: i64,
+    body: Option<Cursor<Vec<u8>>>,
+    _slot: StreamSlot,
+}
+
+impl EventStream {
+    pub fn new(events: Receiver<Delivery>, seen: i64, slot: StreamSlot) -> Self {
+        EventStream {
+            events,
+            seen,
+            body: None,
+            _slot: slot,
+        }
+    }
+
+    /// Wait for events, and make the response's body from them
+    fn wait(&self) -> String {
+        let mut body = format!("retry: {}\n\n", RETRY);
+        match self.events.recv_timeout(WAIT) {
+            Ok(first) => {
+                body.push_str(&frame(&first));
+                for delivery in self.events.try_iter() {
+                    body.push_str(&frame(&delivery));
+                }
+            }
+            // Just the id, so the client reconnects from where it's up to
+            Err(RecvTimeoutError::Timeout) => body.push_str(&format!("id: {}\n\n", self.seen)),
+            Err(RecvTimeoutError::Disconnected) => {}
+        }
+        body
+    }
+}
+
+fn frame(delivery: &Delivery) -> String {
+    let event = &delivery.event;
+    let data = serde_json::to_string(event).unwrap_or_else(|_| "{}".to_owned());
+    format!(
+        "id: {}\nevent: {}\ndata: {}\n\n",
+        delivery.id,
+        event.name(),
+        data
+    )
+}
+
+impl Read for EventStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.body.is_none() {
+            self.body = Some(Cursor::new(self.wait().into_bytes()));
+        }
+        match self.body {
+            Some(ref mut body) => body.read(buf),
+            None => Ok(0),
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//
+// events/types.rs
+//
+use deals::types::Deal;
+use notifications::types::{Notification, NotificationKind};
+
+/// Something for connected clients to hear about
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type")]
+pub enum Event {
+    DealCreated {
+        deal: Deal,
+    },
+    DealUpdated {
+        deal: Deal,
+    },
+    /// An item for a deal's timeline
+    DealActivity {
+        deal_id: i32,
+        kind: NotificationKind,
+        message: String,
+    },
+    Notification {
+        notification: Notification,
+    },
+}
+
+impl Event {
+    /// Name clients listen for with `addEventListener`
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Event::DealCreated { .. } => "deal_created",
+            Event::DealUpdated { .. } => "deal_updated",
+            Event::DealActivity { .. } => "deal_activity",
+            Event::Notification { .. } => "notification",
+        }
+    }
+}
+
+/// Who hears an event
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Audience {
+    pub users: Vec<i32>,
+    /// Every admin, as well as `users`
+    pub admins: bool,
+}
+
+impl Audience {
+    /// Admins and the deal's buyer and seller
+    pub fn deal(deal: &Deal) -> Self {
+        Audience {
+            users: deal.buyer_id.into_iter().chain(deal.seller_id).collect(),
+            admins: true,
+        }
+    }
+
+    /// Just the one user
+    pub fn user(user_id: i32) -> Self {
+        Audience {
+            users: vec![user_id],
+            admins: false,
+        }
+    }
+
+    pub fn hears(&self, user_id: i32, is_admin: bool) -> bool {
+        (self.admins && is_admin) || self.users.contains(&user_id)
+    }
+}
+
+/// An event as sent through Postgres
+#[derive(Serialize, Deserialize)]
+pub struct Broadcast {
+    pub audience: Audience,
+    pub event: Event,
+}
+
+/// An event as a stream sends it
+#[derive(Clone)]
+pub struct Delivery {
+    /// When this process heard of the event, in nanoseconds. Sent as the SSE
+    /// `id`, so a reconnecting client can ask for what it missed.
+    pub id: i64,
+    pub event: Event,
+}
+
+/// Lets a browser open an event stream without putting its API key in a URL
+#[derive(Serialize)]
+pub struct StreamToken {
+    /// Sent as the stream's `token` query parameter
+    pub token: String,
+    pub expires: chrono::NaiveDateTime,
+}
@@ -0,0 +1,18 @@
+//
+// geocoding/disabled.rs
+//
+use geocoding::Geocoder;
+use houses::types::GoogleAddress;
+use result::Error;
+
+/// Geocoder for deployments without one. Every lookup fails, so addresses
+/// have to come with a google address from the client.
+pub struct DisabledGeocoder;
+
+impl Geocoder for DisabledGeocoder {
+    fn geocode(&self, _address: &str) -> Result<Option<GoogleAddress>, Error> {
+        Err(Error::GeocodingError(
+            "Geocoding is not configured, send a google address".to_owned(),
+        ))
+    }
+}
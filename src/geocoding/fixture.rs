@@ -0,0 +1,53 @@
+//
+// geocoding/fixture.rs
+//
+use geocoding::Geocoder;
+use houses::address::canonicalize;
+use houses::types::GoogleAddress;
+use result::Error;
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+
+/// Geocoder backed by canned results, for tests and offline use.
+///
+/// Fixtures are a JSON object mapping addresses to google addresses.
+/// Addresses are matched on their canonical form, so "123 Main Street"
+/// finds a fixture stored as "123 Main St".
+pub struct FixtureGeocoder {
+    fixtures: HashMap<String, serde_json::Value>,
+}
+
+impl FixtureGeocoder {
+    pub fn new(fixtures: HashMap<String, serde_json::Value>) -> Self {
+        FixtureGeocoder {
+            fixtures: fixtures
+                .into_iter()
+                .map(|(address, result)| (canonicalize(&address), result))
+                .collect(),
+        }
+    }
+
+    /// Load fixtures from a file. A missing file gives an empty geocoder.
+    pub fn from_file(path: &str) -> Result<Self, Error> {
+        if !Path::new(path).exists() {
+            println!("No geocoder fixtures at {}", path);
+            return Ok(FixtureGeocoder::new(HashMap::new()));
+        }
+
+        let file = File::open(path)
+            .map_err(|e| Error::GeocodingError(format!("Could not open {}: {}", path, e)))?;
+        let fixtures = serde_json::from_reader(file)?;
+
+        Ok(FixtureGeocoder::new(fixtures))
+    }
+}
+
+impl Geocoder for FixtureGeocoder {
+    fn geocode(&self, address: &str) -> Result<Option<GoogleAddress>, Error> {
+        match self.fixtures.get(&canonicalize(address)) {
+            Some(result) => Ok(Some(serde_json::from_value(result.clone())?)),
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,149 @@
+//
+// geocoding/google.rs
+//
+use geocoding::Geocoder;
+use houses::types::{AddressComponents, Geometry, GoogleAddress, Location, Viewport};
+use result::Error;
+
+const GEOCODE_URL: &str = "https://maps.googleapis.com/maps/api/geocode/json";
+
+/// Geocoder backed by the google geocoding API
+pub struct GoogleGeocoder {
+    api_key: String,
+    client: reqwest::Client,
+}
+
+impl GoogleGeocoder {
+    pub fn new(api_key: String) -> Self {
+        GoogleGeocoder {
+            api_key,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+impl Geocoder for GoogleGeocoder {
+    fn geocode(&self, address: &str) -> Result<Option<GoogleAddress>, Error> {
+        let mut res = self
+            .client
+            .get(GEOCODE_URL)
+            .query(&[("address", address), ("key", self.api_key.as_str())])
+            .send()
+            .map_err(|e| Error::GeocodingError(e.to_string()))?;
+        let body = res
+            .json::<GeocodeResponse>()
+            .map_err(|e| Error::GeocodingError(e.to_string()))?;
+
+        match body.status.as_str() {
+            "OK" => Ok(body.results.into_iter().next().map(GoogleAddress::from)),
+            "ZERO_RESULTS" => Ok(None),
+            status => Err(Error::GeocodingError(format!(
+                "{}: {}",
+                status,
+                body.error_message.unwrap_or_default()
+            ))),
+        }
+    }
+}
+
+//
+// Google's wire format.
+//
+// Stored google addresses use the frontend's encoding, so these are
+// converted on the way in: `street_number` becomes `StreetNumber`,
+// `ROOFTOP` becomes `Rooftop` and `lat`/`lng` become `latitude`/`longitude`.
+//
+
+#[derive(Deserialize)]
+struct GeocodeResponse {
+    status: String,
+    #[serde(default)]
+    results: Vec<GeocodeResult>,
+    error_message: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GeocodeResult {
+    address_components: Vec<GeocodeComponent>,
+    formatted_address: String,
+    geometry: GeocodeGeometry,
+    place_id: String,
+    types: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct GeocodeComponent {
+    long_name: String,
+    short_name: String,
+    types: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct GeocodeGeometry {
+    location: LatLng,
+    location_type: String,
+    viewport: GeocodeViewport,
+}
+
+#[derive(Deserialize)]
+struct GeocodeViewport {
+    northeast: LatLng,
+    southwest: LatLng,
+}
+
+#[derive(Deserialize)]
+struct LatLng {
+    lat: f64,
+    lng: f64,
+}
+
+impl From<LatLng> for Location {
+    fn from(l: LatLng) -> Self {
+        Location {
+            latitude: l.lat,
+            longitude: l.lng,
+        }
+    }
+}
+
+impl From<GeocodeResult> for GoogleAddress {
+    fn from(r: GeocodeResult) -> Self {
+        GoogleAddress {
+            address_components: r
+                .address_components
+                .into_iter()
+                .map(|c| AddressComponents {
+                    long_name: c.long_name,
+                    short_name: c.short_name,
+                    types: c.types.iter().map(|t| pascal_case(t)).collect(),
+                })
+                .collect(),
+            formatted_address: r.formatted_address,
+            geometry: Geometry {
+                location: Location::from(r.geometry.location),
+                location_type: pascal_case(&r.geometry.location_type),
+                viewport: Viewport {
+                    northeast: Location::from(r.geometry.viewport.northeast),
+                    southwest: Location::from(r.geometry.viewport.southwest),
+                },
+            },
+            place_id: r.place_id,
+            types: r.types.iter().map(|t| pascal_case(t)).collect(),
+        }
+    }
+}
+
+/// `administrative_area_level_1` -> `AdministrativeAreaLevel1`
+fn pascal_case(value: &str) -> String {
+    value
+        .split('_')
+        .map(|word| {
+            let lower = word.to_lowercase();
+            let mut chars = lower.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,59 @@
+//
+// geocoding/mod.rs
+//
+pub mod disabled;
+pub mod fixture;
+pub mod google;
+
+use self::disabled::DisabledGeocoder;
+use self::fixture::FixtureGeocoder;
+use self::google::GoogleGeocoder;
+use dotenv::dotenv;
+use houses::types::GoogleAddress;
+use result::Error;
+use std::env;
+
+/// Turns a free-text address into a google address
+pub trait Geocoder: Send + Sync {
+    /// Look up an address, returning `None` when nothing matches
+    fn geocode(&self, address: &str) -> Result<Option<GoogleAddress>, Error>;
+}
+
+/// State container for the geocoder
+pub struct Geocoding(pub Box<dyn Geocoder>);
+
+/// Build the geocoder selected by `GEOCODER`.
+///
+/// `google` needs `GOOGLE_API_KEY`. `fixture` reads canned results from
+/// `GEOCODER_FIXTURES`, which is meant for tests and offline use, so it's
+/// only used when asked for. `disabled` fails every lookup. Without
+/// `GEOCODER`, google is used when `GOOGLE_API_KEY` is set, and geocoding is
+/// disabled otherwise, since clients can send google addresses themselves.
+pub fn from_env() -> Result<Box<dyn Geocoder>, String> {
+    dotenv().ok();
+
+    let kind = env::var("GEOCODER").unwrap_or_else(|_| match env::var("GOOGLE_API_KEY") {
+        Ok(_) => "google".to_owned(),
+        Err(_) => "disabled".to_owned(),
+    });
+
+    match kind.as_str() {
+        "google" => {
+            let api_key = env::var("GOOGLE_API_KEY")
+                .map_err(|_| "GOOGLE_API_KEY must be set to use the google geocoder".to_owned())?;
+            Ok(Box::new(GoogleGeocoder::new(api_key)))
+        }
+        "fixture" => {
+            let path = env::var("GEOCODER_FIXTURES")
+                .unwrap_or_else(|_| "fixtures/geocoding.json".to_owned());
+            let fixtures = FixtureGeocoder::from_file(&path)
+                .map_err(|e| format!("Failed to load geocoder fixtures: {:?}", e))?;
+            Ok(Box::new(fixtures))
+        }
+        "disabled" => {
+            println!("Geocoding is disabled, set GOOGLE_API_KEY to enable it");
+            Ok(Box::new(DisabledGeocoder))
+        }
+        other => Err(format!("Unknown geocoder {}", other)),
+    }
+}
@@ -0,0 +1,517 @@
+//
+// graphql/limits.rs
+//
+// Queries are checked before they run, so a client can't ask for the deals
+// of the houses of the deals of... until the database gives out. Depth is
+// how deeply selections nest, and complexity is how many fields are
+// selected in all, with fragments counted wherever they're spread.
+// Introspection fields don't touch the database, so they're left out of
+// both.
+//
+use result::Error;
+use std::collections::HashMap;
+
+/// Most nested selections a query can have
+pub const MAX_DEPTH: usize = 6;
+
+/// Most fields a query can select
+pub const MAX_COMPLEXITY: usize = 250;
+
+/// Deepest nesting that's parsed at all, counting introspection fields,
+/// inline fragments and chains of fragment spreads. Keeps a hostile query
+/// from overflowing the stack before it's measured.
+const MAX_NESTING: usize = 32;
+
+/// Why a query wasn't measured
+#[derive(Debug)]
+enum Problem {
+    Malformed,
+    TooDeep,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Punct(char),
+    Spread,
+    /// A string or number. Only ever skipped.
+    Value,
+}
+
+/// Size of a selection set
+#[derive(Debug, Default, Copy, Clone)]
+struct Cost {
+    depth: usize,
+    fields: usize,
+}
+
+/// The fields of a selection set, and the fragments spread into it at each
+/// level
+#[derive(Debug, Default)]
+struct Selection {
+    cost: Cost,
+    spreads: Vec<(usize, String)>,
+}
+
+impl Selection {
+    fn absorb(&mut self, other: Selection) {
+        self.cost.depth = self.cost.depth.max(other.cost.depth);
+        self.cost.fields += other.cost.fields;
+        self.spreads.extend(other.spreads);
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+///
+/// Helpers
+///
+
+fn tokenize(source: &str) -> Result<Vec<Token>, Problem> {
+    let chars = source.chars().collect::<Vec<char>>();
+    let at = |i: usize| chars.get(i).cloned();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while let Some(c) = at(i) {
+        i += 1;
+        match c {
+            ' ' | '\t' | '\n' | '\r' | ',' | '\u{feff}' => {}
+            '#' => {
+                while at(i).map_or(false, |c| c != '\n' && c != '\r') {
+                    i += 1;
+                }
+            }
+            '"' if at(i) == Some('"') && at(i + 1) == Some('"') => {
+                i += 2;
+                loop {
+                    match at(i) {
+                        Some('\\') if at(i + 1) == Some('"') => i += 2,
+                        Some('"') if at(i + 1) == Some('"') && at(i + 2) == Some('"') => {
+                            i += 3;
+                            break;
+                        }
+                        Some(_) => i += 1,
+                        None => return Err(Problem::Malformed),
+                    }
+                }
+                tokens.push(Token::Value);
+            }
+            '"' => {
+                loop {
+                    match at(i) {
+                        Some('\\') => i += 2,
+                        Some('"') => {
+                            i += 1;
+                            break;
+                        }
+                        Some('\n') | None => return Err(Problem::Malformed),
+                        Some(_) => i += 1,
+                    }
+                }
+                tokens.push(Token::Value);
+            }
+            '.' if at(i) == Some('.') && at(i + 1) == Some('.') => {
+                i += 2;
+                tokens.push(Token::Spread);
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut name = c.to_string();
+                while let Some(c) = at(i).filter(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    name.push(c);
+                    i += 1;
+                }
+                tokens.push(Token::Name(name));
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                while at(i).map_or(false, |c| {
+                    c.is_ascii_alphanumeric() || c == '.' || c == '+' || c == '-'
+                }) {
+                    i += 1;
+                }
+                tokens.push(Token::Value);
+            }
+            '{' | '}' | '(' | ')' | '[' | ']' | ':' | '=' | '@' | '$' | '!' | '|' | '&' => {
+                tokens.push(Token::Punct(c))
+            }
+            _ => return Err(Problem::Malformed),
+        }
+    }
+    Ok(tokens)
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn at(&self, c: char) -> bool {
+        self.peek() == Some(&Token::Punct(c))
+    }
+
+    fn at_name(&self, name: &str) -> bool {
+        match self.peek() {
+            Some(&Token::Name(ref n)) => n == name,
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), Problem> {
+        match self.next() {
+            Some(Token::Punct(p)) if p == c => Ok(()),
+            _ => Err(Problem::Malformed),
+        }
+    }
+
+    fn name(&mut self) -> Result<String, Problem> {
+        match self.next() {
+            Some(Token::Name(name)) => Ok(name),
+            _ => Err(Problem::Malformed),
+        }
+    }
+
+    /// Skip a bracketed group, like arguments or variable definitions
+    fn skip_group(&mut self, open: char, close: char) -> Result<(), Problem> {
+        self.expect(open)?;
+        let mut level = 1;
+        while level > 0 {
+            match self.next() {
+                Some(Token::Punct(c)) if c == open => level += 1,
+                Some(Token::Punct(c)) if c == close => level -= 1,
+                Some(_) => {}
+                None => return Err(Problem::Malformed),
+            }
+        }
+        Ok(())
+    }
+
+    fn skip_directives(&mut self) -> Result<(), Problem> {
+        while self.at('@') {
+            self.next();
+            self.name()?;
+            if self.at('(') {
+                self.skip_group('(', ')')?;
+            }
+        }
+        Ok(())
+    }
+
+    /// A selection set whose fields are `level` deep, inside `nesting`
+    /// others. Inline fragments don't add a level, but do add nesting.
+    fn selection_set(&mut self, level: usize, nesting: usize) -> Result<Selection, Problem> {
+        if nesting > MAX_NESTING {
+            return Err(Problem::TooDeep);
+        }
+        self.expect('{')?;
+
+        let mut selection = Selection {
+            cost: Cost {
+                depth: level,
+                fields: 0,
+            },
+            spreads: Vec::new(),
+        };
+        while !self.at('}') {
+            if self.peek() == Some(&Token::Spread) {
+                self.next();
+                if self.at('@') || self.at('{') || self.at_name("on") {
+                    // Inline fragment, on the same level
+                    if self.at_name("on") {
+                        self.next();
+                        self.name()?;
+                    }
+                    self.skip_directives()?;
+                    let inline = self.selection_set(level, nesting + 1)?;
+                    selection.absorb(inline);
+                } else {
+                    let name = self.name()?;
+                    self.skip_directives()?;
+                    selection.spreads.push((level, name));
+                }
+                continue;
+            }
+
+            // A field, maybe behind an alias
+            let mut name = self.name()?;
+            if self.at(':') {
+                self.next();
+                name = self.name()?;
+            }
+            if self.at('(') {
+                self.skip_group('(', ')')?;
+            }
+            self.skip_directives()?;
+
+            let introspection = name.starts_with("__");
+            if !introspection {
+                selection.cost.fields += 1;
+            }
+            if self.at('{') {
+                let inner = self.selection_set(level + 1, nesting + 1)?;
+                if !introspection {
+                    selection.absorb(inner);
+                }
+            }
+        }
+        self.next();
+        Ok(selection)
+    }
+
+    /// Parse a document into its operations and named fragments
+    fn document(&mut self) -> Result<(Vec<Selection>, HashMap<String, Selection>), Problem> {
+        let mut operations = Vec::new();
+        let mut fragments = HashMap::new();
+
+        while self.peek().is_some() {
+            if self.at('{') {
+                operations.push(self.selection_set(1, 1)?);
+            } else if self.at_name("fragment") {
+                self.next();
+                let name = self.name()?;
+                if !self.at_name("on") {
+                    return Err(Problem::Malformed);
+                }
+                self.next();
+                self.name()?;
+                self.skip_directives()?;
+                fragments.insert(name, self.selection_set(1, 1)?);
+            } else {
+                // `query`, `mutation` or `subscription`, then an optional
+                // name and variables
+                self.name()?;
+                let named = match self.peek() {
+                    Some(&Token::Name(_)) => true,
+                    _ => false,
+                };
+                if named {
+                    self.next();
+                }
+                if self.at('(') {
+                    self.skip_group('(', ')')?;
+                }
+                self.skip_directives()?;
+                operations.push(self.selection_set(1, 1)?);
+            }
+        }
+        Ok((operations, fragments))
+    }
+}
+
+/// Cost of a selection with the fragments it spreads counted in. `seen`
+/// holds the fragments being measured, to stop on cycles.
+fn total_cost(
+    selection: &Selection,
+    fragments: &HashMap<String, Selection>,
+    costs: &mut HashMap<String, Cost>,
+    seen: &mut Vec<String>,
+) -> Result<Cost, Problem> {
+    let mut cost = selection.cost;
+    for &(level, ref name) in &selection.spreads {
+        let spread = match costs.get(name).cloned() {
+            Some(c) => c,
+            None => {
+                if seen.contains(name) {
+                    return Err(Problem::Malformed);
+                }
+                if seen.len() >= MAX_NESTING {
+                    return Err(Problem::TooDeep);
+                }
+                let fragment = fragments.get(name).ok_or(Problem::Malformed)?;
+                seen.push(name.clone());
+                let c = total_cost(fragment, fragments, costs, seen)?;
+                seen.pop();
+                costs.insert(name.clone(), c);
+                c
+            }
+        };
+        // A fragment's levels count from one, at the level it's spread on
+        cost.depth = cost.depth.max(level + spread.depth - 1);
+        cost.fields = cost.fields.saturating_add(spread.fields);
+    }
+    Ok(cost)
+}
+
+fn malformed() -> Error {
+    Error::from_custom_validation("query_malformed", "query", "Query couldn't be parsed")
+}
+
+fn too_deep() -> Error {
+    Error::from_custom_validation(
+        "query_too_deep",
+        "query",
+        &format!("Queries can nest at most {} levels", MAX_DEPTH),
+    )
+}
+
+fn too_complex() -> Error {
+    Error::from_custom_validation(
+        "query_too_complex",
+        "query",
+        &format!("Queries can select at most {} fields", MAX_COMPLEXITY),
+    )
+}
+
+///
+/// Public API
+///
+
+/// Check a query's depth and complexity. Queries that can't be measured
+/// don't run.
+pub fn check(source: &str) -> Result<(), Error> {
+    let measured = tokenize(source).and_then(|tokens| {
+        let (operations, fragments) = Parser { tokens, pos: 0 }.document()?;
+        let mut costs = HashMap::new();
+        operations
+            .iter()
+            .map(|o| total_cost(o, &fragments, &mut costs, &mut Vec::new()))
+            .collect::<Result<Vec<Cost>, Problem>>()
+    });
+
+    let costs = match measured {
+        Ok(costs) => costs,
+        Err(Problem::Malformed) => return Err(malformed()),
+        Err(Problem::TooDeep) => return Err(too_deep()),
+    };
+    for cost in costs {
+        if cost.depth > MAX_DEPTH {
+            return Err(too_deep());
+        }
+        if cost.fields > MAX_COMPLEXITY {
+            return Err(too_complex());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The code a query is rejected with, if it is
+    fn rejected(query: &str) -> Option<String> {
+        match check(query) {
+            Ok(()) => None,
+            Err(Error::InvalidInput(e)) => e
+                .field_errors()
+                .get("query")
+                .and_then(|errors| errors.first())
+                .map(|error| error.code.to_string()),
+            Err(e) => panic!("unexpected error {:?}", e),
+        }
+    }
+
+    /// Fields nested `depth` deep, like `{ a { a { a } } }`
+    fn nested(depth: usize) -> String {
+        format!("{}{{ a{}", "{ a ".repeat(depth - 1), " }".repeat(depth))
+    }
+
+    #[test]
+    fn allows_queries_within_limits() {
+        assert_eq!(
+            rejected("{ me { id name deals { id house { address } } } }"),
+            None
+        );
+        assert_eq!(rejected(&nested(MAX_DEPTH)), None);
+        assert_eq!(
+            rejected("query Deals($limit: Int) { deals(limit: $limit) { ...D } } fragment D on Deal { id title }"),
+            None
+        );
+        assert_eq!(
+            rejected("{ __schema { types { fields { type { ofType { name } } } } } }"),
+            None
+        );
+    }
+
+    #[test]
+    fn rejects_deep_queries() {
+        assert_eq!(
+            rejected(&nested(MAX_DEPTH + 1)),
+            Some("query_too_deep".to_string())
+        );
+        assert_eq!(
+            rejected(&nested(10_000)),
+            Some("query_too_deep".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_complex_queries() {
+        let fields = (0..MAX_COMPLEXITY)
+            .map(|i| format!("f{}: id", i))
+            .collect::<Vec<_>>();
+        assert_eq!(rejected(&format!("{{ {} }}", fields.join(" "))), None);
+        assert_eq!(
+            rejected(&format!("{{ extra: id {} }}", fields.join(" "))),
+            Some("query_too_complex".to_string())
+        );
+    }
+
+    #[test]
+    fn counts_fragments_where_spread() {
+        let fragment = "fragment F on User { deals { house { deals { id } } } }";
+        assert_eq!(rejected(&format!("{{ me {{ ...F }} }} {}", fragment)), None);
+        assert_eq!(
+            rejected(&format!(
+                "{{ users {{ deals {{ buyer {{ ...F }} }} }} }} {}",
+                fragment
+            )),
+            Some("query_too_deep".to_string())
+        );
+
+        let fields = (0..MAX_COMPLEXITY / 2 + 1)
+            .map(|i| format!("f{}: id", i))
+            .collect::<Vec<_>>();
+        let query = format!(
+            "{{ a: me {{ ...F }} b: me {{ ...F }} }} fragment F on User {{ {} }}",
+            fields.join(" ")
+        );
+        assert_eq!(rejected(&query), Some("query_too_complex".to_string()));
+    }
+
+    #[test]
+    fn rejects_nested_fragments() {
+        let inline = format!(
+            "{{ me {{ {}id{} }} }}",
+            "...{ ".repeat(10_000),
+            " }".repeat(10_000)
+        );
+        assert_eq!(rejected(&inline), Some("query_too_deep".to_string()));
+        let inline = format!(
+            "{{ me {{ {}id{} }} }}",
+            "... on User { ".repeat(MAX_NESTING + 1),
+            " }".repeat(MAX_NESTING + 1)
+        );
+        assert_eq!(rejected(&inline), Some("query_too_deep".to_string()));
+
+        let chain = (0..10_000)
+            .map(|i| format!("fragment F{} on User {{ ...F{} }}", i, i + 1))
+            .collect::<Vec<_>>();
+        let query = format!(
+            "{{ me {{ ...F0 }} }} {} fragment F10000 on User {{ id }}",
+            chain.join(" ")
+        );
+        assert_eq!(rejected(&query), Some("query_too_deep".to_string()));
+    }
+
+    #[test]
+    fn rejects_malformed_queries() {
+        let malformed = Some("query_malformed".to_string());
+        assert_eq!(rejected("{ me { id "), malformed);
+        assert_eq!(rejected("{ me(id: \"1) { id } }"), malformed);
+        assert_eq!(rejected("{ me { ...Missing } }"), malformed);
+        assert_eq!(
+            rejected("{ me { ...A } } fragment A on User { ...B } fragment B on User { ...A }"),
+            malformed
+        );
+        assert_eq!(rejected("{ me { id } } %"), malformed);
+    }
+}
@@ -0,0 +1,62 @@
+//
+// graphql/loader.rs
+//
+// GraphQL resolves one field at a time, so loading a relation per object
+// would cost a query for every row of a list. Resolvers that load a list
+// note the keys its rows will ask for with `want`, and the first `load`
+// fetches all of them at once.
+//
+use result::Error;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// Rows fetched during one request, by key
+pub struct Loader<T> {
+    wanted: RefCell<Vec<i32>>,
+    loaded: RefCell<HashMap<i32, Option<T>>>,
+}
+
+impl<T: Clone> Loader<T> {
+    pub fn new() -> Self {
+        Loader {
+            wanted: RefCell::new(Vec::new()),
+            loaded: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Note keys that are likely to be loaded, so they're fetched together
+    pub fn want<I: IntoIterator<Item = i32>>(&self, keys: I) {
+        self.wanted.borrow_mut().extend(keys);
+    }
+
+    /// Load a key, fetching it along with every wanted key that isn't
+    /// loaded yet. `fetch` returns the rows it found by key.
+    pub fn load<F>(&self, key: i32, fetch: F) -> Result<Option<T>, Error>
+    where
+        F: FnOnce(&[i32]) -> Result<Vec<(i32, T)>, Error>,
+    {
+        if let Some(row) = self.loaded.borrow().get(&key) {
+            return Ok(row.clone());
+        }
+
+        let mut keys = self.wanted.borrow_mut().drain(..).collect::<Vec<i32>>();
+        keys.push(key);
+        keys.sort();
+        keys.dedup();
+        {
+            let loaded = self.loaded.borrow();
+            keys.retain(|k| !loaded.contains_key(k));
+        }
+
+        let found = fetch(&keys)?;
+        let mut loaded = self.loaded.borrow_mut();
+        for (k, row) in found {
+            loaded.insert(k, Some(row));
+        }
+        // Remember misses too, so they aren't fetched again
+        for k in keys {
+            loaded.entry(k).or_insert(None);
+        }
+        Ok(loaded.get(&key).cloned().unwrap_or(None))
+    }
+}
@@ -0,0 +1,119 @@
+//
+// graphql/mod.rs
+//
+// Read-only GraphQL over users, profiles, deals and houses. Who can see what
+// follows the REST endpoints: admins see everything, and other users see
+// themselves, their profile, and the deals they're on with their houses.
+//
+pub mod limits;
+pub mod loader;
+pub mod query;
+pub mod types;
+
+use self::loader::Loader;
+use self::query::Query;
+use self::types::*;
+use accounts::types::{CurrentUser, CurrentUser::*, Profile, User};
+use db::{Conn, PooledConnection};
+use deals::types::Deal;
+use houses::types::House;
+use juniper::http::GraphQLRequest;
+use juniper::{EmptyMutation, FieldError, RootNode};
+use result::Error;
+
+pub type Schema = RootNode<'static, Query, EmptyMutation<Context>>;
+
+/// State for one request. The loaders cache rows by key for as long as the
+/// request runs.
+pub struct Context {
+    pub conn: PooledConnection,
+    pub user: CurrentUser,
+    /// Users by id
+    pub users: Loader<User>,
+    /// Profiles by user id
+    pub profiles: Loader<Profile>,
+    /// Houses by id
+    pub houses: Loader<House>,
+    /// Deals by the id of their buyer or seller
+    pub user_deals: Loader<Vec<Deal>>,
+    /// Deals by house id
+    pub house_deals: Loader<Vec<Deal>>,
+}
+
+impl juniper::Context for Context {}
+
+impl Context {
+    pub fn new(conn: PooledConnection, user: CurrentUser) -> Self {
+        Context {
+            conn,
+            user,
+            users: Loader::new(),
+            profiles: Loader::new(),
+            houses: Loader::new(),
+            user_deals: Loader::new(),
+            house_deals: Loader::new(),
+        }
+    }
+
+    pub fn is_admin(&self) -> bool {
+        match self.user {
+            Admin(_) => true,
+            _ => false,
+        }
+    }
+
+    /// The signed in user
+    pub fn viewer(&self) -> Result<&User, Error> {
+        match self.user {
+            Admin(ref user) => Ok(user),
+            Authenticated(ref user) => Ok(user),
+            Anonymous => Err(Error::AccessDenied),
+        }
+    }
+
+    /// Whether a user's account and profile are visible
+    pub fn can_see_user(&self, user_id: i32) -> bool {
+        match self.user {
+            Admin(_) => true,
+            Authenticated(ref user) => user.id == user_id,
+            Anonymous => false,
+        }
+    }
+
+    /// Whether a deal, and its house, are visible
+    pub fn can_see_deal(&self, deal: &Deal) -> bool {
+        deal.role_of(&self.user).is_some()
+    }
+}
+
+impl From<Error> for FieldError {
+    fn from(error: Error) -> Self {
+        // Log the error
+        println!("{:?}", error);
+
+        FieldError::new(error.message(), graphql_value!({ "code": (error.code()) }))
+    }
+}
+
+///
+/// Public API
+///
+
+/// Run a GraphQL request as the current user
+pub fn execute(input: GraphQLInput, user: CurrentUser, conn: Conn) -> Result<GraphQLOutput, Error> {
+    limits::check(&input.query)?;
+    let Conn(conn) = conn;
+
+    // The context holds a connection, which can't be shared across threads,
+    // so the schema is built for each request rather than kept in managed
+    // state
+    let schema = Schema::new(Query, EmptyMutation::new());
+    let context = Context::new(conn, user);
+    let request = GraphQLRequest::new(input.query, input.operation_name, input.variables);
+    let response = request.execute(&schema, &context);
+
+    Ok(GraphQLOutput {
+        ok: response.is_ok(),
+        body: serde_json::to_value(&response)?,
+    })
+}
@@ -0,0 +1,440 @@
+//
+// graphql/query.rs
+//
+// Field names are camelCased by juniper, so `buyer_id` is `buyerId`.
+//
+use accounts::types::{Profile, User};
+use deals::types::Deal;
+use diesel::prelude::*;
+use graphql::Context;
+use houses::types::House;
+use juniper::FieldResult;
+use result::Error;
+use serde::Serialize;
+
+/// Rows a list returns when no limit is asked for
+const DEFAULT_LIMIT: i32 = 30;
+
+/// Most rows a list returns
+const MAX_LIMIT: i32 = 100;
+
+pub struct Query;
+
+///
+/// Helpers
+///
+
+fn limit(requested: Option<i32>) -> i64 {
+    requested.unwrap_or(DEFAULT_LIMIT).max(0).min(MAX_LIMIT) as i64
+}
+
+/// An enum's name as the REST API sends it
+fn name_of<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(|s| s.to_owned()))
+        .unwrap_or_default()
+}
+
+/// Timestamps as the REST API sends them
+fn timestamp(value: &chrono::NaiveDateTime) -> String {
+    name_of(value)
+}
+
+/// Note the relations a list of users will ask for
+fn users_loaded(ctx: &Context, loaded: &[User]) {
+    ctx.profiles.want(loaded.iter().map(|u| u.id));
+    ctx.user_deals.want(loaded.iter().map(|u| u.id));
+}
+
+/// Note the relations a list of deals will ask for
+fn deals_loaded(ctx: &Context, loaded: &[Deal]) {
+    ctx.houses.want(loaded.iter().filter_map(|d| d.house_id));
+    ctx.users.want(loaded.iter().filter_map(|d| d.buyer_id));
+    ctx.users.want(loaded.iter().filter_map(|d| d.seller_id));
+}
+
+/// Note the relations a list of houses will ask for
+fn houses_loaded(ctx: &Context, loaded: &[House]) {
+    ctx.house_deals.want(loaded.iter().map(|h| h.id));
+}
+
+fn load_user(ctx: &Context, user_id: i32) -> Result<Option<User>, Error> {
+    use schema::users::dsl::*;
+
+    ctx.users.load(user_id, |ids| {
+        Ok(users
+            .filter(id.eq_any(ids))
+            .load::<User>(&*ctx.conn)?
+            .into_iter()
+            .map(|u| (u.id, u))
+            .collect())
+    })
+}
+
+fn load_profile(ctx: &Context, user_id: i32) -> Result<Option<Profile>, Error> {
+    use schema::profiles::dsl::*;
+
+    ctx.profiles.load(user_id, |ids| {
+        Ok(profiles
+            .filter(uid.eq_any(ids))
+            .load::<Profile>(&*ctx.conn)?
+            .into_iter()
+            .map(|p| (p.uid, p))
+            .collect())
+    })
+}
+
+fn load_house(ctx: &Context, house_id: i32) -> Result<Option<House>, Error> {
+    use schema::houses::dsl::*;
+
+    ctx.houses.load(house_id, |ids| {
+        Ok(houses
+            .filter(id.eq_any(ids))
+            .load::<House>(&*ctx.conn)?
+            .into_iter()
+            .map(|h| (h.id, h))
+            .collect())
+    })
+}
+
+/// A user's deals that the viewer can see, as buyer or seller
+fn load_user_deals(ctx: &Context, user_id: i32) -> Result<Vec<Deal>, Error> {
+    use schema::deals::dsl::*;
+
+    let found = ctx.user_deals.load(user_id, |ids| {
+        let loaded = deals
+            .filter(buyer_id.eq_any(ids).or(seller_id.eq_any(ids)))
+            .order_by(created.desc())
+            .load::<Deal>(&*ctx.conn)?;
+        Ok(ids
+            .iter()
+            .map(|uid| {
+                let theirs = loaded
+                    .iter()
+                    .filter(|d| d.buyer_id == Some(*uid) || d.seller_id == Some(*uid))
+                    .cloned()
+                    .collect();
+                (*uid, theirs)
+            })
+            .collect())
+    })?;
+    Ok(visible_deals(ctx, found.unwrap_or_default()))
+}
+
+/// A house's deals that the viewer can see
+fn load_house_deals(ctx: &Context, hid: i32) -> Result<Vec<Deal>, Error> {
+    use schema::deals::dsl::*;
+
+    let found = ctx.house_deals.load(hid, |ids| {
+        let loaded = deals
+            .filter(house_id.eq_any(ids))
+            .order_by(created.desc())
+            .load::<Deal>(&*ctx.conn)?;
+        Ok(ids
+            .iter()
+            .map(|h| {
+                let theirs = loaded
+                    .iter()
+                    .filter(|d| d.house_id == Some(*h))
+                    .cloned()
+                    .collect();
+                (*h, theirs)
+            })
+            .collect())
+    })?;
+    Ok(visible_deals(ctx, found.unwrap_or_default()))
+}
+
+fn visible_deals(ctx: &Context, found: Vec<Deal>) -> Vec<Deal> {
+    let visible = found
+        .into_iter()
+        .filter(|d| ctx.can_see_deal(d))
+        .collect::<Vec<Deal>>();
+    deals_loaded(ctx, &visible);
+    visible
+}
+
+/// A user, if the viewer can see them
+fn visible_user(ctx: &Context, user_id: Option<i32>) -> Result<Option<User>, Error> {
+    match user_id {
+        Some(uid) if ctx.can_see_user(uid) => load_user(ctx, uid),
+        _ => Ok(None),
+    }
+}
+
+fn query_user(ctx: &Context, user_id: i32) -> Result<Option<User>, Error> {
+    if !ctx.can_see_user(user_id) {
+        return Err(Error::AccessDenied);
+    }
+    load_user(ctx, user_id)
+}
+
+fn query_users(ctx: &Context, count: Option<i32>) -> Result<Vec<User>, Error> {
+    use schema::users::dsl::*;
+
+    if !ctx.is_admin() {
+        return Err(Error::AccessDenied);
+    }
+
+    let u = users
+        .order_by(id)
+        .limit(limit(count))
+        .load::<User>(&*ctx.conn)?;
+    users_loaded(ctx, &u);
+    Ok(u)
+}
+
+fn query_deal(ctx: &Context, deal_id: i32) -> Result<Option<Deal>, Error> {
+    use schema::deals::dsl::*;
+
+    let d = deals.find(deal_id).first::<Deal>(&*ctx.conn).optional()?;
+    match d {
+        Some(ref d) if !ctx.can_see_deal(d) => Err(Error::AccessDenied),
+        d => Ok(d),
+    }
+}
+
+/// Deals the viewer can see, newest first. Admins can pick a buyer's.
+fn query_deals(ctx: &Context, buyer: Option<i32>, count: Option<i32>) -> Result<Vec<Deal>, Error> {
+    use schema::deals::dsl::*;
+
+    let viewer = ctx.viewer()?;
+    let mut q = deals.into_boxed();
+    if !ctx.is_admin() {
+        q = q.filter(buyer_id.eq(viewer.id).or(seller_id.eq(viewer.id)));
+    }
+    if let Some(b) = buyer {
+        q = q.filter(buyer_id.eq(b));
+    }
+
+    let d = q
+        .order_by(created.desc())
+        .limit(limit(count))
+        .load::<Deal>(&*ctx.conn)?;
+    deals_loaded(ctx, &d);
+    Ok(d)
+}
+
+fn query_house(ctx: &Context, house_id: i32) -> Result<Option<House>, Error> {
+    if !ctx.is_admin() {
+        return Err(Error::AccessDenied);
+    }
+    load_house(ctx, house_id)
+}
+
+fn query_houses(ctx: &Context, count: Option<i32>) -> Result<Vec<House>, Error> {
+    use schema::houses::dsl::*;
+
+    if !ctx.is_admin() {
+        return Err(Error::AccessDenied);
+    }
+
+    let h = houses
+        .order_by(created.desc())
+        .limit(limit(count))
+        .load::<House>(&*ctx.conn)?;
+    houses_loaded(ctx, &h);
+    Ok(h)
+}
+
+///
+/// Schema
+///
+
+graphql_object!(Query: Context |&self| {
+    field me(&executor) -> FieldResult<User> {
+        Ok(executor.context().viewer()?.clone())
+    }
+
+    field user(&executor, id: i32) -> FieldResult<Option<User>> {
+        Ok(query_user(executor.context(), id)?)
+    }
+
+    field users(&executor, limit: Option<i32>) -> FieldResult<Vec<User>> {
+        Ok(query_users(executor.context(), limit)?)
+    }
+
+    field deal(&executor, id: i32) -> FieldResult<Option<Deal>> {
+        Ok(query_deal(executor.context(), id)?)
+    }
+
+    field deals(&executor, buyer_id: Option<i32>, limit: Option<i32>) -> FieldResult<Vec<Deal>> {
+        Ok(query_deals(executor.context(), buyer_id, limit)?)
+    }
+
+    field house(&executor, id: i32) -> FieldResult<Option<House>> {
+        Ok(query_house(executor.context(), id)?)
+    }
+
+    field houses(&executor, limit: Option<i32>) -> FieldResult<Vec<House>> {
+        Ok(query_houses(executor.context(), limit)?)
+    }
+});
+
+graphql_object!(User: Context |&self| {
+    field id() -> i32 {
+        self.id
+    }
+
+    field name() -> &str {
+        &self.name
+    }
+
+    field email() -> &str {
+        &self.email
+    }
+
+    field roles() -> Vec<String> {
+        self.roles.iter().map(name_of).collect()
+    }
+
+    field profile(&executor) -> FieldResult<Option<Profile>> {
+        Ok(load_profile(executor.context(), self.id)?)
+    }
+
+    field deals(&executor) -> FieldResult<Vec<Deal>> {
+        Ok(load_user_deals(executor.context(), self.id)?)
+    }
+});
+
+graphql_object!(Profile: Context |&self| {
+    field id() -> i32 {
+        self.id
+    }
+
+    field user_id() -> i32 {
+        self.uid
+    }
+
+    field title() -> &str {
+        &self.title
+    }
+
+    field intro() -> &str {
+        &self.intro
+    }
+
+    field body() -> &str {
+        &self.body
+    }
+
+    field user(&executor) -> FieldResult<Option<User>> {
+        Ok(visible_user(executor.context(), Some(self.uid))?)
+    }
+});
+
+graphql_object!(Deal: Context |&self| {
+    field id() -> i32 {
+        self.id
+    }
+
+    field title() -> &str {
+        &self.title
+    }
+
+    field status() -> String {
+        name_of(&self.status)
+    }
+
+    field access_code(&executor) -> Option<&str> {
+        if executor.context().is_admin() {
+            Some(&self.access_code)
+        } else {
+            None
+        }
+    }
+
+    field buyer_id() -> Option<i32> {
+        self.buyer_id
+    }
+
+    field seller_id() -> Option<i32> {
+        self.seller_id
+    }
+
+    field house_id() -> Option<i32> {
+        self.house_id
+    }
+
+    field created() -> String {
+        timestamp(&self.created)
+    }
+
+    field updated() -> String {
+        timestamp(&self.updated)
+    }
+
+    field buyer(&executor) -> FieldResult<Option<User>> {
+        Ok(visible_user(executor.context(), self.buyer_id)?)
+    }
+
+    field seller(&executor) -> FieldResult<Option<User>> {
+        Ok(visible_user(executor.context(), self.seller_id)?)
+    }
+
+    field house(&executor) -> FieldResult<Option<House>> {
+        match self.house_id {
+            Some(hid) => Ok(load_house(executor.context(), hid)?),
+            None => Ok(None),
+        }
+    }
+});
+
+graphql_object!(House: Context |&self| {
+    field id() -> i32 {
+        self.id
+    }
+
+    field address() -> &str {
+        &self.address
+    }
+
+    field formatted_address() -> Option<String> {
+        self.parsed_google_address().map(|a| a.formatted_address)
+    }
+
+    field street_number() -> Option<&str> {
+        self.street_number.as_ref().map(|s| s.as_str())
+    }
+
+    field route() -> Option<&str> {
+        self.route.as_ref().map(|s| s.as_str())
+    }
+
+    field city() -> Option<&str> {
+        self.city.as_ref().map(|s| s.as_str())
+    }
+
+    field state() -> Option<&str> {
+        self.state.as_ref().map(|s| s.as_str())
+    }
+
+    field postal_code() -> Option<&str> {
+        self.postal_code.as_ref().map(|s| s.as_str())
+    }
+
+    field country() -> Option<&str> {
+        self.country.as_ref().map(|s| s.as_str())
+    }
+
+    field lat() -> Option<f64> {
+        self.lat
+    }
+
+    field lng() -> Option<f64> {
+        self.lng
+    }
+
+    field created() -> String {
+        timestamp(&self.created)
+    }
+
+    field updated() -> String {
+        timestamp(&self.updated)
+    }
+
+    field deals(&executor) -> FieldResult<Vec<Deal>> {
+        Ok(load_house_deals(executor.context(), self.id)?)
+    }
+});
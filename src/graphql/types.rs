@@ -0,0 +1,20 @@
+//
+// graphql/types.rs
+//
+
+/// A GraphQL request as clients POST it
+#[derive(Deserialize)]
+pub struct GraphQLInput {
+    pub query: String,
+    #[serde(rename = "operationName")]
+    pub operation_name: Option<String>,
+    pub variables: Option<juniper::InputValue>,
+}
+
+/// An executed request's `data` and `errors`
+pub struct GraphQLOutput {
+    /// Whether the request could run at all. Errors in single fields don't
+    /// count.
+    pub ok: bool,
+    pub body: serde_json::Value,
+}
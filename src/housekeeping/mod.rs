@@ -1,10 +1,51 @@
+pub mod scheduler;
+pub mod stats;
+pub mod types;
+
+use self::types::*;
+use accounts;
 use accounts::types::*;
 use db::single_connection;
+use deals;
 use diesel::prelude::*;
 use diesel::result::DatabaseErrorKind;
 use diesel::result::Error::DatabaseError;
+use offers;
 use result::Error;
 use schema::users::dsl::*;
+use tasks;
+
+/// Every periodic task, run by the scheduler
+pub const PERIODIC: &[Periodic] = &[
+    Periodic {
+        name: "purge_sessions",
+        schedule: Schedule::Every { minutes: 60 },
+        run: accounts::purge_sessions,
+    },
+    Periodic {
+        name: "expire_offers",
+        schedule: Schedule::Every { minutes: 15 },
+        run: offers::expire_offers,
+    },
+    Periodic {
+        name: "expire_access_codes",
+        schedule: Schedule::Daily { hour: 3, minute: 0 },
+        run: deals::expire_access_codes,
+    },
+    Periodic {
+        name: "follow_up_reminders",
+        schedule: Schedule::Daily {
+            hour: 13,
+            minute: 0,
+        },
+        run: tasks::add_follow_up_reminders,
+    },
+    Periodic {
+        name: "daily_stats",
+        schedule: Schedule::Every { minutes: 60 },
+        run: stats::roll_up,
+    },
+];
 
 pub fn run() -> Result<(), Error> {
     println!("Running housekeeping job");
@@ -22,7 +63,7 @@ pub fn run() -> Result<(), Error> {
         .get_result::<User>(&conn)
         .map_err(|e| match e {
             DatabaseError(DatabaseErrorKind::UniqueViolation, _info) => {
-                Error::from_custom_validation("email_taken", "email", "Email is taken")
+                Error::from_conflict("email_taken", "email", "Email is taken")
             }
             _ => Error::from(e),
         })?;
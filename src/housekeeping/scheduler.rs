@@ -0,0 +1,121 @@
+//
+// housekeeping/scheduler.rs
+//
+// Every process runs the scheduler. Each task's run is guarded by a Postgres
+// advisory lock and its last run time is kept in `scheduled_runs`, so a task
+// runs once per schedule however many processes are up.
+//
+use db::create_sized_pool;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::sql_types::Integer;
+use housekeeping::types::*;
+use housekeeping::PERIODIC;
+use result::Error;
+use std::thread;
+use std::time::Duration;
+
+/// How often the scheduler checks for due tasks
+const TICK: Duration = Duration::from_secs(30);
+
+/// First half of the scheduler's advisory lock keys, keeping them apart from
+/// any other locks taken on the database
+const LOCK_CLASS: i32 = 0x5343_4844;
+
+///
+/// Helpers
+///
+
+/// Second half of a task's advisory lock key, an FNV-1a hash of its name
+fn lock_key(task_name: &str) -> i32 {
+    task_name.bytes().fold(0x811c_9dc5u32, |hash, b| {
+        (hash ^ b as u32).wrapping_mul(0x0100_0193)
+    }) as i32
+}
+
+/// Take a task's lock until the end of the transaction. Returns false if
+/// another process holds it.
+fn try_lock(conn: &PgConnection, task: &Periodic) -> Result<bool, Error> {
+    let lock = diesel::sql_query("SELECT pg_try_advisory_xact_lock($1, $2) AS locked")
+        .bind::<Integer, _>(LOCK_CLASS)
+        .bind::<Integer, _>(lock_key(task.name))
+        .get_result::<AdvisoryLock>(conn)?;
+    Ok(lock.locked)
+}
+
+/// Run a task if it's due and no other process is running it. Returns
+/// whether it ran.
+fn run_if_due(conn: &PgConnection, task: &Periodic) -> Result<bool, Error> {
+    use schema::scheduled_runs::dsl::*;
+
+    conn.transaction::<_, Error, _>(|| {
+        if !try_lock(conn, task)? {
+            return Ok(false);
+        }
+
+        // Read under the lock, so a run another process just finished counts
+        let now = chrono::Utc::now().naive_utc();
+        let last = scheduled_runs
+            .find(task.name)
+            .first::<ScheduledRun>(conn)
+            .optional()?;
+        if let Some(last) = last {
+            if task.schedule.next_after(last.last_run) > now {
+                return Ok(false);
+            }
+        }
+
+        // The task gets its own savepoint, so a failure undoes its work but
+        // not the record of the attempt
+        let error = match conn.transaction::<_, Error, _>(|| (task.run)(conn)) {
+            Ok(count) => {
+                println!("Scheduled task {} done, {} records", task.name, count);
+                None
+            }
+            Err(e) => {
+                println!("Scheduled task {} failed: {:?}", task.name, e);
+                Some(format!("{:?}", e))
+            }
+        };
+
+        diesel::insert_into(scheduled_runs)
+            .values(&ScheduledRun {
+                name: task.name.to_owned(),
+                last_run: now,
+                last_error: error.clone(),
+                updated: now,
+            })
+            .on_conflict(name)
+            .do_update()
+            .set((last_run.eq(now), last_error.eq(error), updated.eq(now)))
+            .execute(conn)?;
+        Ok(true)
+    })
+}
+
+fn tick(conn: &PgConnection) {
+    for task in PERIODIC {
+        if let Err(e) = run_if_due(conn, task) {
+            println!("Scheduler error on {}: {:?}", task.name, e);
+        }
+    }
+}
+
+///
+/// Public API
+///
+
+/// Start the scheduler in the background
+pub fn spawn() -> thread::JoinHandle<()> {
+    let pool = create_sized_pool(1);
+    thread::Builder::new()
+        .name("scheduler".to_owned())
+        .spawn(move || loop {
+            match pool.get() {
+                Ok(conn) => tick(&conn),
+                Err(e) => println!("Scheduler couldn't connect: {:?}", e),
+            }
+            thread::sleep(TICK);
+        })
+        .expect("Failed to start scheduler")
+}
@@ -0,0 +1,113 @@
+//
+// housekeeping/stats.rs
+//
+use accounts::types::{CurrentUser, CurrentUser::*};
+use db::Conn;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use housekeeping::types::*;
+use offers::types::OfferStatus;
+use result::{Error, Payload, Response};
+
+/// Days rolled up on each run. Earlier days are recounted too, so a missed
+/// run catches up.
+const ROLLUP_DAYS: i64 = 7;
+
+/// Most days a single listing returns
+const MAX_DAYS: i64 = 366;
+
+///
+/// Helpers
+///
+
+/// Count a single day's activity
+fn count_day(conn: &PgConnection, date: chrono::NaiveDate) -> Result<DailyStats, Error> {
+    use schema::{deals, mailer_scans, mailers, offers, seller_responses};
+
+    let start = date.and_hms(0, 0, 0);
+    let end = start + chrono::Duration::days(1);
+
+    let new_deals = deals::table
+        .filter(deals::created.ge(start))
+        .filter(deals::created.lt(end))
+        .count()
+        .get_result::<i64>(conn)?;
+    let sent = mailers::table
+        .filter(mailers::created.ge(start))
+        .filter(mailers::created.lt(end))
+        .count()
+        .get_result::<i64>(conn)?;
+    let scans = mailer_scans::table
+        .filter(mailer_scans::created.ge(start))
+        .filter(mailer_scans::created.lt(end))
+        .count()
+        .get_result::<i64>(conn)?;
+    let responses = seller_responses::table
+        .filter(seller_responses::created.ge(start))
+        .filter(seller_responses::created.lt(end))
+        .count()
+        .get_result::<i64>(conn)?;
+    let accepted = offers::table
+        .filter(offers::status.eq(OfferStatus::Accepted))
+        .filter(offers::updated.ge(start))
+        .filter(offers::updated.lt(end))
+        .count()
+        .get_result::<i64>(conn)?;
+
+    Ok(DailyStats {
+        day: date,
+        deals_created: new_deals as i32,
+        mailers_sent: sent as i32,
+        mailer_scans: scans as i32,
+        seller_responses: responses as i32,
+        offers_accepted: accepted as i32,
+        created: chrono::Utc::now().naive_utc(),
+    })
+}
+
+/// Recount the last few days' activity, up to and including today
+pub fn roll_up(conn: &PgConnection) -> Result<usize, Error> {
+    use schema::daily_stats::dsl::*;
+
+    let today = chrono::Utc::now().naive_utc().date();
+    let days = (0..ROLLUP_DAYS)
+        .map(|n| count_day(conn, today - chrono::Duration::days(n)))
+        .collect::<Result<Vec<DailyStats>, Error>>()?;
+
+    let first = today - chrono::Duration::days(ROLLUP_DAYS - 1);
+    diesel::delete(daily_stats.filter(day.ge(first))).execute(conn)?;
+    diesel::insert_into(daily_stats)
+        .values(&days)
+        .execute(conn)
+        .map_err(|e| Error::from(e))
+}
+
+///
+/// Public API
+///
+
+/// Get daily activity counts, most recent first
+pub fn get_daily_stats(
+    query: DailyStatsQuery,
+    user: CurrentUser,
+    conn: Conn,
+) -> Response<Vec<DailyStats>> {
+    use schema::daily_stats::dsl::*;
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let s = daily_stats
+        .order_by(day.desc())
+        .limit(query.days.unwrap_or(30).max(1).min(MAX_DAYS))
+        .load::<DailyStats>(&conn)?;
+
+    Ok(Payload {
+        data: s,
+        success: true,
+        ..Default::default()
+    })
+}
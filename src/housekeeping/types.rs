@@ -0,0 +1,74 @@
+//
+// housekeeping/types.rs
+//
+use diesel::pg::PgConnection;
+use diesel::sql_types::Bool;
+use result::Error;
+use schema::{daily_stats, scheduled_runs};
+
+/// When a periodic task runs. Times are UTC.
+#[derive(Debug, Clone, Copy)]
+pub enum Schedule {
+    /// Every so many minutes
+    Every { minutes: i64 },
+    /// Once a day at the given time
+    Daily { hour: u32, minute: u32 },
+}
+
+impl Schedule {
+    /// First time the task is due after a run at `last`
+    pub fn next_after(&self, last: chrono::NaiveDateTime) -> chrono::NaiveDateTime {
+        match *self {
+            Schedule::Every { minutes } => last + chrono::Duration::minutes(minutes),
+            Schedule::Daily { hour, minute } => {
+                let at = last.date().and_hms(hour, minute, 0);
+                if at > last {
+                    at
+                } else {
+                    at + chrono::Duration::days(1)
+                }
+            }
+        }
+    }
+}
+
+/// A task the scheduler runs. Returns how many records it touched.
+pub struct Periodic {
+    /// Unique, and kept stable: it names the task's run record and lock
+    pub name: &'static str,
+    pub schedule: Schedule,
+    pub run: fn(&PgConnection) -> Result<usize, Error>,
+}
+
+#[derive(Serialize, Identifiable, Clone, Queryable, Insertable, Debug)]
+#[table_name = "scheduled_runs"]
+#[primary_key(name)]
+pub struct ScheduledRun {
+    pub name: String,
+    pub last_run: chrono::NaiveDateTime,
+    pub last_error: Option<String>,
+    pub updated: chrono::NaiveDateTime,
+}
+
+#[derive(QueryableByName)]
+pub struct AdvisoryLock {
+    #[sql_type = "Bool"]
+    pub locked: bool,
+}
+
+#[derive(Serialize, Clone, Queryable, Insertable, Debug)]
+#[table_name = "daily_stats"]
+pub struct DailyStats {
+    pub day: chrono::NaiveDate,
+    pub deals_created: i32,
+    pub mailers_sent: i32,
+    pub mailer_scans: i32,
+    pub seller_responses: i32,
+    pub offers_accepted: i32,
+    pub created: chrono::NaiveDateTime,
+}
+
+#[derive(FromForm, Default)]
+pub struct DailyStatsQuery {
+    pub days: Option<i64>,
+}
@@ -0,0 +1,187 @@
+//
+// houses/address.rs
+//
+use houses::types::{AddressComponents, AddressFields, GoogleAddress};
+
+/// Street words and their USPS abbreviations.
+///
+/// Must stay in sync with the `canonical_address` function in the
+/// `make_canonical_address_unique` migration.
+const ABBREVIATIONS: &[(&str, &str)] = &[
+    ("street", "st"),
+    ("avenue", "ave"),
+    ("road", "rd"),
+    ("drive", "dr"),
+    ("lane", "ln"),
+    ("boulevard", "blvd"),
+    ("court", "ct"),
+    ("place", "pl"),
+    ("terrace", "ter"),
+    ("circle", "cir"),
+    ("highway", "hwy"),
+    ("parkway", "pkwy"),
+    ("square", "sq"),
+    ("trail", "trl"),
+    ("north", "n"),
+    ("south", "s"),
+    ("east", "e"),
+    ("west", "w"),
+    ("northeast", "ne"),
+    ("northwest", "nw"),
+    ("southeast", "se"),
+    ("southwest", "sw"),
+    ("apartment", "apt"),
+    ("suite", "ste"),
+];
+
+/// Countries dropped from the end of a free-text address, in canonical
+/// form. Canonical addresses built from google's components leave the
+/// country out, so free text has to as well for the two to match.
+///
+/// Must stay in sync with the `make_canonical_address_unique` migration.
+const COUNTRIES: &[&str] = &["usa", "us", "united states", "united states of america"];
+
+/// Accented letters and the plain letters they fold to, matched up by
+/// position.
+///
+/// Must stay in sync with the `make_canonical_address_unique` migration.
+const ACCENTED: &str = "ÀÁÂÃÄÅÇÈÉÊËÌÍÎÏÑÒÓÔÕÖØÙÚÛÜÝàáâãäåçèéêëìíîïñòóôõöøùúûüýÿ";
+const UNACCENTED: &str = "aaaaaaceeeeiiiinoooooouuuuyaaaaaaceeeeiiiinoooooouuuuyy";
+
+/// Non-ASCII spaces and punctuation that separate words, like ASCII ones do
+const PUNCTUATION: &str = "\u{a0}‘’“”–—";
+
+/// Normalize a free-text address so that trivially different spellings
+/// ("123 Main Street" and "123 main st.") compare equal.
+///
+/// Accented letters are folded to plain ones ("Café" and "Cafe" match).
+/// Other non-ASCII letters are kept as they are.
+pub fn canonicalize(raw: &str) -> String {
+    let folded = raw
+        .chars()
+        .map(|c| match ACCENTED.chars().position(|a| a == c) {
+            Some(i) => UNACCENTED.chars().nth(i).unwrap_or(c),
+            None => c.to_ascii_lowercase(),
+        })
+        .collect::<String>();
+
+    folded
+        .split(|c: char| {
+            if c.is_ascii() {
+                !c.is_ascii_alphanumeric()
+            } else {
+                PUNCTUATION.contains(c)
+            }
+        })
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            ABBREVIATIONS
+                .iter()
+                .find(|(long, _)| *long == word)
+                .map_or(word, |(_, short)| *short)
+        })
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Pull the typed address fields out of a google address.
+///
+/// Component types are matched both in the frontend's encoding
+/// (`StreetNumber`) and in google's own (`street_number`).
+pub fn fields_from_google(address: &str, google: Option<&GoogleAddress>) -> AddressFields {
+    let mut fields = AddressFields::default();
+    let mut subpremise = None;
+
+    if let Some(g) = google {
+        let components = &g.address_components;
+        fields.street_number = long_name(components, &["StreetNumber", "street_number"]);
+        fields.route = long_name(components, &["Route", "route"]);
+        subpremise = long_name(components, &["Subpremise", "subpremise"]);
+        fields.city = long_name(components, &["Locality", "locality"])
+            .or_else(|| long_name(components, &["PostalTown", "postal_town"]))
+            .or_else(|| long_name(components, &["Sublocality", "sublocality"]));
+        fields.state = short_name(
+            components,
+            &["AdministrativeAreaLevel1", "administrative_area_level_1"],
+        );
+        fields.postal_code = long_name(components, &["PostalCode", "postal_code"]);
+        fields.country = short_name(components, &["Country", "country"]);
+        fields.place_id = non_blank(&g.place_id);
+        fields.lat = Some(g.geometry.location.latitude);
+        fields.lng = Some(g.geometry.location.longitude);
+    }
+
+    let canonical = match (&fields.street_number, &fields.route) {
+        (Some(number), Some(route)) => {
+            let parts = [
+                Some(number),
+                Some(route),
+                subpremise.as_ref(),
+                fields.city.as_ref(),
+                fields.state.as_ref(),
+                fields.postal_code.as_ref(),
+            ];
+            let joined = parts
+                .iter()
+                .filter_map(|p| p.map(|s| s.as_str()))
+                .collect::<Vec<&str>>()
+                .join(" ");
+            canonicalize(&joined)
+        }
+        _ => {
+            let mut countries = COUNTRIES
+                .iter()
+                .map(|c| (*c).to_owned())
+                .collect::<Vec<String>>();
+            if let Some(g) = google {
+                let country = long_name(&g.address_components, &["Country", "country"]);
+                countries.extend(
+                    country
+                        .iter()
+                        .chain(&fields.country)
+                        .map(|c| canonicalize(c)),
+                );
+            }
+            without_country(canonicalize(address), &countries)
+        }
+    };
+    fields.canonical_address = Some(canonical);
+
+    fields
+}
+
+/// Drop the longest of `countries` that ends a canonical address
+fn without_country(canonical: String, countries: &[String]) -> String {
+    let suffix = countries
+        .iter()
+        .filter(|c| !c.is_empty())
+        .map(|c| format!(" {}", c))
+        .filter(|c| canonical.ends_with(c.as_str()))
+        .max_by_key(|c| c.len());
+
+    match suffix {
+        Some(c) => canonical[..canonical.len() - c.len()].to_owned(),
+        None => canonical,
+    }
+}
+
+fn find<'a>(components: &'a [AddressComponents], kinds: &[&str]) -> Option<&'a AddressComponents> {
+    components
+        .iter()
+        .find(|c| c.types.iter().any(|t| kinds.contains(&t.as_str())))
+}
+
+fn long_name(components: &[AddressComponents], kinds: &[&str]) -> Option<String> {
+    find(components, kinds).and_then(|c| non_blank(&c.long_name))
+}
+
+fn short_name(components: &[AddressComponents], kinds: &[&str]) -> Option<String> {
+    find(components, kinds).and_then(|c| non_blank(&c.short_name))
+}
+
+fn non_blank(value: &str) -> Option<String> {
+    match value.trim() {
+        "" => None,
+        v => Some(v.to_owned()),
+    }
+}
@@ -0,0 +1,205 @@
+//
+// houses/geo.rs
+//
+use diesel::dsl::sql;
+use diesel::expression::SqlLiteral;
+use diesel::sql_types::Double;
+use houses::types::{BoundingBox, Coordinates};
+use result::Error;
+use std::cmp::Ordering;
+
+const EARTH_RADIUS_M: f64 = 6_371_008.8;
+const METERS_PER_DEGREE_LAT: f64 = 111_320.0;
+const MAX_RADIUS_M: f64 = 50_000.0;
+const DEFAULT_RADIUS_M: f64 = 1_000.0;
+
+/// Area to search, from a center point and radius and/or a bounding box.
+/// A box whose west edge is east of its east edge crosses the antimeridian.
+pub struct SearchArea {
+    pub bbox: BoundingBox,
+    pub center: Option<Coordinates>,
+    pub radius_m: Option<f64>,
+}
+
+impl SearchArea {
+    /// Build the area to search from query params.
+    ///
+    /// Returns `None` when no geographic filter was asked for.
+    pub fn from_query(
+        near: Option<Coordinates>,
+        radius_m: Option<f64>,
+        bbox: Option<BoundingBox>,
+    ) -> Result<Option<SearchArea>, Error> {
+        if let Some(center) = near {
+            if !(center.lat.abs() <= 90.0 && center.lng.abs() <= 180.0) {
+                return Err(Error::from_custom_validation(
+                    "invalid_coordinates",
+                    "near",
+                    "Coordinates are out of range",
+                ));
+            }
+        }
+        if let Some(b) = bbox {
+            let in_range = b.south.abs() <= 90.0
+                && b.north.abs() <= 90.0
+                && b.west.abs() <= 180.0
+                && b.east.abs() <= 180.0;
+            if !in_range {
+                return Err(Error::from_custom_validation(
+                    "invalid_bbox",
+                    "bbox",
+                    "Bounding box is out of range",
+                ));
+            }
+            if b.south > b.north {
+                return Err(Error::from_custom_validation(
+                    "invalid_bbox",
+                    "bbox",
+                    "Bounding box's south edge is north of its north edge",
+                ));
+            }
+        }
+
+        let radius_m = match (near, radius_m) {
+            (Some(_), Some(r)) if !(r > 0.0 && r <= MAX_RADIUS_M) => {
+                return Err(Error::from_custom_validation(
+                    "invalid_radius",
+                    "radius_m",
+                    "Radius must be between 0 and 50000 meters",
+                ));
+            }
+            (Some(_), Some(r)) => Some(r),
+            (Some(_), None) => Some(DEFAULT_RADIUS_M),
+            (None, _) => None,
+        };
+
+        Ok(match (near, bbox) {
+            (_, Some(bbox)) => Some(SearchArea {
+                bbox,
+                center: near,
+                radius_m,
+            }),
+            (Some(center), None) => Some(SearchArea {
+                bbox: bounding_box(&center, radius_m.unwrap_or(DEFAULT_RADIUS_M)),
+                center: Some(center),
+                radius_m,
+            }),
+            (None, None) => None,
+        })
+    }
+
+    /// Whether the box wraps past 180° longitude, so it holds longitudes east
+    /// of `west` or west of `east` rather than between them
+    pub fn crosses_antimeridian(&self) -> bool {
+        self.bbox.west > self.bbox.east
+    }
+
+    /// Where results are sorted from: the center, or else the middle of the
+    /// box
+    fn origin(&self) -> Coordinates {
+        self.center.unwrap_or_else(|| {
+            let b = &self.bbox;
+            let east = if self.crosses_antimeridian() {
+                b.east + 360.0
+            } else {
+                b.east
+            };
+            Coordinates {
+                lat: (b.south + b.north) / 2.0,
+                lng: wrap_lng((b.west + east) / 2.0),
+            }
+        })
+    }
+
+    /// SQL ordering rows by approximate squared distance from the area's
+    /// origin, on `table`'s `lat` and `lng` columns. Sorting by it before a
+    /// limit keeps the nearest rows; `nearest_first` then works out exact
+    /// distances.
+    pub fn distance_order(&self, table: &str) -> SqlLiteral<Double> {
+        let origin = self.origin();
+        // Coordinates are validated, so formatting them in is safe
+        sql(&format!(
+            "power({t}.lat - ({lat}), 2) + \
+             power(least(abs({t}.lng - ({lng})), 360 - abs({t}.lng - ({lng}))) * {scale}, 2)",
+            t = table,
+            lat = origin.lat,
+            lng = origin.lng,
+            scale = origin.lat.to_radians().cos(),
+        ))
+    }
+
+    /// Distance in meters from the center of the area, if it has one
+    pub fn distance_m(&self, point: &Coordinates) -> Option<f64> {
+        self.center.map(|c| distance_m(&c, point))
+    }
+
+    /// Keep the items inside the area, nearest first.
+    ///
+    /// Items are expected to be pre-filtered on the bounding box by the
+    /// database, which is cheap and indexed; this trims the corners off to
+    /// the exact radius and attaches distances.
+    pub fn nearest_first<T, F>(&self, items: Vec<T>, coordinates: F) -> Vec<(T, Option<f64>)>
+    where
+        F: Fn(&T) -> Option<Coordinates>,
+    {
+        let mut results = items
+            .into_iter()
+            .filter_map(|item| {
+                let distance = coordinates(&item).and_then(|c| self.distance_m(&c));
+                match (self.radius_m, distance) {
+                    (Some(radius), Some(d)) if d > radius => None,
+                    _ => Some((item, distance)),
+                }
+            })
+            .collect::<Vec<(T, Option<f64>)>>();
+
+        results.sort_by(|a, b| match (a.1, b.1) {
+            (Some(x), Some(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        });
+
+        results
+    }
+}
+
+/// Great-circle distance in meters
+pub fn distance_m(a: &Coordinates, b: &Coordinates) -> f64 {
+    let d_lat = (b.lat - a.lat).to_radians();
+    let d_lng = (b.lng - a.lng).to_radians();
+    let h = (d_lat / 2.0).sin().powi(2)
+        + a.lat.to_radians().cos() * b.lat.to_radians().cos() * (d_lng / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS_M * h.sqrt().min(1.0).asin()
+}
+
+/// Longitude brought back into -180..180
+fn wrap_lng(lng: f64) -> f64 {
+    if lng > 180.0 {
+        lng - 360.0
+    } else if lng < -180.0 {
+        lng + 360.0
+    } else {
+        lng
+    }
+}
+
+/// Smallest lat/lng box containing the circle around `center`. Its edges
+/// wrap past 180° longitude, so it may cross the antimeridian.
+pub fn bounding_box(center: &Coordinates, radius_m: f64) -> BoundingBox {
+    let d_lat = radius_m / METERS_PER_DEGREE_LAT;
+    let d_lng = radius_m / (METERS_PER_DEGREE_LAT * center.lat.to_radians().cos().max(0.01));
+    let (west, east) = if d_lng >= 180.0 {
+        (-180.0, 180.0)
+    } else {
+        (wrap_lng(center.lng - d_lng), wrap_lng(center.lng + d_lng))
+    };
+
+    BoundingBox {
+        south: (center.lat - d_lat).max(-90.0),
+        west,
+        north: (center.lat + d_lat).min(90.0),
+        east,
+    }
+}
@@ -0,0 +1,387 @@
+//
+// houses/mod.rs
+//
+pub mod address;
+pub mod geo;
+pub mod types;
+
+use self::geo::SearchArea;
+use self::types::*;
+use accounts::types::{CurrentUser, CurrentUser::*};
+use db::Conn;
+use deals::types::Deal;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::result::DatabaseErrorKind;
+use diesel::result::Error::DatabaseError;
+use result::{Error, Payload, Response};
+use validator::Validate;
+
+///
+/// Helpers
+///
+
+/// Find the house with an address, creating it if it doesn't exist yet.
+///
+/// Houses are matched on google's place id first, then on the canonical
+/// form of the address, and finally on the exact address. When another
+/// request creates the same house first, its house is used.
+pub fn find_or_create(
+    conn: &PgConnection,
+    formatted_address: &str,
+    google: Option<GoogleAddress>,
+) -> Result<i32, Error> {
+    use schema::houses;
+
+    let fields = address::fields_from_google(formatted_address, google.as_ref());
+
+    if let Some(house) = find_existing(conn, formatted_address, &fields)? {
+        return Ok(house);
+    }
+
+    let google_address = match google {
+        Some(g) => Some(serde_json::to_value(g)?),
+        None => None,
+    };
+
+    let inserted = diesel::insert_into(houses::table)
+        .values(&NewHouse::new(
+            formatted_address.to_owned(),
+            google_address,
+            fields.clone(),
+        ))
+        .on_conflict_do_nothing()
+        .returning(houses::id)
+        .get_result::<i32>(conn)
+        .optional()?;
+
+    match inserted {
+        Some(house) => Ok(house),
+        None => find_existing(conn, formatted_address, &fields)?
+            .ok_or_else(|| Error::from(diesel::NotFound)),
+    }
+}
+
+/// Look up the house matching an address, see `find_or_create`
+fn find_existing(
+    conn: &PgConnection,
+    formatted_address: &str,
+    fields: &AddressFields,
+) -> Result<Option<i32>, Error> {
+    use schema::houses;
+
+    let mut existing = None;
+    if let Some(ref pid) = fields.place_id {
+        existing = houses::table
+            .select(houses::id)
+            .filter(houses::place_id.eq(pid))
+            .first::<i32>(conn)
+            .optional()?;
+    }
+    if let (None, Some(canonical)) = (existing, &fields.canonical_address) {
+        existing = houses::table
+            .select(houses::id)
+            .filter(houses::canonical_address.eq(canonical))
+            .first::<i32>(conn)
+            .optional()?;
+    }
+    if existing.is_none() {
+        existing = houses::table
+            .select(houses::id)
+            .filter(houses::address.eq(formatted_address))
+            .first::<i32>(conn)
+            .optional()?;
+    }
+
+    Ok(existing)
+}
+
+/// Match `search` anywhere in a column, treating LIKE wildcards in it as
+/// plain characters
+fn contains_pattern(search: &str) -> String {
+    let escaped = search
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+/// Load a house along with all of its deals
+fn house_with_deals(conn: &PgConnection, house_id: i32) -> Result<HouseWithDeals, Error> {
+    use schema::deals;
+    use schema::houses;
+
+    let house = houses::table.find(house_id).first::<House>(conn)?;
+    let d = deals::table
+        .filter(deals::house_id.eq(house.id))
+        .order_by(deals::created.desc())
+        .load::<Deal>(conn)?;
+
+    Ok(HouseWithDeals { house, deals: d })
+}
+
+///
+/// Public API
+///
+
+/// Get houses
+///
+/// With `near` (and optionally `radius_m`) or `bbox`, only houses in that
+/// area are returned, nearest first.
+pub fn get_houses(
+    query: Option<HousesQuery>,
+    user: CurrentUser,
+    conn: Conn,
+) -> Response<Vec<HouseSearchResult>> {
+    use schema::houses::dsl::*;
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let query = query.unwrap_or_default();
+    let area = SearchArea::from_query(query.near, query.radius_m, query.bbox)?;
+
+    let mut q = houses.into_boxed();
+    if let Some(search) = query.address {
+        q = q.filter(address.ilike(contains_pattern(search.trim())));
+    }
+
+    let h = match area {
+        Some(area) => {
+            let b = area.bbox;
+            q = q.filter(lat.between(b.south, b.north));
+            q = if area.crosses_antimeridian() {
+                q.filter(lng.ge(b.west).or(lng.le(b.east)))
+            } else {
+                q.filter(lng.between(b.west, b.east))
+            };
+            let in_box = q
+                .order_by(area.distance_order("houses"))
+                .limit(1000)
+                .load::<House>(&conn)?;
+
+            area.nearest_first(in_box, |h| h.coordinates())
+                .into_iter()
+                .take(50)
+                .map(|(house, distance_m)| HouseSearchResult { house, distance_m })
+                .collect()
+        }
+        None => q
+            .order_by(created.desc())
+            .limit(50)
+            .load::<House>(&conn)?
+            .into_iter()
+            .map(|house| HouseSearchResult {
+                house,
+                distance_m: None,
+            })
+            .collect(),
+    };
+
+    Ok(Payload {
+        data: h,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Get a house and its deals
+pub fn get_house(house_id: i32, user: CurrentUser, conn: Conn) -> Response<HouseWithDeals> {
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    Ok(Payload {
+        data: house_with_deals(&conn, house_id)?,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Update house
+pub fn update_house(
+    house_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: UpdateHouseInput,
+) -> Response<House> {
+    use schema::houses::dsl::*;
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    input.validate()?;
+
+    let house = houses.find(house_id).first::<House>(&conn)?;
+
+    // If the field is set, use the value
+    // If it is not set, ignore.
+    let new_address = match input.address {
+        Some(a) => a.trim().to_owned(),
+        None => house.address.clone(),
+    };
+    let new_google_address = match input.google_address {
+        Some(g) => Some(serde_json::to_value(g)?),
+        None => house.google_address.clone(),
+    };
+    let fields = self::address::fields_from_google(
+        &new_address,
+        new_google_address
+            .clone()
+            .and_then(|v| serde_json::from_value::<GoogleAddress>(v).ok())
+            .as_ref(),
+    );
+
+    let house = diesel::update(&house)
+        .set((
+            address.eq(new_address),
+            google_address.eq(new_google_address),
+            updated.eq(chrono::Utc::now().naive_utc()),
+            &fields,
+        ))
+        .get_result::<House>(&conn)
+        .map_err(|e| match e {
+            DatabaseError(DatabaseErrorKind::UniqueViolation, _info) => {
+                Error::from_conflict("house_exists", "address", "House exists")
+            }
+            _ => Error::from(e),
+        })?;
+
+    Ok(Payload {
+        data: house,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Merge duplicate houses
+///
+/// Every deal on the duplicates is moved to the target house, then the
+/// duplicates are removed. Houses with deals for the same buyer can't be
+/// merged until one of those deals is removed.
+pub fn merge_houses(
+    house_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: MergeHousesInput,
+) -> Response<HouseWithDeals> {
+    use schema::deals;
+    use schema::houses;
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let mut duplicate_ids = input
+        .house_ids
+        .into_iter()
+        .filter(|h| *h != house_id)
+        .collect::<Vec<i32>>();
+    duplicate_ids.sort();
+    duplicate_ids.dedup();
+
+    if duplicate_ids.is_empty() {
+        return Err(Error::from_custom_validation(
+            "nothing_to_merge",
+            "house_ids",
+            "No houses to merge",
+        ));
+    }
+
+    conn.transaction::<_, Error, _>(|| {
+        // Make sure the target and every duplicate exist
+        let _ = houses::table.find(house_id).first::<House>(&conn)?;
+        let found = houses::table
+            .filter(houses::id.eq_any(&duplicate_ids))
+            .count()
+            .get_result::<i64>(&conn)?;
+        if found != duplicate_ids.len() as i64 {
+            return Err(Error::from(diesel::NotFound));
+        }
+
+        // A buyer can only have one deal per house
+        let mut buyer_ids = deals::table
+            .select(deals::buyer_id)
+            .filter(
+                deals::house_id
+                    .eq(house_id)
+                    .or(deals::house_id.eq_any(&duplicate_ids)),
+            )
+            .filter(deals::buyer_id.is_not_null())
+            .load::<Option<i32>>(&conn)?;
+        let deal_count = buyer_ids.len();
+        buyer_ids.sort();
+        buyer_ids.dedup();
+        if buyer_ids.len() != deal_count {
+            return Err(Error::from_conflict(
+                "deal_exists",
+                "house_ids",
+                "Houses have deals for the same buyer",
+            ));
+        }
+
+        diesel::update(deals::table.filter(deals::house_id.eq_any(&duplicate_ids)))
+            .set((
+                deals::house_id.eq(house_id),
+                deals::updated.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .execute(&conn)?;
+
+        diesel::delete(houses::table.filter(houses::id.eq_any(&duplicate_ids))).execute(&conn)?;
+
+        Ok(())
+    })?;
+
+    Ok(Payload {
+        data: house_with_deals(&conn, house_id)?,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Delete house
+///
+/// Houses with active deals are only deleted when `force` is set. Their
+/// deals are kept and unlinked from the house.
+pub fn delete_house(
+    house_id: i32,
+    force: bool,
+    user: CurrentUser,
+    conn: Conn,
+) -> Response<House> {
+    use schema::houses::dsl::*;
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let HouseWithDeals { house, deals } = house_with_deals(&conn, house_id)?;
+
+    if !force && deals.iter().any(|d| d.status.is_active()) {
+        return Err(Error::from_conflict(
+            "house_has_active_deals",
+            "id",
+            "House has active deals",
+        ));
+    }
+
+    diesel::delete(houses.find(house.id)).execute(&conn)?;
+
+    Ok(Payload {
+        data: house,
+        success: true,
+        ..Default::default()
+    })
+}
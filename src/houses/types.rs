@@ -0,0 +1,266 @@
+//
+// houses/types.rs
+//
+use deals::types::Deal;
+use rocket::http::RawStr;
+use rocket::request::FromFormValue;
+use schema::houses;
+use validator::Validate;
+
+#[derive(Serialize, Identifiable, Clone, Queryable, Debug)]
+#[table_name = "houses"]
+pub struct House {
+    pub id: i32,
+    pub address: String,
+    pub created: chrono::NaiveDateTime,
+    pub updated: chrono::NaiveDateTime,
+    pub google_address: Option<serde_json::Value>,
+    pub street_number: Option<String>,
+    pub route: Option<String>,
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub postal_code: Option<String>,
+    pub country: Option<String>,
+    pub place_id: Option<String>,
+    pub canonical_address: Option<String>,
+    pub lat: Option<f64>,
+    pub lng: Option<f64>,
+}
+
+impl House {
+    /// Parse the stored google address, if there is one
+    pub fn parsed_google_address(&self) -> Option<GoogleAddress> {
+        self.google_address
+            .clone()
+            .and_then(|v| serde_json::from_value(v).ok())
+    }
+
+    /// Street and city lines for addressing mail
+    pub fn address_lines(&self) -> Vec<String> {
+        match (&self.street_number, &self.route) {
+            (Some(number), Some(route)) => {
+                let mut region = self.city.clone().unwrap_or_default();
+                if let Some(ref state) = self.state {
+                    if !region.is_empty() {
+                        region.push_str(", ");
+                    }
+                    region.push_str(state);
+                }
+                if let Some(ref postal_code) = self.postal_code {
+                    region.push(' ');
+                    region.push_str(postal_code);
+                }
+                vec![format!("{} {}", number, route), region.trim().to_owned()]
+            }
+            _ => {
+                let mut parts = self.address.splitn(2, ',');
+                let street = parts.next().unwrap_or("").trim().to_owned();
+                let region = parts.next().unwrap_or("").trim().to_owned();
+                vec![street, region]
+            }
+        }
+    }
+
+    pub fn coordinates(&self) -> Option<Coordinates> {
+        match (self.lat, self.lng) {
+            (Some(lat), Some(lng)) => Some(Coordinates { lat, lng }),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "houses"]
+pub struct NewHouse {
+    pub address: String,
+    pub created: chrono::NaiveDateTime,
+    pub updated: chrono::NaiveDateTime,
+    pub google_address: Option<serde_json::Value>,
+    pub street_number: Option<String>,
+    pub route: Option<String>,
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub postal_code: Option<String>,
+    pub country: Option<String>,
+    pub place_id: Option<String>,
+    pub canonical_address: Option<String>,
+    pub lat: Option<f64>,
+    pub lng: Option<f64>,
+}
+
+impl NewHouse {
+    pub fn new(
+        address: String,
+        google_address: Option<serde_json::Value>,
+        fields: AddressFields,
+    ) -> Self {
+        NewHouse {
+            address,
+            created: chrono::Utc::now().naive_utc(),
+            updated: chrono::Utc::now().naive_utc(),
+            google_address,
+            street_number: fields.street_number,
+            route: fields.route,
+            city: fields.city,
+            state: fields.state,
+            postal_code: fields.postal_code,
+            country: fields.country,
+            place_id: fields.place_id,
+            canonical_address: fields.canonical_address,
+            lat: fields.lat,
+            lng: fields.lng,
+        }
+    }
+}
+
+/// Typed address fields derived from the google address
+#[derive(Default, Clone, Debug, AsChangeset)]
+#[table_name = "houses"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct AddressFields {
+    pub street_number: Option<String>,
+    pub route: Option<String>,
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub postal_code: Option<String>,
+    pub country: Option<String>,
+    pub place_id: Option<String>,
+    pub canonical_address: Option<String>,
+    pub lat: Option<f64>,
+    pub lng: Option<f64>,
+}
+
+#[derive(Clone)]
+pub struct HouseInput {
+    pub address: String,
+    pub google_address: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize, Serialize, Validate, Debug)]
+pub struct GoogleAddress {
+    pub address_components: Vec<AddressComponents>,
+    #[validate(length(min = "0", max = "500", message = "Too long"))]
+    pub formatted_address: String,
+    pub geometry: Geometry,
+    #[validate(length(min = "0", max = "500", message = "Too long"))]
+    pub place_id: String,
+    pub types: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Validate, Debug)]
+pub struct Geometry {
+    pub location: Location,
+    #[validate(length(min = "0", max = "500", message = "Too long"))]
+    pub location_type: String,
+    pub viewport: Viewport,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Viewport {
+    pub northeast: Location,
+    pub southwest: Location,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Location {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+#[derive(Deserialize, Serialize, Validate, Debug)]
+pub struct AddressComponents {
+    #[validate(length(min = "0", max = "500", message = "Too long"))]
+    pub long_name: String,
+    #[validate(length(min = "0", max = "500", message = "Too long"))]
+    pub short_name: String,
+    #[validate(length(min = "0", max = "500", message = "Too long"))]
+    pub types: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct HouseWithDeals {
+    pub house: House,
+    pub deals: Vec<Deal>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct UpdateHouseInput {
+    #[validate(length(min = "1", max = "255", message = "Cannot be blank"))]
+    pub address: Option<String>,
+    pub google_address: Option<GoogleAddress>,
+}
+
+#[derive(Deserialize)]
+pub struct MergeHousesInput {
+    /// Duplicate houses to fold into the target house
+    pub house_ids: Vec<i32>,
+}
+
+#[derive(FromForm, Deserialize, Debug, Default)]
+pub struct HousesQuery {
+    pub address: Option<String>,
+    /// Center point as `lat,lng`
+    pub near: Option<Coordinates>,
+    pub radius_m: Option<f64>,
+    /// Bounding box as `south,west,north,east`
+    pub bbox: Option<BoundingBox>,
+}
+
+#[derive(Serialize)]
+pub struct HouseSearchResult {
+    #[serde(flatten)]
+    pub house: House,
+    /// Meters from the `near` point, when one was given
+    pub distance_m: Option<f64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+pub struct Coordinates {
+    pub lat: f64,
+    pub lng: f64,
+}
+
+impl<'v> FromFormValue<'v> for Coordinates {
+    type Error = &'v RawStr;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, &'v RawStr> {
+        match parse_floats(form_value).as_ref().map(|v| v.as_slice()) {
+            Some(&[lat, lng]) => Ok(Coordinates { lat, lng }),
+            _ => Err(form_value),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+pub struct BoundingBox {
+    pub south: f64,
+    pub west: f64,
+    pub north: f64,
+    pub east: f64,
+}
+
+impl<'v> FromFormValue<'v> for BoundingBox {
+    type Error = &'v RawStr;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, &'v RawStr> {
+        match parse_floats(form_value).as_ref().map(|v| v.as_slice()) {
+            Some(&[south, west, north, east]) => Ok(BoundingBox {
+                south,
+                west,
+                north,
+                east,
+            }),
+            _ => Err(form_value),
+        }
+    }
+}
+
+/// Parse a comma separated list of floats
+fn parse_floats(value: &RawStr) -> Option<Vec<f64>> {
+    value
+        .url_decode()
+        .ok()?
+        .split(',')
+        .map(|v| v.trim().parse::<f64>().ok())
+        .collect()
+}
@@ -0,0 +1,226 @@
+//
+// jobs/mod.rs
+//
+pub mod types;
+pub mod worker;
+
+use self::types::*;
+use accounts::types::{CurrentUser, CurrentUser::*};
+use db::Conn;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::sql_types::Timestamp;
+use email;
+use email::Mailer;
+use mailers;
+use result::{Error, Payload, Response};
+use webhooks;
+
+/// Times a job is tried before it's dead-lettered
+const DEFAULT_MAX_ATTEMPTS: i32 = 8;
+
+/// Wait before the first retry, doubled for each attempt after
+const RETRY_BASE_SECONDS: i64 = 30;
+
+/// Longest wait between retries
+const RETRY_MAX_SECONDS: i64 = 6 * 60 * 60;
+
+/// Running jobs not finished within this long are assumed to belong to a
+/// worker that died, and are queued again
+const STALE_AFTER_MINUTES: i64 = 15;
+
+///
+/// Helpers
+///
+
+/// Wait before retrying a job that has failed `attempts` times
+pub fn backoff(attempts: i32) -> chrono::Duration {
+    let exp = (attempts - 1).max(0).min(16) as u32;
+    chrono::Duration::seconds((RETRY_BASE_SECONDS * 2i64.pow(exp)).min(RETRY_MAX_SECONDS))
+}
+
+/// Claim the next due job, skipping any another worker holds, and mark it
+/// running
+pub fn claim(conn: &PgConnection) -> Result<Option<Job>, Error> {
+    let now = chrono::Utc::now().naive_utc();
+
+    let mut claimed = diesel::sql_query(
+        "UPDATE jobs \
+         SET status = 'running', attempts = attempts + 1, locked_at = $1, updated = $1 \
+         WHERE id = ( \
+           SELECT id FROM jobs \
+           WHERE status = 'queued' AND run_at <= $1 \
+           ORDER BY run_at, id \
+           FOR UPDATE SKIP LOCKED \
+           LIMIT 1 \
+         ) \
+         RETURNING *",
+    )
+    .bind::<Timestamp, _>(now)
+    .load::<Job>(conn)?;
+
+    Ok(claimed.pop())
+}
+
+/// Run a claimed job's payload
+pub fn perform(conn: &PgConnection, mailer: &dyn Mailer, job: &Job) -> Result<(), Error> {
+    let payload = serde_json::from_value::<JobPayload>(job.payload.clone())?;
+
+    match payload {
+        JobPayload::RenderMailerBatch { batch_id } => mailers::render_batch(conn, batch_id),
+        JobPayload::SendEmail { email_id } => email::deliver(conn, mailer, email_id),
+        JobPayload::DeliverWebhook { delivery_id } => webhooks::deliver(conn, delivery_id),
+    }
+}
+
+/// Record a job's outcome. Failed jobs are retried with backoff until they
+/// run out of attempts, then left dead with the last error.
+pub fn finish(conn: &PgConnection, job: &Job, result: Result<(), String>) -> Result<(), Error> {
+    use schema::jobs::dsl::*;
+
+    let now = chrono::Utc::now().naive_utc();
+    let target = jobs.find(job.id);
+
+    match result {
+        Ok(()) => diesel::update(target)
+            .set((
+                status.eq(JobStatus::Done),
+                locked_at.eq(None::<chrono::NaiveDateTime>),
+                updated.eq(now),
+            ))
+            .execute(conn)?,
+        Err(e) => {
+            let (next, due) = if job.attempts >= job.max_attempts {
+                (JobStatus::Dead, job.run_at)
+            } else {
+                (JobStatus::Queued, now + backoff(job.attempts))
+            };
+            diesel::update(target)
+                .set((
+                    status.eq(next),
+                    run_at.eq(due),
+                    locked_at.eq(None::<chrono::NaiveDateTime>),
+                    last_error.eq(Some(e)),
+                    updated.eq(now),
+                ))
+                .execute(conn)?
+        }
+    };
+    Ok(())
+}
+
+/// Queue jobs whose worker stopped while running them. Claiming a job
+/// counted the attempt, so one that hangs or crashes its worker every time
+/// is dead-lettered once it runs out of attempts, like any other failure.
+pub fn requeue_stale(conn: &PgConnection) -> Result<usize, Error> {
+    let now = chrono::Utc::now().naive_utc();
+    let cutoff = now - chrono::Duration::minutes(STALE_AFTER_MINUTES);
+
+    diesel::sql_query(
+        "UPDATE jobs \
+         SET status = CASE WHEN attempts >= max_attempts THEN 'dead' ELSE 'queued' END, \
+           locked_at = NULL, \
+           last_error = 'Worker stopped while running the job', \
+           updated = $1 \
+         WHERE status = 'running' AND locked_at < $2",
+    )
+    .bind::<Timestamp, _>(now)
+    .bind::<Timestamp, _>(cutoff)
+    .execute(conn)
+    .map_err(|e| Error::from(e))
+}
+
+///
+/// Public API
+///
+
+/// Queue a job to run as soon as a worker is free. Uses the caller's
+/// connection, so a job queued inside a transaction only runs if it commits.
+pub fn enqueue(conn: &PgConnection, payload: &JobPayload) -> Result<Job, Error> {
+    enqueue_at(conn, payload, chrono::Utc::now().naive_utc())
+}
+
+/// Queue a job to run no earlier than `at`
+pub fn enqueue_at(
+    conn: &PgConnection,
+    payload: &JobPayload,
+    at: chrono::NaiveDateTime,
+) -> Result<Job, Error> {
+    use schema::jobs;
+
+    let now = chrono::Utc::now().naive_utc();
+    diesel::insert_into(jobs::table)
+        .values(&NewJob {
+            kind: payload.kind().to_owned(),
+            payload: serde_json::to_value(payload)?,
+            status: JobStatus::Queued,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            run_at: at,
+            created: now,
+            updated: now,
+        })
+        .get_result::<Job>(conn)
+        .map_err(|e| Error::from(e))
+}
+
+/// Get jobs, most recently updated first
+pub fn get_jobs(query: JobsQuery, user: CurrentUser, conn: Conn) -> Response<Vec<Job>> {
+    use schema::jobs::dsl::*;
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let mut q = jobs.into_boxed();
+    if let Some(s) = query.status {
+        q = q.filter(status.eq(s));
+    }
+    if let Some(k) = query.kind {
+        q = q.filter(kind.eq(k));
+    }
+    let j = q.order_by(updated.desc()).limit(100).load::<Job>(&conn)?;
+
+    Ok(Payload {
+        data: j,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Give a dead job a fresh set of attempts
+pub fn retry_job(job_id: i32, user: CurrentUser, conn: Conn) -> Response<Job> {
+    use schema::jobs::dsl::*;
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let job = jobs.find(job_id).first::<Job>(&conn)?;
+    if job.status != JobStatus::Dead {
+        return Err(Error::from_conflict(
+            "job_not_dead",
+            "status",
+            "Only dead jobs can be retried",
+        ));
+    }
+
+    let now = chrono::Utc::now().naive_utc();
+    let job = diesel::update(jobs.find(job_id))
+        .set((
+            status.eq(JobStatus::Queued),
+            attempts.eq(0),
+            run_at.eq(now),
+            updated.eq(now),
+        ))
+        .get_result::<Job>(&conn)?;
+
+    Ok(Payload {
+        data: job,
+        success: true,
+        ..Default::default()
+    })
+}
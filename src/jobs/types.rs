@@ -0,0 +1,123 @@
+//
+// jobs/types.rs
+//
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Varchar;
+use rocket::http::RawStr;
+use rocket::request::FromFormValue;
+use schema::jobs;
+use std::io::Write;
+
+/// Work to do in the background. Stored as JSON in the job's payload, tagged
+/// with the variant name.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type")]
+pub enum JobPayload {
+    /// Render a mailer batch's letters into its PDF
+    RenderMailerBatch { batch_id: i32 },
+    /// Deliver a recorded email
+    SendEmail { email_id: i32 },
+    /// Send a webhook delivery
+    DeliverWebhook { delivery_id: i32 },
+}
+
+impl JobPayload {
+    /// Name stored with the job so the queue can be read without parsing
+    /// payloads
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            JobPayload::RenderMailerBatch { .. } => "render_mailer_batch",
+            JobPayload::SendEmail { .. } => "send_email",
+            JobPayload::DeliverWebhook { .. } => "deliver_webhook",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, AsExpression, FromSqlRow)]
+#[sql_type = "Varchar"]
+pub enum JobStatus {
+    /// Waiting for `run_at`
+    Queued,
+    /// Claimed by a worker
+    Running,
+    Done,
+    /// Out of attempts
+    Dead,
+}
+
+impl ToSql<Varchar, Pg> for JobStatus {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        match *self {
+            JobStatus::Queued => out.write_all(b"queued")?,
+            JobStatus::Running => out.write_all(b"running")?,
+            JobStatus::Done => out.write_all(b"done")?,
+            JobStatus::Dead => out.write_all(b"dead")?,
+        }
+
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Pg> for JobStatus {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match not_none!(bytes) {
+            b"queued" => Ok(JobStatus::Queued),
+            b"running" => Ok(JobStatus::Running),
+            b"done" => Ok(JobStatus::Done),
+            b"dead" => Ok(JobStatus::Dead),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
+
+impl<'v> FromFormValue<'v> for JobStatus {
+    type Error = &'v RawStr;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, &'v RawStr> {
+        match form_value.as_str() {
+            "queued" => Ok(JobStatus::Queued),
+            "running" => Ok(JobStatus::Running),
+            "done" => Ok(JobStatus::Done),
+            "dead" => Ok(JobStatus::Dead),
+            _ => Err(form_value),
+        }
+    }
+}
+
+#[derive(Serialize, Identifiable, Clone, Queryable, QueryableByName, Debug)]
+#[table_name = "jobs"]
+pub struct Job {
+    pub id: i32,
+    pub kind: String,
+    pub payload: serde_json::Value,
+    pub status: JobStatus,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    /// Earliest time a worker may pick the job up
+    pub run_at: chrono::NaiveDateTime,
+    /// When a worker claimed the job
+    pub locked_at: Option<chrono::NaiveDateTime>,
+    pub last_error: Option<String>,
+    pub created: chrono::NaiveDateTime,
+    pub updated: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "jobs"]
+pub struct NewJob {
+    pub kind: String,
+    pub payload: serde_json::Value,
+    pub status: JobStatus,
+    pub max_attempts: i32,
+    pub run_at: chrono::NaiveDateTime,
+    pub created: chrono::NaiveDateTime,
+    pub updated: chrono::NaiveDateTime,
+}
+
+#[derive(FromForm, Default)]
+pub struct JobsQuery {
+    pub status: Option<JobStatus>,
+    pub kind: Option<String>,
+}
@@ -0,0 +1,144 @@
+//
+// jobs/worker.rs
+//
+// Workers run as threads inside the web process, `JOB_WORKERS` of them
+// (default 2). Run `main worker` to start workers without the web server,
+// and set `JOB_WORKERS=0` on the web process to leave jobs to it.
+//
+use db::{create_sized_pool, ConnectionPool};
+use diesel::pg::PgConnection;
+use diesel::Connection;
+use email::{self, Mailer};
+use jobs;
+use result::Error;
+use std::any::Any;
+use std::env;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Workers to start if `JOB_WORKERS` isn't set
+const DEFAULT_WORKERS: usize = 2;
+
+/// How long an idle worker waits before looking for jobs again
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Why a job didn't finish
+enum Failure {
+    Error(Error),
+    Panic(String),
+}
+
+impl From<diesel::result::Error> for Failure {
+    fn from(error: diesel::result::Error) -> Self {
+        Failure::Error(Error::from(error))
+    }
+}
+
+///
+/// Helpers
+///
+
+fn workers_from_env() -> usize {
+    env::var("JOB_WORKERS")
+        .ok()
+        .and_then(|n| n.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_WORKERS)
+}
+
+fn panic_message(cause: Box<dyn Any + Send>) -> String {
+    match cause.downcast::<String>() {
+        Ok(s) => *s,
+        Err(cause) => match cause.downcast::<&str>() {
+            Ok(s) => (*s).to_owned(),
+            Err(_) => "Job panicked".to_owned(),
+        },
+    }
+}
+
+/// Claim and run a single job. Returns whether there was one.
+fn run_next(conn: &PgConnection, mailer: &dyn Mailer) -> Result<bool, Error> {
+    let job = match jobs::claim(conn)? {
+        Some(job) => job,
+        None => return Ok(false),
+    };
+
+    // A failed job shouldn't leave half its work behind, and a panicking one
+    // shouldn't take the worker down with it. The panic is caught inside the
+    // transaction, so it's rolled back like any other failure rather than
+    // left open on the connection.
+    let outcome = conn.transaction::<_, Failure, _>(|| {
+        match panic::catch_unwind(AssertUnwindSafe(|| jobs::perform(conn, mailer, &job))) {
+            Ok(result) => result.map_err(Failure::Error),
+            Err(cause) => Err(Failure::Panic(panic_message(cause))),
+        }
+    });
+    let result = match outcome {
+        Ok(()) => Ok(()),
+        Err(Failure::Error(e)) => Err(format!("{:?}", e)),
+        Err(Failure::Panic(message)) => Err(message),
+    };
+    if let Err(ref e) = result {
+        println!("Job {} ({}) failed: {}", job.id, job.kind, e);
+    }
+
+    jobs::finish(conn, &job, result)?;
+    Ok(true)
+}
+
+fn work(pool: ConnectionPool, mailer: Arc<Box<dyn Mailer>>) {
+    loop {
+        let ran = pool
+            .get()
+            .map_err(|_| Error::ServiceUnavailable)
+            .and_then(|conn| {
+                jobs::requeue_stale(&conn)?;
+                run_next(&conn, &**mailer)
+            });
+
+        match ran {
+            Ok(true) => continue,
+            Ok(false) => thread::sleep(POLL_INTERVAL),
+            Err(e) => {
+                println!("Job worker error: {:?}", e);
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+    }
+}
+
+///
+/// Public API
+///
+
+/// Start the job workers in the background. Fails if the mailer is
+/// misconfigured.
+pub fn spawn() -> Result<Vec<thread::JoinHandle<()>>, String> {
+    let count = workers_from_env();
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mailer = Arc::new(email::from_env()?);
+    println!("Starting {} job workers", count);
+    let pool = create_sized_pool(count as u32);
+    Ok((0..count)
+        .map(|n| {
+            let pool = pool.clone();
+            let mailer = mailer.clone();
+            thread::Builder::new()
+                .name(format!("job-worker-{}", n))
+                .spawn(move || work(pool, mailer))
+                .expect("Failed to start job worker")
+        })
+        .collect())
+}
+
+/// Run the job workers until the process is stopped
+pub fn run() -> Result<(), String> {
+    for handle in spawn()? {
+        let _ = handle.join();
+    }
+    Ok(())
+}
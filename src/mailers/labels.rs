@@ -0,0 +1,292 @@
+//
+// mailers/labels.rs
+//
+// Address labels and lists for mail houses
+//
+use accounts::types::{CurrentUser, CurrentUser::*};
+use csv::Csv;
+use db::Conn;
+use deals::types::Deal;
+use diesel::prelude::*;
+use houses::types::House;
+use mailers::types::*;
+use mailers::DEFAULT_RECIPIENT;
+use pdf::{self, Document, Font, Page, PAGE_HEIGHT};
+use result::Error;
+use std::collections::HashMap;
+
+/// Most labels a single export will include
+const MAX_LABELS: i64 = 5000;
+
+/// Largest font size used on a label, shrunk to fit long lines
+const MAX_FONT_SIZE: f64 = 10.0;
+
+/// Placement of the labels on a sheet, in points
+struct Sheet {
+    columns: usize,
+    rows: usize,
+    top: f64,
+    left: f64,
+    label_width: f64,
+    label_height: f64,
+    column_pitch: f64,
+    row_pitch: f64,
+    padding: f64,
+}
+
+impl Sheet {
+    fn for_layout(layout: LabelLayout) -> Self {
+        match layout {
+            LabelLayout::Avery5160 => Sheet {
+                columns: 3,
+                rows: 10,
+                top: 36.0,
+                left: 13.5,
+                label_width: 189.0,
+                label_height: 72.0,
+                column_pitch: 198.0,
+                row_pitch: 72.0,
+                padding: 9.0,
+            },
+            LabelLayout::Avery5163 => Sheet {
+                columns: 2,
+                rows: 5,
+                top: 36.0,
+                left: 11.25,
+                label_width: 288.0,
+                label_height: 144.0,
+                column_pitch: 301.5,
+                row_pitch: 144.0,
+                padding: 18.0,
+            },
+        }
+    }
+
+    fn per_page(&self) -> usize {
+        self.columns * self.rows
+    }
+}
+
+/// One label's worth of address
+struct Label {
+    deal_id: i32,
+    recipient: String,
+    street: String,
+    city: String,
+    state: String,
+    postal_code: String,
+}
+
+impl Label {
+    fn new(deal: &Deal, house: &House, recipient: String) -> Self {
+        match (&house.street_number, &house.route) {
+            (Some(number), Some(route)) => Label {
+                deal_id: deal.id,
+                recipient,
+                street: format!("{} {}", number, route),
+                city: house.city.clone().unwrap_or_default(),
+                state: house.state.clone().unwrap_or_default(),
+                postal_code: house.postal_code.clone().unwrap_or_default(),
+            },
+            // Without a structured address, the region can't be split up,
+            // so it all goes in the city
+            _ => {
+                let lines = house.address_lines();
+                Label {
+                    deal_id: deal.id,
+                    recipient,
+                    street: lines.get(0).cloned().unwrap_or_default(),
+                    city: lines.get(1).cloned().unwrap_or_default(),
+                    state: String::new(),
+                    postal_code: String::new(),
+                }
+            }
+        }
+    }
+
+    fn last_line(&self) -> String {
+        let mut line = self.city.clone();
+        if !self.state.is_empty() {
+            if !line.is_empty() {
+                line.push_str(", ");
+            }
+            line.push_str(&self.state);
+        }
+        if !self.postal_code.is_empty() {
+            line.push_str("  ");
+            line.push_str(&self.postal_code);
+        }
+        line.trim().to_owned()
+    }
+}
+
+///
+/// Helpers
+///
+
+/// Load labels for the deals matching the query, sorted by ZIP code so
+/// mail houses can presort them.
+///
+/// Claimed deals are addressed to their seller, the rest to the fallback
+/// recipient.
+fn load_labels(conn: &PgConnection, query: &LabelExportQuery) -> Result<Vec<Label>, Error> {
+    use schema::{deals, houses, users};
+
+    let mut q = deals::table.inner_join(houses::table).into_boxed();
+    if let Some(ref ids) = query.deal_ids {
+        q = q.filter(deals::id.eq_any(ids.0.clone()));
+    }
+    if let Some(b) = query.buyer_id {
+        q = q.filter(deals::buyer_id.eq(b));
+    }
+    if let Some(s) = query.status {
+        q = q.filter(deals::status.eq(s));
+    }
+    let rows = q
+        .order_by((
+            houses::postal_code,
+            houses::route,
+            houses::street_number,
+            deals::id,
+        ))
+        .limit(MAX_LABELS)
+        .load::<(Deal, House)>(conn)?;
+
+    let seller_ids = rows
+        .iter()
+        .filter_map(|(deal, _)| deal.seller_id)
+        .collect::<Vec<i32>>();
+    let sellers = users::table
+        .select((users::id, users::name))
+        .filter(users::id.eq_any(&seller_ids))
+        .load::<(i32, String)>(conn)?
+        .into_iter()
+        .collect::<HashMap<i32, String>>();
+
+    let fallback = query
+        .recipient
+        .as_ref()
+        .map(|r| r.trim().to_owned())
+        .filter(|r| !r.is_empty())
+        .unwrap_or_else(|| DEFAULT_RECIPIENT.to_owned());
+
+    Ok(rows
+        .iter()
+        .map(|(deal, house)| {
+            let recipient = deal
+                .seller_id
+                .and_then(|s| sellers.get(&s))
+                .cloned()
+                .unwrap_or_else(|| fallback.clone());
+            Label::new(deal, house, recipient)
+        })
+        .collect())
+}
+
+/// Draw a line, shrinking the font until it fits the label
+fn fit_text(page: &mut Page, x: f64, y: f64, width: f64, font: Font, text: &str) {
+    let natural = pdf::text_width(text, MAX_FONT_SIZE);
+    let size = if natural > width {
+        MAX_FONT_SIZE * width / natural
+    } else {
+        MAX_FONT_SIZE
+    };
+    page.text(x, y, size, font, text);
+}
+
+/// Label sheets, filled left to right and top to bottom
+fn label_sheets(labels: &[Label], layout: LabelLayout) -> Document {
+    let sheet = Sheet::for_layout(layout);
+    let mut document = Document::new();
+    let leading = MAX_FONT_SIZE * 1.3;
+    let width = sheet.label_width - sheet.padding * 2.0;
+
+    for chunk in labels.chunks(sheet.per_page()) {
+        let mut page = Page::new();
+        for (i, label) in chunk.iter().enumerate() {
+            let (column, row) = (i % sheet.columns, i / sheet.columns);
+            let x = sheet.left + column as f64 * sheet.column_pitch + sheet.padding;
+
+            // Center the three lines vertically on the label
+            let label_top = PAGE_HEIGHT - sheet.top - row as f64 * sheet.row_pitch;
+            let mut y = label_top - (sheet.label_height - leading * 3.0) / 2.0 - MAX_FONT_SIZE;
+
+            fit_text(&mut page, x, y, width, Font::Regular, &label.recipient);
+            y -= leading;
+            fit_text(&mut page, x, y, width, Font::Regular, &label.street);
+            y -= leading;
+            fit_text(&mut page, x, y, width, Font::Regular, &label.last_line());
+        }
+        document.add_page(page);
+    }
+
+    document
+}
+
+/// Keep a spreadsheet from reading a field as a formula
+fn plain_text(field: &str) -> String {
+    if field.starts_with(|c: char| c == '=' || c == '+' || c == '-' || c == '@') {
+        format!("'{}", field)
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Address list with the columns in the order USPS and most mail houses
+/// expect them
+fn address_list(labels: &[Label]) -> String {
+    let mut csv = Csv::new();
+    csv.row(&[
+        "name",
+        "address_1",
+        "address_2",
+        "city",
+        "state",
+        "zip",
+        "deal_id",
+    ]);
+    for label in labels {
+        csv.row(&[
+            plain_text(&label.recipient),
+            plain_text(&label.street),
+            String::new(),
+            plain_text(&label.city),
+            plain_text(&label.state),
+            plain_text(&label.postal_code),
+            label.deal_id.to_string(),
+        ]);
+    }
+    csv.into_string()
+}
+
+///
+/// Public API
+///
+
+/// Export address labels for a filtered set of deals, as a label sheet PDF
+/// or a CSV address list
+pub fn export_labels(
+    query: LabelExportQuery,
+    user: CurrentUser,
+    conn: Conn,
+) -> Result<Vec<u8>, Error> {
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let labels = load_labels(&conn, &query)?;
+    if labels.is_empty() {
+        return Err(Error::from_custom_validation(
+            "no_deals",
+            "deal_ids",
+            "No deals match the selection",
+        ));
+    }
+
+    Ok(match query.format.unwrap_or_default() {
+        ExportFormat::Pdf => label_sheets(&labels, query.layout.unwrap_or_default()).to_bytes(),
+        ExportFormat::Csv => address_list(&labels).into_bytes(),
+    })
+}
@@ -0,0 +1,200 @@
+//
+// mailers/letter.rs
+//
+use mailers::scans;
+use mailers::types::{LetterData, LetterTemplate};
+use mailers::DEFAULT_RECIPIENT;
+use pdf::{self, Document, Font, Page, PAGE_HEIGHT, PAGE_WIDTH};
+use qrcode::{Color, QrCode};
+use std::collections::HashMap;
+use std::mem;
+use template;
+
+const MARGIN: f64 = 72.0;
+/// Side of the printed QR code, in points
+const QR_SIZE: f64 = 72.0;
+/// Room the response code takes at the bottom of the last page, in points
+const RESPONSE_HEIGHT: f64 = QR_SIZE + 24.0;
+
+/// Placeholders a mailer template can use
+pub const VARIABLES: &[&str] = &[
+    "buyer.name",
+    "profile.title",
+    "profile.intro",
+    "profile.body",
+    "house.address",
+    "house.street",
+    "house.city",
+    "house.state",
+    "house.postal_code",
+    "deal.access_code",
+    "deal.response_url",
+    "date",
+];
+
+/// Copy used when no template has been made the default
+pub const DEFAULT_TEMPLATE: &str = "# {{profile.title}}
+
+{{profile.intro}}
+
+{{profile.body}}
+
+If you would consider selling, we would love to hear from you. Scan the \
+code below with your phone or visit {{deal.response_url}}
+
+Sincerely,
+{{buyer.name}}";
+
+/// Values for each placeholder, for one letter
+pub fn variables(letter: &LetterData) -> HashMap<&'static str, String> {
+    let house = &letter.house;
+    let mut values = HashMap::new();
+    values.insert("buyer.name", letter.buyer.name.clone());
+    values.insert("profile.title", letter.profile.title.clone());
+    values.insert("profile.intro", letter.profile.intro.clone());
+    values.insert("profile.body", letter.profile.body.clone());
+    values.insert("house.address", house.address.clone());
+    values.insert(
+        "house.street",
+        house.address_lines().into_iter().next().unwrap_or_default(),
+    );
+    values.insert("house.city", house.city.clone().unwrap_or_default());
+    values.insert("house.state", house.state.clone().unwrap_or_default());
+    values.insert(
+        "house.postal_code",
+        house.postal_code.clone().unwrap_or_default(),
+    );
+    values.insert("deal.access_code", letter.deal.access_code.clone());
+    values.insert(
+        "deal.response_url",
+        scans::response_url(&letter.deal.access_code),
+    );
+    values.insert("date", chrono::Utc::now().format("%B %-d, %Y").to_string());
+    values
+}
+
+/// Render one letter per deal, along with the first and last page each
+/// letter is printed on, counting from 1
+pub fn render_all(
+    letters: &[LetterData],
+    letter_template: &LetterTemplate,
+) -> (Document, Vec<(usize, usize)>) {
+    let mut document = Document::new();
+    let mut page_ranges = Vec::new();
+    let mut next_page = 1;
+    for letter in letters {
+        let pages = render(letter, letter_template);
+        page_ranges.push((next_page, next_page + pages.len() - 1));
+        next_page += pages.len();
+        for page in pages {
+            document.add_page(page);
+        }
+    }
+    (document, page_ranges)
+}
+
+/// Lay out a single letter.
+///
+/// The sender and recipient blocks are fixed, with the recipient where a
+/// standard #10 window envelope shows it. The rest is the rendered
+/// template: lines starting with `# ` are headings and blank lines separate
+/// paragraphs. Copy too long for the first page carries on to more pages,
+/// and the response QR code and short URL are always printed at the bottom
+/// of the last one.
+pub fn render(letter: &LetterData, letter_template: &LetterTemplate) -> Vec<Page> {
+    let mut pages = Vec::new();
+    let mut page = Page::new();
+    let width = PAGE_WIDTH - MARGIN * 2.0;
+    let values = variables(letter);
+
+    // Sender and date
+    let mut y = PAGE_HEIGHT - MARGIN;
+    page.text(MARGIN, y, 12.0, Font::Bold, &letter.buyer.name);
+    y -= 16.0;
+    page.text(MARGIN, y, 10.0, Font::Regular, &values["date"]);
+
+    // Recipient
+    y = PAGE_HEIGHT - 2.0 * MARGIN - 24.0;
+    page.text(MARGIN, y, 11.0, Font::Regular, DEFAULT_RECIPIENT);
+    for line in letter.house.address_lines() {
+        if line.is_empty() {
+            continue;
+        }
+        y -= 14.0;
+        page.text(MARGIN, y, 11.0, Font::Regular, &line);
+    }
+
+    // Template copy
+    y -= 56.0;
+    let body = template::render(&letter_template.body, &values);
+    for line in body.lines() {
+        if line.trim().is_empty() {
+            y -= 8.0;
+            continue;
+        }
+        let (size, font, text) = if line.starts_with("# ") {
+            (18.0, Font::Bold, &line[2..])
+        } else {
+            (11.0, Font::Regular, line)
+        };
+        for wrapped in pdf::wrap(text, size, width) {
+            if y < MARGIN {
+                pages.push(mem::replace(&mut page, Page::new()));
+                y = PAGE_HEIGHT - MARGIN;
+            }
+            page.text(MARGIN, y, size, font, &wrapped);
+            y -= size * 1.4;
+        }
+    }
+    if y < MARGIN + RESPONSE_HEIGHT {
+        pages.push(mem::replace(&mut page, Page::new()));
+    }
+
+    // Response code
+    let url = &values["deal.response_url"];
+    qr_code(&mut page, MARGIN, MARGIN, QR_SIZE, url);
+    let x = MARGIN + QR_SIZE + 14.0;
+    page.text(
+        x,
+        MARGIN + 44.0,
+        11.0,
+        Font::Bold,
+        "Scan to respond, or visit",
+    );
+    page.text(x, MARGIN + 28.0, 11.0, Font::Regular, url);
+    page.text(
+        x,
+        MARGIN + 12.0,
+        10.0,
+        Font::Regular,
+        &format!("Access code: {}", letter.deal.access_code),
+    );
+
+    pages.push(page);
+    pages
+}
+
+/// Draw `data` as a QR code with its bottom left corner at (x, y)
+fn qr_code(page: &mut Page, x: f64, y: f64, size: f64, data: &str) {
+    let code = match QrCode::new(data.as_bytes()) {
+        Ok(code) => code,
+        Err(e) => {
+            println!("Could not encode QR code for {}: {:?}", data, e);
+            return;
+        }
+    };
+    let modules = code.width();
+    let module_size = size / modules as f64;
+
+    for (i, color) in code.to_colors().into_iter().enumerate() {
+        if color == Color::Dark {
+            let (column, row) = (i % modules, i / modules);
+            page.rect(
+                x + column as f64 * module_size,
+                y + size - (row + 1) as f64 * module_size,
+                module_size,
+                module_size,
+            );
+        }
+    }
+}
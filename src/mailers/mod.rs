@@ -0,0 +1,526 @@
+//
+// mailers/mod.rs
+//
+pub mod labels;
+pub mod letter;
+pub mod scans;
+pub mod templates;
+pub mod types;
+
+use self::types::*;
+use accounts::types::{CurrentUser, CurrentUser::*, Profile, User};
+use csv::Csv;
+use db::Conn;
+use deals;
+use deals::types::{Deal, DealStatus};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use houses::types::House;
+use jobs;
+use jobs::types::JobPayload;
+use result::{Error, Payload, Response};
+use std::collections::HashMap;
+
+/// Addressee on mail for a house whose owner we don't know
+pub const DEFAULT_RECIPIENT: &str = "Current Resident";
+
+/// Most deals a single batch will include
+const MAX_BATCH_SIZE: i64 = 500;
+
+///
+/// Helpers
+///
+
+/// Load the letter data for a set of deals, in the order given.
+///
+/// Fails if any deal is missing its house, buyer or the buyer's profile,
+/// since its letter couldn't be printed.
+pub fn load_letters(conn: &PgConnection, deal_ids: &[i32]) -> Result<Vec<LetterData>, Error> {
+    let (letters, incomplete) = gather_letters(conn, deal_ids)?;
+    match incomplete.into_iter().next() {
+        Some((deal_id, reason)) => Err(self::incomplete(deal_id, &reason)),
+        None => Ok(letters),
+    }
+}
+
+/// Load the letter data for the deals that have everything a letter needs,
+/// in the order given, and why each of the others couldn't be printed
+fn gather_letters(
+    conn: &PgConnection,
+    deal_ids: &[i32],
+) -> Result<(Vec<LetterData>, Vec<(i32, String)>), Error> {
+    use schema::{deals, houses, profiles, users};
+
+    let rows = deals::table
+        .inner_join(houses::table)
+        .filter(deals::id.eq_any(deal_ids))
+        .load::<(Deal, House)>(conn)?;
+
+    let buyer_ids = rows
+        .iter()
+        .filter_map(|(deal, _)| deal.buyer_id)
+        .collect::<Vec<i32>>();
+    let buyers = users::table
+        .filter(users::id.eq_any(&buyer_ids))
+        .load::<User>(conn)?
+        .into_iter()
+        .map(|u| (u.id, u))
+        .collect::<HashMap<i32, User>>();
+    let profiles = profiles::table
+        .filter(profiles::uid.eq_any(&buyer_ids))
+        .load::<Profile>(conn)?
+        .into_iter()
+        .map(|p| (p.uid, p))
+        .collect::<HashMap<i32, Profile>>();
+
+    let mut by_deal = rows
+        .into_iter()
+        .map(|(deal, house)| (deal.id, (deal, house)))
+        .collect::<HashMap<i32, (Deal, House)>>();
+
+    let mut letters = Vec::new();
+    let mut incomplete = Vec::new();
+    for deal_id in deal_ids {
+        let (deal, house) = match by_deal.remove(deal_id) {
+            Some(row) => row,
+            None => {
+                incomplete.push((*deal_id, "has no house".to_owned()));
+                continue;
+            }
+        };
+        let buyer = match deal.buyer_id.and_then(|b| buyers.get(&b)) {
+            Some(buyer) => buyer.clone(),
+            None => {
+                incomplete.push((*deal_id, "has no buyer".to_owned()));
+                continue;
+            }
+        };
+        let profile = match profiles.get(&buyer.id) {
+            Some(profile) => profile.clone(),
+            None => {
+                incomplete.push((*deal_id, "buyer has no profile".to_owned()));
+                continue;
+            }
+        };
+
+        letters.push(LetterData {
+            deal,
+            house,
+            buyer,
+            profile,
+        });
+    }
+    Ok((letters, incomplete))
+}
+
+/// CSV manifest of a batch, one row per printed letter with the pages it's
+/// printed on, then a row for each deal left out for missing something its
+/// letter needs
+pub fn manifest(
+    letters: &[LetterData],
+    page_ranges: &[(usize, usize)],
+    incomplete: &[(i32, String)],
+) -> String {
+    let mut csv = Csv::new();
+    csv.row(&[
+        "first_page",
+        "last_page",
+        "deal_id",
+        "access_code",
+        "response_url",
+        "buyer",
+        "recipient",
+        "street",
+        "city_state_zip",
+        "not_printed",
+    ]);
+    for (letter, &(first, last)) in letters.iter().zip(page_ranges) {
+        let lines = letter.house.address_lines();
+        csv.row(&[
+            first.to_string(),
+            last.to_string(),
+            letter.deal.id.to_string(),
+            letter.deal.access_code.clone(),
+            scans::response_url(&letter.deal.access_code),
+            letter.buyer.name.clone(),
+            DEFAULT_RECIPIENT.to_owned(),
+            lines.get(0).cloned().unwrap_or_default(),
+            lines.get(1).cloned().unwrap_or_default(),
+            String::new(),
+        ]);
+    }
+    for &(deal_id, ref reason) in incomplete {
+        let mut row = vec![String::new(); 10];
+        row[2] = deal_id.to_string();
+        row[9] = format!("Deal {}", reason);
+        csv.row(&row);
+    }
+    csv.into_string()
+}
+
+/// The status a mailed deal moves to. Only deals that haven't been mailed
+/// yet advance, so mailing a deal again never moves it back.
+fn next_status(current: DealStatus) -> Option<DealStatus> {
+    match current {
+        DealStatus::Initialized => Some(DealStatus::MailerSent),
+        _ => None,
+    }
+}
+
+/// Record a deal's mailer and advance the deal's status
+fn record_mailer(
+    conn: &PgConnection,
+    deal: &Deal,
+    user_id: i32,
+    batch_id: Option<i32>,
+    template_version_id: Option<i32>,
+) -> Result<(), Error> {
+    use schema::mailers;
+
+    diesel::insert_into(mailers::table)
+        .values(&NewMailer {
+            deal_id: deal.id,
+            sent_by: Some(user_id),
+            created: chrono::Utc::now().naive_utc(),
+            batch_id,
+            template_version_id,
+        })
+        .execute(conn)?;
+    if let Some(status) = next_status(deal.status) {
+        deals::set_status(conn, deal, status, Some(user_id))?;
+    }
+    Ok(())
+}
+
+fn incomplete(deal_id: i32, reason: &str) -> Error {
+    Error::from_custom_validation(
+        "mailer_incomplete",
+        "deal_id",
+        &format!("Deal {} {}", deal_id, reason),
+    )
+}
+
+///
+/// Public API
+///
+
+/// Generate a deal's mailer as a PDF and mark a new deal as sent
+pub fn send_mailer(
+    deal_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: SendMailerInput,
+) -> Result<Vec<u8>, Error> {
+    let user = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let letter_template = templates::resolve(&conn, input.template_id)?;
+    let letters = load_letters(&conn, &[deal_id])?;
+    let (document, _) = letter::render_all(&letters, &letter_template);
+
+    conn.transaction::<_, Error, _>(|| {
+        for letter in &letters {
+            record_mailer(
+                &conn,
+                &letter.deal,
+                user.id,
+                None,
+                letter_template.version_id,
+            )?;
+        }
+        Ok(())
+    })?;
+
+    Ok(document.to_bytes())
+}
+
+/// Create a batch of mailers.
+///
+/// Deals that already had a mailer are skipped unless `force` is set. The
+/// batch's PDF and manifest are stored so it can be reprinted, and every
+/// included deal that hadn't been mailed yet moves to `MailerSent` together
+/// with the batch.
+pub fn create_batch(
+    user: CurrentUser,
+    conn: Conn,
+    input: CreateMailerBatchInput,
+) -> Response<MailerBatchResult> {
+    use schema::{deals, mailer_batches, mailers};
+
+    let user = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    if input.deal_ids.is_none() && input.buyer_id.is_none() && input.status.is_none() {
+        return Err(Error::from_custom_validation(
+            "filter_required",
+            "deal_ids",
+            "Deal ids or a filter is required",
+        ));
+    }
+
+    // Deals the input picks out, including ones already mailed
+    let matching = || {
+        let mut q = deals::table.select(deals::id).into_boxed();
+        if let Some(ref ids) = input.deal_ids {
+            q = q.filter(deals::id.eq_any(ids.clone()));
+        }
+        if let Some(b) = input.buyer_id {
+            q = q.filter(deals::buyer_id.eq(b));
+        }
+        if let Some(s) = input.status {
+            q = q.filter(deals::status.eq(s));
+        }
+        q
+    };
+    let mailed = || mailers::table.select(mailers::deal_id);
+
+    let result = conn.transaction::<_, Error, _>(|| {
+        // Deals that already had a mailer are left out before the limit, so
+        // they can't crowd out ones that haven't
+        let mut q = matching();
+        if !input.force {
+            q = q.filter(deals::id.ne_all(mailed()));
+        }
+        let candidates = q
+            .order_by(deals::id)
+            .limit(MAX_BATCH_SIZE)
+            .load::<i32>(&conn)?;
+
+        // Lock the deals so a concurrent batch can't include them too, and
+        // drop any it mailed before the lock was taken
+        let locked = deals::table
+            .select(deals::id)
+            .filter(deals::id.eq_any(&candidates))
+            .order_by(deals::id)
+            .for_update()
+            .load::<i32>(&conn)?;
+        let candidates = if input.force {
+            locked
+        } else {
+            let mailed_since = mailed()
+                .filter(mailers::deal_id.eq_any(&locked))
+                .load::<i32>(&conn)?;
+            locked
+                .into_iter()
+                .filter(|d| !mailed_since.contains(d))
+                .collect()
+        };
+
+        let skipped = if input.force {
+            Vec::new()
+        } else {
+            matching()
+                .filter(deals::id.eq_any(mailed()))
+                .order_by(deals::id)
+                .load::<i32>(&conn)?
+        };
+
+        let letter_template = templates::resolve(&conn, input.template_id)?;
+        let (letters, incomplete) = gather_letters(&conn, &candidates)?;
+        if letters.is_empty() {
+            return Err(Error::from_custom_validation(
+                "no_deals_to_mail",
+                "deal_ids",
+                "No deals to mail",
+            ));
+        }
+        let included = letters.iter().map(|l| l.deal.id).collect::<Vec<i32>>();
+
+        let batch = diesel::insert_into(mailer_batches::table)
+            .values(&NewMailerBatch {
+                created_by: Some(user.id),
+                deal_count: included.len() as i32,
+                skipped_count: (skipped.len() + incomplete.len()) as i32,
+                created: chrono::Utc::now().naive_utc(),
+                // Written once the letters are rendered and their pages known
+                manifest: String::new(),
+                incomplete: serde_json::to_value(&incomplete)?,
+            })
+            .returning((
+                mailer_batches::id,
+                mailer_batches::created_by,
+                mailer_batches::deal_count,
+                mailer_batches::skipped_count,
+                mailer_batches::created,
+                mailer_batches::rendered,
+            ))
+            .get_result::<MailerBatch>(&conn)?;
+
+        for letter in &letters {
+            record_mailer(
+                &conn,
+                &letter.deal,
+                user.id,
+                Some(batch.id),
+                letter_template.version_id,
+            )?;
+        }
+
+        // Rendering hundreds of letters is slow, so the PDF is left to a job
+        jobs::enqueue(&conn, &JobPayload::RenderMailerBatch { batch_id: batch.id })?;
+
+        Ok(MailerBatchResult {
+            batch,
+            deal_ids: included,
+            skipped_deal_ids: skipped,
+            incomplete_deal_ids: incomplete.iter().map(|&(d, _)| d).collect(),
+        })
+    })?;
+
+    Ok(Payload {
+        data: result,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Render a batch's letters into its PDF and write its manifest. Runs as a
+/// job after the batch is created.
+///
+/// Deals that lost something their letter needs since the batch was
+/// created are left out and listed in the manifest with the others.
+pub fn render_batch(conn: &PgConnection, batch_id: i32) -> Result<(), Error> {
+    use schema::{mailer_batches, mailers};
+
+    let sent = mailers::table
+        .filter(mailers::batch_id.eq(batch_id))
+        .order_by(mailers::id)
+        .load::<Mailer>(conn)?;
+    let deal_ids = sent.iter().map(|m| m.deal_id).collect::<Vec<i32>>();
+
+    // Every mailer in a batch is printed with the same template version
+    let letter_template =
+        templates::version(conn, sent.first().and_then(|m| m.template_version_id))?;
+    let (letters, left_out) = gather_letters(conn, &deal_ids)?;
+    let (document, page_ranges) = letter::render_all(&letters, &letter_template);
+
+    let stored = mailer_batches::table
+        .find(batch_id)
+        .select(mailer_batches::incomplete)
+        .first::<serde_json::Value>(conn)?;
+    let mut incomplete = serde_json::from_value::<Vec<(i32, String)>>(stored)?;
+    let left_out_count = left_out.len() as i32;
+    incomplete.extend(left_out);
+
+    diesel::update(mailer_batches::table.find(batch_id))
+        .set((
+            mailer_batches::pdf.eq(Some(document.to_bytes())),
+            mailer_batches::manifest.eq(manifest(&letters, &page_ranges, &incomplete)),
+            mailer_batches::incomplete.eq(serde_json::to_value(&incomplete)?),
+            mailer_batches::deal_count.eq(letters.len() as i32),
+            mailer_batches::skipped_count.eq(mailer_batches::skipped_count + left_out_count),
+            mailer_batches::rendered.eq(Some(chrono::Utc::now().naive_utc())),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Get mailer batches
+pub fn get_batches(user: CurrentUser, conn: Conn) -> Response<Vec<MailerBatch>> {
+    use schema::mailer_batches::dsl::*;
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let b = mailer_batches
+        .select((id, created_by, deal_count, skipped_count, created, rendered))
+        .order_by(created.desc())
+        .limit(50)
+        .load::<MailerBatch>(&conn)?;
+
+    Ok(Payload {
+        data: b,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Get a mailer batch and its mailers
+pub fn get_batch(batch_id: i32, user: CurrentUser, conn: Conn) -> Response<MailerBatchDetail> {
+    use schema::{mailer_batches, mailers};
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let batch = mailer_batches::table
+        .find(batch_id)
+        .select((
+            mailer_batches::id,
+            mailer_batches::created_by,
+            mailer_batches::deal_count,
+            mailer_batches::skipped_count,
+            mailer_batches::created,
+            mailer_batches::rendered,
+        ))
+        .first::<MailerBatch>(&conn)?;
+    let m = mailers::table
+        .filter(mailers::batch_id.eq(batch.id))
+        .order_by(mailers::id)
+        .load::<Mailer>(&conn)?;
+
+    Ok(Payload {
+        data: MailerBatchDetail { batch, mailers: m },
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Get a batch's combined PDF, for printing or reprinting
+pub fn batch_pdf(batch_id: i32, user: CurrentUser, conn: Conn) -> Result<Vec<u8>, Error> {
+    use schema::mailer_batches::dsl::*;
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    mailer_batches
+        .find(batch_id)
+        .select(pdf)
+        .first::<Option<Vec<u8>>>(&conn)?
+        .ok_or_else(|| {
+            Error::from_conflict(
+                "batch_not_ready",
+                "batch_id",
+                "Batch is still being rendered",
+            )
+        })
+}
+
+/// Get a batch's CSV manifest. Its page numbers are only known once the
+/// batch is rendered.
+pub fn batch_manifest(batch_id: i32, user: CurrentUser, conn: Conn) -> Result<String, Error> {
+    use schema::mailer_batches::dsl::*;
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let (m, r) = mailer_batches
+        .find(batch_id)
+        .select((manifest, rendered))
+        .first::<(String, Option<chrono::NaiveDateTime>)>(&conn)?;
+    match r {
+        Some(_) => Ok(m),
+        None => Err(Error::from_conflict(
+            "batch_not_ready",
+            "batch_id",
+            "Batch is still being rendered",
+        )),
+    }
+}
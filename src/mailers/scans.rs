@@ -0,0 +1,176 @@
+//
+// mailers/scans.rs
+//
+// Each mailer carries a short URL (also encoded as a QR code) built from
+// the deal's access code. Following it records a scan and sends the
+// homeowner on to the claim flow in the frontend.
+//
+use accounts::types::{CurrentUser, CurrentUser::*};
+use db::Conn;
+use deals;
+use diesel::prelude::*;
+use mailers::types::*;
+use result::{Error, Payload, Response};
+use std::collections::HashMap;
+use std::env;
+
+/// Public base URL of this server, used for the short URLs on mailers
+const DEFAULT_SHORT_URL_BASE: &str = "http://localhost:8000";
+/// Public base URL of the frontend
+const DEFAULT_APP_URL: &str = "http://localhost:3000";
+
+///
+/// Helpers
+///
+
+fn base_url(var: &str, default: &str) -> String {
+    env::var(var)
+        .unwrap_or_else(|_| default.to_owned())
+        .trim_end_matches('/')
+        .to_owned()
+}
+
+/// Short URL printed on a deal's mailer, set by `SHORT_URL_BASE`
+pub fn response_url(access_code: &str) -> String {
+    format!(
+        "{}/m/{}",
+        base_url("SHORT_URL_BASE", DEFAULT_SHORT_URL_BASE),
+        access_code
+    )
+}
+
+/// Frontend page where a homeowner claims a deal, under `APP_URL`
+pub fn claim_url(access_code: &str) -> String {
+    format!(
+        "{}/claim/{}",
+        base_url("APP_URL", DEFAULT_APP_URL),
+        access_code
+    )
+}
+
+/// Frontend page for a deal, under `APP_URL`
+pub fn deal_url(deal_id: i32) -> String {
+    format!("{}/deals/{}", base_url("APP_URL", DEFAULT_APP_URL), deal_id)
+}
+
+/// Summarize a deal's scan times
+fn report(deal_id: i32, scanned: &[chrono::NaiveDateTime]) -> DealScanReport {
+    DealScanReport {
+        deal_id,
+        scan_count: scanned.len() as i64,
+        first_scanned: scanned.iter().min().cloned(),
+        last_scanned: scanned.iter().max().cloned(),
+    }
+}
+
+///
+/// Public API
+///
+
+/// Record a mailer scan, returning where to send the homeowner next
+///
+/// The scan is attributed to the deal's most recent mailer, if it has one.
+pub fn record_scan(code: String, conn: Conn) -> Result<String, Error> {
+    use schema::{mailer_scans, mailers};
+
+    let Conn(conn) = conn;
+
+    let deal = deals::find_by_access_code(&conn, &code)?;
+    let mailer_id = mailers::table
+        .select(mailers::id)
+        .filter(mailers::deal_id.eq(deal.id))
+        .order_by(mailers::created.desc())
+        .first::<i32>(&conn)
+        .optional()?;
+
+    diesel::insert_into(mailer_scans::table)
+        .values(&NewMailerScan {
+            deal_id: deal.id,
+            mailer_id,
+            created: chrono::Utc::now().naive_utc(),
+        })
+        .execute(&conn)?;
+
+    Ok(claim_url(&deal.access_code))
+}
+
+/// Scans of a deal's mailers
+pub fn deal_scans(deal_id: i32, user: CurrentUser, conn: Conn) -> Response<DealScanReport> {
+    use schema::mailer_scans;
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let scanned = mailer_scans::table
+        .select(mailer_scans::created)
+        .filter(mailer_scans::deal_id.eq(deal_id))
+        .load::<chrono::NaiveDateTime>(&conn)?;
+
+    Ok(Payload {
+        data: report(deal_id, &scanned),
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Scans of a batch's mailers, per deal
+pub fn batch_scans(batch_id: i32, user: CurrentUser, conn: Conn) -> Response<BatchScanReport> {
+    use schema::{mailer_batches, mailer_scans, mailers};
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let batch = mailer_batches::table
+        .find(batch_id)
+        .select((
+            mailer_batches::id,
+            mailer_batches::created_by,
+            mailer_batches::deal_count,
+            mailer_batches::skipped_count,
+            mailer_batches::created,
+            mailer_batches::rendered,
+        ))
+        .first::<MailerBatch>(&conn)?;
+
+    let deal_ids = mailers::table
+        .select(mailers::deal_id)
+        .filter(mailers::batch_id.eq(batch.id))
+        .order_by(mailers::deal_id)
+        .load::<i32>(&conn)?;
+
+    let mut scanned = HashMap::new();
+    for (deal_id, created) in mailer_scans::table
+        .inner_join(mailers::table)
+        .select((mailer_scans::deal_id, mailer_scans::created))
+        .filter(mailers::batch_id.eq(batch.id))
+        .load::<(i32, chrono::NaiveDateTime)>(&conn)?
+    {
+        scanned
+            .entry(deal_id)
+            .or_insert_with(Vec::new)
+            .push(created);
+    }
+
+    let per_deal = deal_ids
+        .iter()
+        .map(|d| report(*d, scanned.get(d).map(|s| &s[..]).unwrap_or(&[])))
+        .collect::<Vec<DealScanReport>>();
+
+    Ok(Payload {
+        data: BatchScanReport {
+            batch_id: batch.id,
+            deal_count: batch.deal_count,
+            deals_scanned: per_deal.iter().filter(|d| d.scan_count > 0).count() as i64,
+            scan_count: per_deal.iter().map(|d| d.scan_count).sum(),
+            deals: per_deal,
+        },
+        success: true,
+        ..Default::default()
+    })
+}
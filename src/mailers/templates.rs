@@ -0,0 +1,377 @@
+//
+// mailers/templates.rs
+//
+use accounts::types::{CurrentUser, CurrentUser::*};
+use db::Conn;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::result::DatabaseErrorKind;
+use diesel::result::Error::DatabaseError;
+use mailers::letter::{self, DEFAULT_TEMPLATE, VARIABLES};
+use mailers::load_letters;
+use mailers::types::*;
+use result::{Error, Payload, Response};
+use std::collections::HashMap;
+use template;
+use validator::Validate;
+
+///
+/// Helpers
+///
+
+/// Newest version of a template
+fn latest_version(conn: &PgConnection, tid: i32) -> Result<MailerTemplateVersion, Error> {
+    use schema::mailer_template_versions::dsl::*;
+
+    mailer_template_versions
+        .filter(template_id.eq(tid))
+        .order_by(version.desc())
+        .first::<MailerTemplateVersion>(conn)
+        .map_err(|e| Error::from(e))
+}
+
+/// Pick the copy to print with: the given template, else the default
+/// template, else the built-in copy
+pub fn resolve(conn: &PgConnection, tid: Option<i32>) -> Result<LetterTemplate, Error> {
+    use schema::mailer_templates::dsl::*;
+
+    let t = match tid {
+        Some(tid) => Some(
+            mailer_templates
+                .find(tid)
+                .filter(archived.eq(false))
+                .first::<MailerTemplate>(conn)?,
+        ),
+        None => mailer_templates
+            .filter(is_default.eq(true))
+            .filter(archived.eq(false))
+            .first::<MailerTemplate>(conn)
+            .optional()?,
+    };
+
+    match t {
+        Some(t) => {
+            let v = latest_version(conn, t.id)?;
+            Ok(LetterTemplate {
+                version_id: Some(v.id),
+                body: v.body,
+            })
+        }
+        None => Ok(LetterTemplate {
+            version_id: None,
+            body: DEFAULT_TEMPLATE.to_owned(),
+        }),
+    }
+}
+
+/// The copy a letter was printed with, by template version. `None` is the
+/// built-in copy.
+pub fn version(conn: &PgConnection, vid: Option<i32>) -> Result<LetterTemplate, Error> {
+    use schema::mailer_template_versions::dsl::*;
+
+    match vid {
+        Some(vid) => {
+            let v = mailer_template_versions
+                .find(vid)
+                .first::<MailerTemplateVersion>(conn)?;
+            Ok(LetterTemplate {
+                version_id: Some(v.id),
+                body: v.body,
+            })
+        }
+        None => Ok(LetterTemplate {
+            version_id: None,
+            body: DEFAULT_TEMPLATE.to_owned(),
+        }),
+    }
+}
+
+/// Clear the default flag from every template
+fn clear_default(conn: &PgConnection) -> Result<(), Error> {
+    use schema::mailer_templates::dsl::*;
+
+    diesel::update(mailer_templates.filter(is_default.eq(true)))
+        .set(is_default.eq(false))
+        .execute(conn)?;
+    Ok(())
+}
+
+fn map_name_taken(e: diesel::result::Error) -> Error {
+    match e {
+        DatabaseError(DatabaseErrorKind::UniqueViolation, _info) => {
+            Error::from_conflict("name_taken", "name", "Name is taken")
+        }
+        _ => Error::from(e),
+    }
+}
+
+///
+/// Public API
+///
+
+/// Get templates with their current versions
+pub fn get_templates(user: CurrentUser, conn: Conn) -> Response<Vec<MailerTemplateWithVersion>> {
+    use schema::{mailer_template_versions, mailer_templates};
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let templates = mailer_templates::table
+        .filter(mailer_templates::archived.eq(false))
+        .order_by(mailer_templates::name)
+        .load::<MailerTemplate>(&conn)?;
+    let template_ids = templates.iter().map(|t| t.id).collect::<Vec<i32>>();
+
+    // Versions come newest first, so the first one seen is the current one
+    let mut current = HashMap::new();
+    for v in mailer_template_versions::table
+        .filter(mailer_template_versions::template_id.eq_any(&template_ids))
+        .order_by((
+            mailer_template_versions::template_id,
+            mailer_template_versions::version.desc(),
+        ))
+        .load::<MailerTemplateVersion>(&conn)?
+    {
+        current.entry(v.template_id).or_insert(v);
+    }
+
+    let t = templates
+        .into_iter()
+        .filter_map(|template| {
+            current
+                .remove(&template.id)
+                .map(|current_version| MailerTemplateWithVersion {
+                    template,
+                    current_version,
+                })
+        })
+        .collect();
+
+    Ok(Payload {
+        data: t,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Get a template with all of its versions
+pub fn get_template(tid: i32, user: CurrentUser, conn: Conn) -> Response<MailerTemplateDetail> {
+    use schema::{mailer_template_versions, mailer_templates};
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let template = mailer_templates::table
+        .find(tid)
+        .first::<MailerTemplate>(&conn)?;
+    let versions = mailer_template_versions::table
+        .filter(mailer_template_versions::template_id.eq(template.id))
+        .order_by(mailer_template_versions::version.desc())
+        .load::<MailerTemplateVersion>(&conn)?;
+
+    Ok(Payload {
+        data: MailerTemplateDetail { template, versions },
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Create a template and its first version
+pub fn create_template(
+    user: CurrentUser,
+    conn: Conn,
+    input: CreateMailerTemplateInput,
+) -> Response<MailerTemplateWithVersion> {
+    use schema::{mailer_template_versions, mailer_templates};
+
+    let user = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    input.validate()?;
+    template::validate(&input.body, VARIABLES)?;
+
+    let t = conn.transaction::<_, Error, _>(|| {
+        if input.is_default {
+            clear_default(&conn)?;
+        }
+
+        let template = diesel::insert_into(mailer_templates::table)
+            .values(&NewMailerTemplate {
+                name: input.name.trim().to_owned(),
+                is_default: input.is_default,
+                archived: false,
+                created_by: Some(user.id),
+                created: chrono::Utc::now().naive_utc(),
+                updated: chrono::Utc::now().naive_utc(),
+            })
+            .get_result::<MailerTemplate>(&conn)
+            .map_err(map_name_taken)?;
+
+        let current_version = diesel::insert_into(mailer_template_versions::table)
+            .values(&NewMailerTemplateVersion {
+                template_id: template.id,
+                version: 1,
+                body: input.body.clone(),
+                created_by: Some(user.id),
+                created: chrono::Utc::now().naive_utc(),
+            })
+            .get_result::<MailerTemplateVersion>(&conn)?;
+
+        Ok(MailerTemplateWithVersion {
+            template,
+            current_version,
+        })
+    })?;
+
+    Ok(Payload {
+        data: t,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Update template
+///
+/// Versions are immutable, so a changed body is saved as a new version.
+pub fn update_template(
+    tid: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: UpdateMailerTemplateInput,
+) -> Response<MailerTemplateWithVersion> {
+    use schema::{mailer_template_versions, mailer_templates};
+
+    let user = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    input.validate()?;
+    if let Some(ref body) = input.body {
+        template::validate(body, VARIABLES)?;
+    }
+
+    let t = conn.transaction::<_, Error, _>(|| {
+        let template = mailer_templates::table
+            .find(tid)
+            .filter(mailer_templates::archived.eq(false))
+            .for_update()
+            .first::<MailerTemplate>(&conn)?;
+
+        if input.is_default == Some(true) && !template.is_default {
+            clear_default(&conn)?;
+        }
+
+        // If the field is set, use the value
+        // If it is not set, ignore.
+        let template = diesel::update(&template)
+            .set((
+                mailer_templates::name.eq(input
+                    .name
+                    .as_ref()
+                    .map(|n| n.trim().to_owned())
+                    .unwrap_or_else(|| template.name.clone())),
+                mailer_templates::is_default.eq(input.is_default.unwrap_or(template.is_default)),
+                mailer_templates::updated.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .get_result::<MailerTemplate>(&conn)
+            .map_err(map_name_taken)?;
+
+        let latest = latest_version(&conn, template.id)?;
+        let current_version = match input.body {
+            Some(ref body) if *body != latest.body => {
+                diesel::insert_into(mailer_template_versions::table)
+                    .values(&NewMailerTemplateVersion {
+                        template_id: template.id,
+                        version: latest.version + 1,
+                        body: body.clone(),
+                        created_by: Some(user.id),
+                        created: chrono::Utc::now().naive_utc(),
+                    })
+                    .get_result::<MailerTemplateVersion>(&conn)?
+            }
+            _ => latest,
+        };
+
+        Ok(MailerTemplateWithVersion {
+            template,
+            current_version,
+        })
+    })?;
+
+    Ok(Payload {
+        data: t,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Archive template
+///
+/// Templates are never deleted, since sent mailers point at their versions.
+pub fn archive_template(tid: i32, user: CurrentUser, conn: Conn) -> Response<MailerTemplate> {
+    use schema::mailer_templates::dsl::*;
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let t = diesel::update(mailer_templates.find(tid))
+        .set((
+            archived.eq(true),
+            is_default.eq(false),
+            updated.eq(chrono::Utc::now().naive_utc()),
+        ))
+        .get_result::<MailerTemplate>(&conn)?;
+
+    Ok(Payload {
+        data: t,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Render a template against a real deal, as the PDF that would be printed
+pub fn preview_template(
+    tid: i32,
+    query: MailerPreviewQuery,
+    user: CurrentUser,
+    conn: Conn,
+) -> Result<Vec<u8>, Error> {
+    use schema::mailer_template_versions;
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let v = match query.version {
+        Some(v) => mailer_template_versions::table
+            .filter(mailer_template_versions::template_id.eq(tid))
+            .filter(mailer_template_versions::version.eq(v))
+            .first::<MailerTemplateVersion>(&conn)?,
+        None => latest_version(&conn, tid)?,
+    };
+    let letter_template = LetterTemplate {
+        version_id: Some(v.id),
+        body: v.body,
+    };
+
+    let letters = load_letters(&conn, &[query.deal_id])?;
+    let (document, _) = letter::render_all(&letters, &letter_template);
+
+    Ok(document.to_bytes())
+}
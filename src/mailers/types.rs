@@ -0,0 +1,305 @@
+//
+// mailers/types.rs
+//
+use accounts::types::{Profile, User};
+use deals::types::{Deal, DealStatus};
+use houses::types::House;
+use rocket::http::RawStr;
+use rocket::request::FromFormValue;
+use schema::{mailer_batches, mailer_scans, mailer_template_versions, mailer_templates, mailers};
+use validator::Validate;
+
+#[derive(Serialize, Identifiable, Clone, Queryable, Debug)]
+#[table_name = "mailers"]
+pub struct Mailer {
+    pub id: i32,
+    pub deal_id: i32,
+    pub sent_by: Option<i32>,
+    pub created: chrono::NaiveDateTime,
+    pub batch_id: Option<i32>,
+    pub template_version_id: Option<i32>,
+}
+
+#[derive(Insertable)]
+#[table_name = "mailers"]
+pub struct NewMailer {
+    pub deal_id: i32,
+    pub sent_by: Option<i32>,
+    pub created: chrono::NaiveDateTime,
+    pub batch_id: Option<i32>,
+    pub template_version_id: Option<i32>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct SendMailerInput {
+    /// Template to print with, instead of the default one
+    pub template_id: Option<i32>,
+}
+
+/// A batch of mailers, without the rendered output
+#[derive(Serialize, Clone, Queryable, Debug)]
+pub struct MailerBatch {
+    pub id: i32,
+    pub created_by: Option<i32>,
+    pub deal_count: i32,
+    pub skipped_count: i32,
+    pub created: chrono::NaiveDateTime,
+    /// When the batch PDF finished rendering
+    pub rendered: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[table_name = "mailer_batches"]
+pub struct NewMailerBatch {
+    pub created_by: Option<i32>,
+    pub deal_count: i32,
+    pub skipped_count: i32,
+    pub created: chrono::NaiveDateTime,
+    pub manifest: String,
+    /// Deals left out for missing something their letter needs, and why
+    pub incomplete: serde_json::Value,
+}
+
+/// Deals to include in a batch, either by id or by filter
+#[derive(Deserialize)]
+pub struct CreateMailerBatchInput {
+    pub deal_ids: Option<Vec<i32>>,
+    pub buyer_id: Option<i32>,
+    pub status: Option<DealStatus>,
+    /// Include deals that already had a mailer
+    #[serde(default)]
+    pub force: bool,
+    /// Template to print with, instead of the default one
+    pub template_id: Option<i32>,
+}
+
+#[derive(Serialize)]
+pub struct MailerBatchResult {
+    pub batch: MailerBatch,
+    pub deal_ids: Vec<i32>,
+    /// Deals that already had a mailer
+    pub skipped_deal_ids: Vec<i32>,
+    /// Deals missing something their letter needs. The manifest says what.
+    pub incomplete_deal_ids: Vec<i32>,
+}
+
+#[derive(Serialize)]
+pub struct MailerBatchDetail {
+    pub batch: MailerBatch,
+    pub mailers: Vec<Mailer>,
+}
+
+/// Everything a deal's letter is merged from
+#[derive(Clone)]
+pub struct LetterData {
+    pub deal: Deal,
+    pub house: House,
+    pub buyer: User,
+    pub profile: Profile,
+}
+
+/// The copy a letter is printed with
+pub struct LetterTemplate {
+    /// `None` for the built-in copy
+    pub version_id: Option<i32>,
+    pub body: String,
+}
+
+#[derive(Serialize, Identifiable, Clone, Queryable, Debug)]
+#[table_name = "mailer_templates"]
+pub struct MailerTemplate {
+    pub id: i32,
+    pub name: String,
+    pub is_default: bool,
+    pub archived: bool,
+    pub created_by: Option<i32>,
+    pub created: chrono::NaiveDateTime,
+    pub updated: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "mailer_templates"]
+pub struct NewMailerTemplate {
+    pub name: String,
+    pub is_default: bool,
+    pub archived: bool,
+    pub created_by: Option<i32>,
+    pub created: chrono::NaiveDateTime,
+    pub updated: chrono::NaiveDateTime,
+}
+
+#[derive(Serialize, Identifiable, Clone, Queryable, Debug)]
+#[table_name = "mailer_template_versions"]
+pub struct MailerTemplateVersion {
+    pub id: i32,
+    pub template_id: i32,
+    pub version: i32,
+    pub body: String,
+    pub created_by: Option<i32>,
+    pub created: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "mailer_template_versions"]
+pub struct NewMailerTemplateVersion {
+    pub template_id: i32,
+    pub version: i32,
+    pub body: String,
+    pub created_by: Option<i32>,
+    pub created: chrono::NaiveDateTime,
+}
+
+#[derive(Serialize)]
+pub struct MailerTemplateWithVersion {
+    pub template: MailerTemplate,
+    pub current_version: MailerTemplateVersion,
+}
+
+#[derive(Serialize)]
+pub struct MailerTemplateDetail {
+    pub template: MailerTemplate,
+    /// Newest first
+    pub versions: Vec<MailerTemplateVersion>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CreateMailerTemplateInput {
+    #[validate(length(min = "1", max = "255", message = "Cannot be blank"))]
+    pub name: String,
+    #[validate(length(min = "1", max = "10000", message = "Cannot be blank"))]
+    pub body: String,
+    #[serde(default)]
+    pub is_default: bool,
+}
+
+/// Changing the body creates a new version
+#[derive(Deserialize, Validate)]
+pub struct UpdateMailerTemplateInput {
+    #[validate(length(min = "1", max = "255", message = "Cannot be blank"))]
+    pub name: Option<String>,
+    #[validate(length(min = "1", max = "10000", message = "Cannot be blank"))]
+    pub body: Option<String>,
+    pub is_default: Option<bool>,
+}
+
+#[derive(FromForm, Deserialize, Debug)]
+pub struct MailerPreviewQuery {
+    pub deal_id: i32,
+    /// Version to preview, the latest by default
+    pub version: Option<i32>,
+}
+
+#[derive(Serialize, Clone, Queryable, Debug)]
+pub struct MailerScan {
+    pub id: i32,
+    pub deal_id: i32,
+    pub mailer_id: Option<i32>,
+    pub created: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "mailer_scans"]
+pub struct NewMailerScan {
+    pub deal_id: i32,
+    pub mailer_id: Option<i32>,
+    pub created: chrono::NaiveDateTime,
+}
+
+#[derive(Serialize)]
+pub struct DealScanReport {
+    pub deal_id: i32,
+    pub scan_count: i64,
+    pub first_scanned: Option<chrono::NaiveDateTime>,
+    pub last_scanned: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Serialize)]
+pub struct BatchScanReport {
+    pub batch_id: i32,
+    pub deal_count: i32,
+    /// Deals scanned at least once
+    pub deals_scanned: i64,
+    pub scan_count: i64,
+    pub deals: Vec<DealScanReport>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+pub enum ExportFormat {
+    Pdf,
+    Csv,
+}
+
+impl Default for ExportFormat {
+    fn default() -> Self {
+        ExportFormat::Pdf
+    }
+}
+
+impl<'v> FromFormValue<'v> for ExportFormat {
+    type Error = &'v RawStr;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, &'v RawStr> {
+        match form_value.as_str() {
+            "pdf" => Ok(ExportFormat::Pdf),
+            "csv" => Ok(ExportFormat::Csv),
+            _ => Err(form_value),
+        }
+    }
+}
+
+/// Label sheets, named by their Avery product number
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+pub enum LabelLayout {
+    /// 30 per sheet, 2 5/8" x 1"
+    Avery5160,
+    /// 10 per sheet, 4" x 2"
+    Avery5163,
+}
+
+impl Default for LabelLayout {
+    fn default() -> Self {
+        LabelLayout::Avery5160
+    }
+}
+
+impl<'v> FromFormValue<'v> for LabelLayout {
+    type Error = &'v RawStr;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, &'v RawStr> {
+        match form_value.as_str() {
+            "5160" => Ok(LabelLayout::Avery5160),
+            "5163" => Ok(LabelLayout::Avery5163),
+            _ => Err(form_value),
+        }
+    }
+}
+
+/// Comma separated ids, as in `1,2,3`
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct IdList(pub Vec<i32>);
+
+impl<'v> FromFormValue<'v> for IdList {
+    type Error = &'v RawStr;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, &'v RawStr> {
+        form_value
+            .split(',')
+            .map(|id| id.trim().parse::<i32>())
+            .collect::<Result<Vec<i32>, _>>()
+            .map(IdList)
+            .map_err(|_| form_value)
+    }
+}
+
+#[derive(FromForm, Debug, Default)]
+pub struct LabelExportQuery {
+    /// `pdf` (the default) or `csv`
+    pub format: Option<ExportFormat>,
+    /// `5160` (the default) or `5163`
+    pub layout: Option<LabelLayout>,
+    pub deal_ids: Option<IdList>,
+    pub buyer_id: Option<i32>,
+    pub status: Option<DealStatus>,
+    /// Name used when a deal has no seller, "Current Resident" by default
+    pub recipient: Option<String>,
+}
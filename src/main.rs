@@ -3,9 +3,19 @@
 
 extern crate bcrypt;
 extern crate dotenv;
+extern crate fallible_iterator;
+extern crate hex;
+extern crate hmac;
+extern crate lettre;
+extern crate lettre_email;
+extern crate postgres;
+extern crate qrcode;
+extern crate rand;
+extern crate reqwest;
 extern crate rocket_contrib;
 extern crate serde;
 extern crate serde_json;
+extern crate sha2;
 extern crate validator;
 
 #[macro_use]
@@ -13,19 +23,58 @@ extern crate rocket;
 #[macro_use]
 extern crate diesel;
 #[macro_use]
+extern crate juniper;
+#[macro_use]
 extern crate validator_derive;
 #[macro_use]
 extern crate serde_derive;
 
 mod accounts;
+mod csv;
 mod db;
 mod deals;
+mod documents;
+mod email;
+mod events;
+mod geocoding;
+mod graphql;
 mod housekeeping;
+mod houses;
+mod jobs;
+mod mailers;
+mod notes;
+mod notifications;
+mod offers;
+mod pdf;
+mod responses;
 mod result;
 mod schema;
+mod storage;
+mod tasks;
+mod template;
 mod web;
+mod webhooks;
+
+use std::env;
+use std::process;
 
 fn main() {
+    if let Err(e) = start() {
+        eprintln!("Configuration error: {}", e);
+        process::exit(1);
+    }
+}
+
+/// Start the web server and job workers, or only the workers with `worker`
+fn start() -> Result<(), String> {
     let _ = housekeeping::run();
-    web::launch();
+    housekeeping::scheduler::spawn();
+
+    match env::args().nth(1) {
+        Some(ref cmd) if cmd == "worker" => jobs::worker::run(),
+        _ => {
+            jobs::worker::spawn()?;
+            web::launch()
+        }
+    }
 }
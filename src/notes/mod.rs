@@ -0,0 +1,359 @@
+//
+// notes/mod.rs
+//
+pub mod types;
+
+use self::types::*;
+use accounts::types::{CurrentUser, CurrentUser::*};
+use db::Conn;
+use deals::types::{Deal, DealRole};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use notifications;
+use notifications::types::NotificationKind;
+use result::{Error, Payload, Response};
+use validator::Validate;
+
+///
+/// Helpers
+///
+
+/// Visibilities the user can read and write on a deal. Admins see every
+/// note, even on deals where they're the buyer.
+fn allowed_visibilities(deal: &Deal, user: &CurrentUser) -> Result<Vec<NoteVisibility>, Error> {
+    if let Admin(_) = *user {
+        return Ok(vec![
+            NoteVisibility::Internal,
+            NoteVisibility::Buyer,
+            NoteVisibility::Seller,
+        ]);
+    }
+    match deal.role_of(user) {
+        Some(DealRole::Buyer) => Ok(vec![NoteVisibility::Buyer]),
+        Some(DealRole::Seller) => Ok(vec![NoteVisibility::Seller]),
+        _ => Err(Error::AccessDenied),
+    }
+}
+
+fn check_visibility(allowed: &[NoteVisibility], visibility: NoteVisibility) -> Result<(), Error> {
+    if allowed.contains(&visibility) {
+        Ok(())
+    } else {
+        Err(Error::from_custom_validation(
+            "invalid_visibility",
+            "visibility",
+            "You cannot post notes with this visibility",
+        ))
+    }
+}
+
+/// A thread shares one visibility, so replies can't have their own, and a
+/// note with replies can't change its own
+fn check_thread_visibility(
+    conn: &PgConnection,
+    note: &DealNote,
+    visibility: NoteVisibility,
+) -> Result<(), Error> {
+    use schema::deal_notes;
+
+    if visibility == note.visibility {
+        return Ok(());
+    }
+    if note.parent_id.is_some() {
+        return Err(reply_visibility());
+    }
+    let replies = deal_notes::table
+        .filter(deal_notes::parent_id.eq(note.id))
+        .filter(deal_notes::deleted.is_null())
+        .count()
+        .get_result::<i64>(conn)?;
+    if replies > 0 {
+        return Err(Error::from_custom_validation(
+            "thread_visibility",
+            "visibility",
+            "Notes with replies keep their visibility",
+        ));
+    }
+    Ok(())
+}
+
+fn reply_visibility() -> Error {
+    Error::from_custom_validation(
+        "reply_visibility",
+        "visibility",
+        "Replies take their parent's visibility",
+    )
+}
+
+fn user_id(user: &CurrentUser) -> Option<i32> {
+    match *user {
+        Admin(ref u) | Authenticated(ref u) => Some(u.id),
+        Anonymous => None,
+    }
+}
+
+/// Let the side a note is shared with know about it
+fn notify_readers(conn: &PgConnection, deal: &Deal, note: &DealNote) -> Result<(), Error> {
+    let reader = match note.visibility {
+        NoteVisibility::Internal => None,
+        NoteVisibility::Buyer => deal.buyer_id,
+        NoteVisibility::Seller => deal.seller_id,
+    };
+    let recipients = reader
+        .into_iter()
+        .filter(|r| Some(*r) != note.author_id)
+        .collect::<Vec<i32>>();
+
+    notifications::notify(
+        conn,
+        deal,
+        &recipients,
+        NotificationKind::NoteAdded,
+        format!("New note on {}", deal.title),
+    )?;
+    Ok(())
+}
+
+/// Only admins and a note's author can change it or see its history
+fn check_can_modify(note: &DealNote, user: &CurrentUser) -> Result<(), Error> {
+    match *user {
+        Admin(_) => Ok(()),
+        _ if note.author_id.is_some() && note.author_id == user_id(user) => Ok(()),
+        _ => Err(Error::AccessDenied),
+    }
+}
+
+fn load_deal(conn: &PgConnection, deal_id: i32) -> Result<Deal, Error> {
+    use schema::deals::dsl::*;
+
+    deals
+        .find(deal_id)
+        .first::<Deal>(conn)
+        .map_err(|e| Error::from(e))
+}
+
+/// Load a note the user can see. Notes they can't see are not found.
+fn load_note(
+    conn: &PgConnection,
+    deal: &Deal,
+    note_id: i32,
+    allowed: &[NoteVisibility],
+) -> Result<DealNote, Error> {
+    use schema::deal_notes::dsl::*;
+
+    deal_notes
+        .find(note_id)
+        .filter(deal_id.eq(deal.id))
+        .filter(deleted.is_null())
+        .filter(visibility.eq_any(allowed.to_vec()))
+        .first::<DealNote>(conn)
+        .map_err(|e| Error::from(e))
+}
+
+/// Save the note as it is now, before it's changed
+fn save_revision(conn: &PgConnection, note: &DealNote, user: &CurrentUser) -> Result<(), Error> {
+    use schema::deal_note_revisions;
+
+    diesel::insert_into(deal_note_revisions::table)
+        .values(&NewDealNoteRevision {
+            note_id: note.id,
+            body: note.body.clone(),
+            visibility: note.visibility,
+            edited_by: user_id(user),
+            created: chrono::Utc::now().naive_utc(),
+        })
+        .execute(conn)?;
+    Ok(())
+}
+
+///
+/// Public API
+///
+
+/// Get the notes on a deal the user can see, oldest first
+pub fn get_notes(deal_id: i32, user: CurrentUser, conn: Conn) -> Response<Vec<DealNote>> {
+    use schema::deal_notes;
+
+    let Conn(conn) = conn;
+
+    let deal = load_deal(&conn, deal_id)?;
+    let allowed = allowed_visibilities(&deal, &user)?;
+
+    let n = deal_notes::table
+        .filter(deal_notes::deal_id.eq(deal.id))
+        .filter(deal_notes::deleted.is_null())
+        .filter(deal_notes::visibility.eq_any(allowed))
+        .order_by(deal_notes::created)
+        .load::<DealNote>(&conn)?;
+
+    Ok(Payload {
+        data: n,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Add a note, or a reply to one
+pub fn create_note(
+    deal_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: CreateNoteInput,
+) -> Response<DealNote> {
+    use schema::deal_notes;
+
+    let Conn(conn) = conn;
+
+    input.validate()?;
+
+    let deal = load_deal(&conn, deal_id)?;
+    let allowed = allowed_visibilities(&deal, &user)?;
+
+    let visibility = match input.parent_id {
+        Some(parent_id) => {
+            let v = load_note(&conn, &deal, parent_id, &allowed)?.visibility;
+            match input.visibility {
+                Some(requested) if requested != v => return Err(reply_visibility()),
+                _ => v,
+            }
+        }
+        None => {
+            let v = input.visibility.unwrap_or(allowed[0]);
+            check_visibility(&allowed, v)?;
+            v
+        }
+    };
+
+    let n = conn.transaction::<_, Error, _>(|| {
+        let note = diesel::insert_into(deal_notes::table)
+            .values(&NewDealNote {
+                deal_id: deal.id,
+                parent_id: input.parent_id,
+                author_id: user_id(&user),
+                body: input.body,
+                visibility,
+                created: chrono::Utc::now().naive_utc(),
+                updated: chrono::Utc::now().naive_utc(),
+            })
+            .get_result::<DealNote>(&conn)?;
+        notify_readers(&conn, &deal, &note)?;
+        Ok(note)
+    })?;
+
+    Ok(Payload {
+        data: n,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Edit a note, keeping the previous version in its history
+pub fn update_note(
+    deal_id: i32,
+    note_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: UpdateNoteInput,
+) -> Response<DealNote> {
+    use schema::deal_notes;
+
+    let Conn(conn) = conn;
+
+    input.validate()?;
+
+    let deal = load_deal(&conn, deal_id)?;
+    let allowed = allowed_visibilities(&deal, &user)?;
+
+    let n = conn.transaction::<_, Error, _>(|| {
+        let note = load_note(&conn, &deal, note_id, &allowed)?;
+        check_can_modify(&note, &user)?;
+        if let Some(v) = input.visibility {
+            check_visibility(&allowed, v)?;
+            check_thread_visibility(&conn, &note, v)?;
+        }
+
+        save_revision(&conn, &note, &user)?;
+
+        // If the field is set, use the value
+        // If it is not set, ignore.
+        diesel::update(&note)
+            .set((
+                deal_notes::body.eq(input.body.clone().unwrap_or_else(|| note.body.clone())),
+                deal_notes::visibility.eq(input.visibility.unwrap_or(note.visibility)),
+                deal_notes::updated.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .get_result::<DealNote>(&conn)
+            .map_err(|e| Error::from(e))
+    })?;
+
+    Ok(Payload {
+        data: n,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Delete a note
+///
+/// Notes are only marked deleted, so their history is kept.
+pub fn delete_note(
+    deal_id: i32,
+    note_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+) -> Response<DealNote> {
+    use schema::deal_notes;
+
+    let Conn(conn) = conn;
+
+    let deal = load_deal(&conn, deal_id)?;
+    let allowed = allowed_visibilities(&deal, &user)?;
+
+    let n = conn.transaction::<_, Error, _>(|| {
+        let note = load_note(&conn, &deal, note_id, &allowed)?;
+        check_can_modify(&note, &user)?;
+
+        save_revision(&conn, &note, &user)?;
+
+        diesel::update(&note)
+            .set(deal_notes::deleted.eq(Some(chrono::Utc::now().naive_utc())))
+            .get_result::<DealNote>(&conn)
+            .map_err(|e| Error::from(e))
+    })?;
+
+    Ok(Payload {
+        data: n,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Get a note with its earlier versions
+pub fn get_note_history(
+    deal_id: i32,
+    note_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+) -> Response<DealNoteHistory> {
+    use schema::deal_note_revisions;
+
+    let Conn(conn) = conn;
+
+    let deal = load_deal(&conn, deal_id)?;
+    let allowed = allowed_visibilities(&deal, &user)?;
+    let note = load_note(&conn, &deal, note_id, &allowed)?;
+
+    // Earlier versions may have had a narrower visibility
+    check_can_modify(&note, &user)?;
+
+    let revisions = deal_note_revisions::table
+        .filter(deal_note_revisions::note_id.eq(note.id))
+        .order_by(deal_note_revisions::created.desc())
+        .load::<DealNoteRevision>(&conn)?;
+
+    Ok(Payload {
+        data: DealNoteHistory { note, revisions },
+        success: true,
+        ..Default::default()
+    })
+}
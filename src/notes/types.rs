@@ -0,0 +1,118 @@
+//
+// notes/types.rs
+//
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Varchar;
+use schema::{deal_note_revisions, deal_notes};
+use std::io::Write;
+use validator::Validate;
+
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, AsExpression, FromSqlRow)]
+#[sql_type = "Varchar"]
+pub enum NoteVisibility {
+    /// Admins only
+    Internal,
+    /// Admins and the deal's buyer
+    Buyer,
+    /// Admins and the deal's seller
+    Seller,
+}
+
+impl ToSql<Varchar, Pg> for NoteVisibility {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        match *self {
+            NoteVisibility::Internal => out.write_all(b"internal")?,
+            NoteVisibility::Buyer => out.write_all(b"buyer")?,
+            NoteVisibility::Seller => out.write_all(b"seller")?,
+        }
+
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Pg> for NoteVisibility {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match not_none!(bytes) {
+            b"internal" => Ok(NoteVisibility::Internal),
+            b"buyer" => Ok(NoteVisibility::Buyer),
+            b"seller" => Ok(NoteVisibility::Seller),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
+
+#[derive(Serialize, Identifiable, Clone, Queryable, Debug)]
+#[table_name = "deal_notes"]
+pub struct DealNote {
+    pub id: i32,
+    pub deal_id: i32,
+    /// Note this one replies to
+    pub parent_id: Option<i32>,
+    pub author_id: Option<i32>,
+    /// Markdown
+    pub body: String,
+    pub visibility: NoteVisibility,
+    pub created: chrono::NaiveDateTime,
+    pub updated: chrono::NaiveDateTime,
+    pub deleted: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[table_name = "deal_notes"]
+pub struct NewDealNote {
+    pub deal_id: i32,
+    pub parent_id: Option<i32>,
+    pub author_id: Option<i32>,
+    pub body: String,
+    pub visibility: NoteVisibility,
+    pub created: chrono::NaiveDateTime,
+    pub updated: chrono::NaiveDateTime,
+}
+
+#[derive(Serialize, Clone, Queryable, Debug)]
+pub struct DealNoteRevision {
+    pub id: i32,
+    pub note_id: i32,
+    pub body: String,
+    pub visibility: NoteVisibility,
+    pub edited_by: Option<i32>,
+    pub created: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "deal_note_revisions"]
+pub struct NewDealNoteRevision {
+    pub note_id: i32,
+    pub body: String,
+    pub visibility: NoteVisibility,
+    pub edited_by: Option<i32>,
+    pub created: chrono::NaiveDateTime,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CreateNoteInput {
+    #[validate(length(min = "1", max = "20000", message = "Cannot be blank"))]
+    pub body: String,
+    /// Defaults to internal for admins, and to their own side for the
+    /// buyer and seller. Replies always take their parent's visibility,
+    /// and can only give it if it's the same.
+    pub visibility: Option<NoteVisibility>,
+    pub parent_id: Option<i32>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct UpdateNoteInput {
+    #[validate(length(min = "1", max = "20000", message = "Cannot be blank"))]
+    pub body: Option<String>,
+    /// Only notes without replies can change it
+    pub visibility: Option<NoteVisibility>,
+}
+
+#[derive(Serialize)]
+pub struct DealNoteHistory {
+    pub note: DealNote,
+    /// Earlier versions, newest first
+    pub revisions: Vec<DealNoteRevision>,
+}
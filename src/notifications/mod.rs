@@ -0,0 +1,279 @@
+//
+// notifications/mod.rs
+//
+pub mod types;
+
+use self::types::*;
+use accounts::types::{CurrentUser, CurrentUser::*, User};
+use db::Conn;
+use deals::types::Deal;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use email;
+use email::types::EmailTemplate;
+use events;
+use events::types::{Audience, Event};
+use mailers::scans::deal_url;
+use result::{Error, Payload, Response};
+
+/// Every kind of notification, in the order preferences are listed
+const ALL_KINDS: [NotificationKind; 4] = [
+    NotificationKind::StatusChanged,
+    NotificationKind::OfferReceived,
+    NotificationKind::NoteAdded,
+    NotificationKind::SellerResponded,
+];
+
+/// Whether a kind goes out by email for users who haven't said
+const EMAIL_BY_DEFAULT: bool = true;
+
+/// Most notifications a single listing returns
+const MAX_NOTIFICATIONS: i64 = 100;
+
+///
+/// Helpers
+///
+
+fn signed_in(user: CurrentUser) -> Result<User, Error> {
+    match user {
+        Admin(user) => Ok(user),
+        Authenticated(user) => Ok(user),
+        Anonymous => Err(Error::AccessDenied),
+    }
+}
+
+/// A user's email setting for every kind, with defaults filled in
+fn preferences(conn: &PgConnection, uid: i32) -> Result<Vec<NotificationPreference>, Error> {
+    use schema::notification_preferences::dsl::*;
+
+    let saved = notification_preferences
+        .filter(user_id.eq(uid))
+        .load::<NotificationPreferenceRecord>(conn)?;
+
+    Ok(ALL_KINDS
+        .iter()
+        .map(|k| NotificationPreference {
+            kind: *k,
+            email: saved
+                .iter()
+                .find(|p| p.kind == *k)
+                .map_or(EMAIL_BY_DEFAULT, |p| p.email),
+        })
+        .collect())
+}
+
+/// Notify users about something that happened on a deal, emailing those
+/// who want this kind by email. Users are only notified once however often
+/// they're listed. The deal's timeline gets the message too, for the users
+/// and admins.
+pub fn notify(
+    conn: &PgConnection,
+    deal: &Deal,
+    recipients: &[i32],
+    kind: NotificationKind,
+    message: String,
+) -> Result<usize, Error> {
+    use schema::{notifications, users};
+
+    let mut recipients = recipients.to_vec();
+    recipients.sort();
+    recipients.dedup();
+
+    events::publish(
+        conn,
+        Audience {
+            users: recipients.clone(),
+            admins: true,
+        },
+        Event::DealActivity {
+            deal_id: deal.id,
+            kind,
+            message: message.clone(),
+        },
+    )?;
+
+    for uid in &recipients {
+        let notification = diesel::insert_into(notifications::table)
+            .values(&NewNotification {
+                user_id: *uid,
+                deal_id: Some(deal.id),
+                kind,
+                message: message.clone(),
+                created: chrono::Utc::now().naive_utc(),
+            })
+            .get_result::<Notification>(conn)?;
+        events::publish(
+            conn,
+            Audience::user(*uid),
+            Event::Notification { notification },
+        )?;
+
+        let wants_email = preferences(conn, *uid)?
+            .iter()
+            .any(|p| p.kind == kind && p.email);
+        if wants_email {
+            let user = users::table.find(*uid).first::<User>(conn)?;
+            email::queue(
+                conn,
+                &user.email,
+                Some(user.id),
+                &EmailTemplate::DealActivity {
+                    name: user.name.clone(),
+                    deal_title: deal.title.clone(),
+                    summary: message.clone(),
+                    deal_url: deal_url(deal.id),
+                },
+            )?;
+        }
+    }
+
+    Ok(recipients.len())
+}
+
+/// The deal's buyer and seller
+pub fn parties(deal: &Deal) -> Vec<i32> {
+    vec![deal.buyer_id, deal.seller_id]
+        .into_iter()
+        .filter_map(|p| p)
+        .collect()
+}
+
+///
+/// Public API
+///
+
+/// Get the current user's notifications, newest first, with their unread
+/// count
+pub fn get_notifications(
+    query: NotificationsQuery,
+    user: CurrentUser,
+    conn: Conn,
+) -> Response<NotificationList> {
+    use schema::notifications::dsl::*;
+
+    let user = signed_in(user)?;
+    let Conn(conn) = conn;
+
+    let mut q = notifications.filter(user_id.eq(user.id)).into_boxed();
+    if query.unread == Some(true) {
+        q = q.filter(read.is_null());
+    }
+    let n = q
+        .order_by(created.desc())
+        .limit(MAX_NOTIFICATIONS)
+        .load::<Notification>(&conn)?;
+    let unread_count = notifications
+        .filter(user_id.eq(user.id))
+        .filter(read.is_null())
+        .count()
+        .get_result::<i64>(&conn)?;
+
+    Ok(Payload {
+        data: NotificationList {
+            notifications: n,
+            unread_count,
+        },
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Mark one of the current user's notifications read
+pub fn mark_read(notification_id: i32, user: CurrentUser, conn: Conn) -> Response<Notification> {
+    use schema::notifications::dsl::*;
+
+    let user = signed_in(user)?;
+    let Conn(conn) = conn;
+
+    let n = notifications
+        .find(notification_id)
+        .filter(user_id.eq(user.id))
+        .first::<Notification>(&conn)?;
+    let n = match n.read {
+        Some(_) => n,
+        None => diesel::update(&n)
+            .set(read.eq(Some(chrono::Utc::now().naive_utc())))
+            .get_result::<Notification>(&conn)?,
+    };
+
+    Ok(Payload {
+        data: n,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Mark all the current user's notifications read. Returns how many were
+/// unread.
+pub fn mark_all_read(user: CurrentUser, conn: Conn) -> Response<usize> {
+    use schema::notifications::dsl::*;
+
+    let user = signed_in(user)?;
+    let Conn(conn) = conn;
+
+    let count = diesel::update(
+        notifications
+            .filter(user_id.eq(user.id))
+            .filter(read.is_null()),
+    )
+    .set(read.eq(Some(chrono::Utc::now().naive_utc())))
+    .execute(&conn)?;
+
+    Ok(Payload {
+        data: count,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Get the current user's email preferences
+pub fn get_preferences(user: CurrentUser, conn: Conn) -> Response<Vec<NotificationPreference>> {
+    let user = signed_in(user)?;
+    let Conn(conn) = conn;
+
+    let p = preferences(&conn, user.id)?;
+
+    Ok(Payload {
+        data: p,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Update the current user's email preferences. Kinds left out keep their
+/// current setting.
+pub fn update_preferences(
+    user: CurrentUser,
+    conn: Conn,
+    input: Vec<NotificationPreference>,
+) -> Response<Vec<NotificationPreference>> {
+    use schema::notification_preferences::dsl::*;
+
+    let user = signed_in(user)?;
+    let Conn(conn) = conn;
+
+    conn.transaction::<_, Error, _>(|| {
+        let now = chrono::Utc::now().naive_utc();
+        for p in &input {
+            diesel::insert_into(notification_preferences)
+                .values(&NotificationPreferenceRecord {
+                    user_id: user.id,
+                    kind: p.kind,
+                    email: p.email,
+                    updated: now,
+                })
+                .on_conflict((user_id, kind))
+                .do_update()
+                .set((email.eq(p.email), updated.eq(now)))
+                .execute(&conn)?;
+        }
+        Ok(())
+    })?;
+    let p = preferences(&conn, user.id)?;
+
+    Ok(Payload {
+        data: p,
+        success: true,
+        ..Default::default()
+    })
+}
@@ -0,0 +1,95 @@
+//
+// notifications/types.rs
+//
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Varchar;
+use schema::{notification_preferences, notifications};
+use std::io::Write;
+
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, AsExpression, FromSqlRow)]
+#[sql_type = "Varchar"]
+pub enum NotificationKind {
+    /// The deal moved to a new status
+    StatusChanged,
+    /// The other side made an offer or counteroffer
+    OfferReceived,
+    NoteAdded,
+    SellerResponded,
+}
+
+impl ToSql<Varchar, Pg> for NotificationKind {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        match *self {
+            NotificationKind::StatusChanged => out.write_all(b"status_changed")?,
+            NotificationKind::OfferReceived => out.write_all(b"offer_received")?,
+            NotificationKind::NoteAdded => out.write_all(b"note_added")?,
+            NotificationKind::SellerResponded => out.write_all(b"seller_responded")?,
+        }
+
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Pg> for NotificationKind {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match not_none!(bytes) {
+            b"status_changed" => Ok(NotificationKind::StatusChanged),
+            b"offer_received" => Ok(NotificationKind::OfferReceived),
+            b"note_added" => Ok(NotificationKind::NoteAdded),
+            b"seller_responded" => Ok(NotificationKind::SellerResponded),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Identifiable, Clone, Queryable, Debug)]
+#[table_name = "notifications"]
+pub struct Notification {
+    pub id: i32,
+    pub user_id: i32,
+    pub deal_id: Option<i32>,
+    pub kind: NotificationKind,
+    pub message: String,
+    pub read: Option<chrono::NaiveDateTime>,
+    pub created: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "notifications"]
+pub struct NewNotification {
+    pub user_id: i32,
+    pub deal_id: Option<i32>,
+    pub kind: NotificationKind,
+    pub message: String,
+    pub created: chrono::NaiveDateTime,
+}
+
+#[derive(Serialize)]
+pub struct NotificationList {
+    pub notifications: Vec<Notification>,
+    pub unread_count: i64,
+}
+
+#[derive(FromForm, Default)]
+pub struct NotificationsQuery {
+    /// Only unread notifications
+    pub unread: Option<bool>,
+}
+
+#[derive(Queryable, Insertable)]
+#[table_name = "notification_preferences"]
+pub struct NotificationPreferenceRecord {
+    pub user_id: i32,
+    pub kind: NotificationKind,
+    pub email: bool,
+    pub updated: chrono::NaiveDateTime,
+}
+
+/// Whether a kind of notification also goes out by email
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct NotificationPreference {
+    pub kind: NotificationKind,
+    pub email: bool,
+}
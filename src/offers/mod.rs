@@ -0,0 +1,402 @@
+//
+// offers/mod.rs
+//
+pub mod types;
+
+use self::types::*;
+use accounts::types::CurrentUser;
+use db::Conn;
+use deals::types::{Deal, DealRole, DealStatus};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use notifications;
+use notifications::types::NotificationKind;
+use result::{Error, Payload, Response};
+use serde_json::json;
+use webhooks;
+use webhooks::types::WebhookEvent;
+
+/// Most contingencies a single offer can carry
+const MAX_CONTINGENCIES: usize = 20;
+const MAX_CONTINGENCY_LENGTH: usize = 255;
+
+///
+/// Helpers
+///
+
+/// Side the user acts for on this deal. Admins who aren't on the deal
+/// have to say which side they're acting for.
+fn party_for(
+    deal: &Deal,
+    user: &CurrentUser,
+    requested: Option<OfferParty>,
+) -> Result<OfferParty, Error> {
+    match deal.role_of(user) {
+        Some(DealRole::Buyer) => Ok(OfferParty::Buyer),
+        Some(DealRole::Seller) => Ok(OfferParty::Seller),
+        Some(DealRole::Admin) => requested.ok_or_else(|| {
+            Error::from_custom_validation("party_required", "party", "Party is required")
+        }),
+        None => Err(Error::AccessDenied),
+    }
+}
+
+fn validate_input(input: &OfferInput) -> Result<(), Error> {
+    if input.amount <= 0 {
+        return Err(Error::from_custom_validation(
+            "invalid_amount",
+            "amount",
+            "Must be a positive amount",
+        ));
+    }
+    if input.contingencies.len() > MAX_CONTINGENCIES
+        || input
+            .contingencies
+            .iter()
+            .any(|c| c.trim().is_empty() || c.len() > MAX_CONTINGENCY_LENGTH)
+    {
+        return Err(Error::from_custom_validation(
+            "invalid_contingencies",
+            "contingencies",
+            "Contingencies must be between 1 and 255 characters, at most 20",
+        ));
+    }
+    if let Some(expires) = input.expires {
+        if expires <= chrono::Utc::now().naive_utc() {
+            return Err(Error::from_custom_validation(
+                "expired",
+                "expires",
+                "Expiry must be in the future",
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Lock a deal that is still open to offers
+fn lock_deal(conn: &PgConnection, deal_id: i32) -> Result<Deal, Error> {
+    use schema::deals::dsl::*;
+
+    let deal = deals.find(deal_id).for_update().first::<Deal>(conn)?;
+    match deal.status {
+        DealStatus::UnderContract => Err(Error::from_conflict(
+            "deal_under_contract",
+            "deal_id",
+            "Deal is already under contract",
+        )),
+        _ => Ok(deal),
+    }
+}
+
+/// Lock an open offer that the given party can respond to
+fn lock_open_offer(
+    conn: &PgConnection,
+    deal: &Deal,
+    offer_id: i32,
+    responder: OfferParty,
+) -> Result<Offer, Error> {
+    use schema::offers::dsl::*;
+
+    let offer = offers
+        .find(offer_id)
+        .filter(deal_id.eq(deal.id))
+        .for_update()
+        .first::<Offer>(conn)?;
+
+    if offer.status != OfferStatus::Open {
+        return Err(Error::from_conflict(
+            "offer_closed",
+            "offer_id",
+            "Offer is no longer open",
+        ));
+    }
+    if offer.party == responder {
+        return Err(Error::from_custom_validation(
+            "own_offer",
+            "offer_id",
+            "Cannot respond to your own offer",
+        ));
+    }
+    Ok(offer)
+}
+
+fn set_offer_status(
+    conn: &PgConnection,
+    offer: &Offer,
+    new_status: OfferStatus,
+) -> Result<Offer, Error> {
+    use schema::offers::dsl::*;
+
+    diesel::update(offer)
+        .set((
+            status.eq(new_status),
+            updated.eq(chrono::Utc::now().naive_utc()),
+        ))
+        .get_result::<Offer>(conn)
+        .map_err(|e| Error::from(e))
+}
+
+fn insert_offer(
+    conn: &PgConnection,
+    deal: &Deal,
+    counter_to: Option<i32>,
+    user: &CurrentUser,
+    party: OfferParty,
+    input: OfferInput,
+) -> Result<Offer, Error> {
+    use schema::offers;
+
+    let author_id = match *user {
+        CurrentUser::Admin(ref u) | CurrentUser::Authenticated(ref u) => Some(u.id),
+        CurrentUser::Anonymous => None,
+    };
+
+    diesel::insert_into(offers::table)
+        .values(&NewOffer {
+            deal_id: deal.id,
+            counter_to,
+            author_id,
+            party,
+            amount: input.amount,
+            contingencies: input
+                .contingencies
+                .into_iter()
+                .map(|c| c.trim().to_owned())
+                .collect(),
+            expires: input.expires,
+            status: OfferStatus::Open,
+            created: chrono::Utc::now().naive_utc(),
+            updated: chrono::Utc::now().naive_utc(),
+        })
+        .get_result::<Offer>(conn)
+        .map_err(|e| Error::from(e))
+}
+
+/// Let the other side know about a new offer
+fn notify_other_side(conn: &PgConnection, deal: &Deal, offer: &Offer) -> Result<(), Error> {
+    let recipient = match offer.party {
+        OfferParty::Buyer => deal.seller_id,
+        OfferParty::Seller => deal.buyer_id,
+    };
+    let message = match offer.counter_to {
+        Some(_) => format!("Counteroffer of ${} on {}", offer.amount, deal.title),
+        None => format!("New offer of ${} on {}", offer.amount, deal.title),
+    };
+
+    notifications::notify(
+        conn,
+        deal,
+        &recipient.into_iter().collect::<Vec<i32>>(),
+        NotificationKind::OfferReceived,
+        message,
+    )?;
+    Ok(())
+}
+
+/// Mark a deal's open offers past their expiry as expired.
+///
+/// The scheduler does this for every deal through `expire_offers`, but
+/// responses run it on the deal they lock first, so an offer that expired
+/// since the last run can't be accepted or countered.
+fn expire_deal_offers(conn: &PgConnection, deal: &Deal) -> Result<usize, Error> {
+    use schema::offers::dsl::*;
+
+    let now = chrono::Utc::now().naive_utc();
+    diesel::update(
+        offers
+            .filter(deal_id.eq(deal.id))
+            .filter(status.eq(OfferStatus::Open))
+            .filter(expires.lt(now)),
+    )
+    .set((status.eq(OfferStatus::Expired), updated.eq(now)))
+    .execute(conn)
+    .map_err(|e| Error::from(e))
+}
+
+/// Mark open offers past their expiry as expired. Run by the scheduler,
+/// see `expire_deal_offers` for the deal being responded to.
+pub fn expire_offers(conn: &PgConnection) -> Result<usize, Error> {
+    use schema::offers::dsl::*;
+
+    let now = chrono::Utc::now().naive_utc();
+    diesel::update(
+        offers
+            .filter(status.eq(OfferStatus::Open))
+            .filter(expires.lt(now)),
+    )
+    .set((status.eq(OfferStatus::Expired), updated.eq(now)))
+    .execute(conn)
+    .map_err(|e| Error::from(e))
+}
+
+///
+/// Public API
+///
+
+/// Get a deal's offers, newest first
+pub fn get_offers(deal_id: i32, user: CurrentUser, conn: Conn) -> Response<Vec<Offer>> {
+    use schema::{deals, offers};
+
+    let Conn(conn) = conn;
+
+    let deal = deals::table.find(deal_id).first::<Deal>(&conn)?;
+    if deal.role_of(&user).is_none() {
+        return Err(Error::AccessDenied);
+    }
+
+    // Offers past their expiry show as expired, even before the scheduler
+    // marks them
+    let now = chrono::Utc::now().naive_utc();
+    let o = offers::table
+        .filter(offers::deal_id.eq(deal.id))
+        .order_by(offers::created.desc())
+        .load::<Offer>(&conn)?
+        .into_iter()
+        .map(|mut offer| {
+            if offer.status == OfferStatus::Open && offer.expires.map_or(false, |e| e < now) {
+                offer.status = OfferStatus::Expired;
+            }
+            offer
+        })
+        .collect::<Vec<Offer>>();
+
+    Ok(Payload {
+        data: o,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Make an offer on a deal
+pub fn make_offer(
+    deal_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: OfferInput,
+) -> Response<Offer> {
+    let Conn(conn) = conn;
+
+    validate_input(&input)?;
+
+    let offer = conn.transaction::<_, Error, _>(|| {
+        let deal = lock_deal(&conn, deal_id)?;
+        let party = party_for(&deal, &user, input.party)?;
+        let offer = insert_offer(&conn, &deal, None, &user, party, input)?;
+        notify_other_side(&conn, &deal, &offer)?;
+        Ok(offer)
+    })?;
+
+    Ok(Payload {
+        data: offer,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Counter an offer from the other side
+///
+/// The countered offer is closed and the counteroffer takes its place.
+pub fn counter_offer(
+    deal_id: i32,
+    offer_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: OfferInput,
+) -> Response<Offer> {
+    let Conn(conn) = conn;
+
+    validate_input(&input)?;
+
+    let offer = conn.transaction::<_, Error, _>(|| {
+        let deal = lock_deal(&conn, deal_id)?;
+        expire_deal_offers(&conn, &deal)?;
+        let party = party_for(&deal, &user, input.party)?;
+        let countered = lock_open_offer(&conn, &deal, offer_id, party)?;
+
+        set_offer_status(&conn, &countered, OfferStatus::Countered)?;
+        let offer = insert_offer(&conn, &deal, Some(countered.id), &user, party, input)?;
+        notify_other_side(&conn, &deal, &offer)?;
+        Ok(offer)
+    })?;
+
+    Ok(Payload {
+        data: offer,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Accept an offer from the other side
+///
+/// The deal goes under contract and every other open offer on it is
+/// rejected.
+pub fn accept_offer(
+    deal_id: i32,
+    offer_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: RespondToOfferInput,
+) -> Response<Offer> {
+    use schema::offers;
+
+    let Conn(conn) = conn;
+
+    let offer = conn.transaction::<_, Error, _>(|| {
+        let deal = lock_deal(&conn, deal_id)?;
+        expire_deal_offers(&conn, &deal)?;
+        let party = party_for(&deal, &user, input.party)?;
+        let offer = lock_open_offer(&conn, &deal, offer_id, party)?;
+
+        let offer = set_offer_status(&conn, &offer, OfferStatus::Accepted)?;
+        diesel::update(
+            offers::table
+                .filter(offers::deal_id.eq(deal.id))
+                .filter(offers::status.eq(OfferStatus::Open)),
+        )
+        .set((
+            offers::status.eq(OfferStatus::Rejected),
+            offers::updated.eq(chrono::Utc::now().naive_utc()),
+        ))
+        .execute(&conn)?;
+        let deal = ::deals::set_status(&conn, &deal, DealStatus::UnderContract, user.id())?;
+        webhooks::trigger(
+            &conn,
+            WebhookEvent::OfferAccepted,
+            json!({ "deal": deal, "offer": offer }),
+        )?;
+
+        Ok(offer)
+    })?;
+
+    Ok(Payload {
+        data: offer,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Reject an offer from the other side
+pub fn reject_offer(
+    deal_id: i32,
+    offer_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: RespondToOfferInput,
+) -> Response<Offer> {
+    let Conn(conn) = conn;
+
+    let offer = conn.transaction::<_, Error, _>(|| {
+        let deal = lock_deal(&conn, deal_id)?;
+        expire_deal_offers(&conn, &deal)?;
+        let party = party_for(&deal, &user, input.party)?;
+        let offer = lock_open_offer(&conn, &deal, offer_id, party)?;
+
+        set_offer_status(&conn, &offer, OfferStatus::Rejected)
+    })?;
+
+    Ok(Payload {
+        data: offer,
+        success: true,
+        ..Default::default()
+    })
+}
@@ -0,0 +1,125 @@
+//
+// offers/types.rs
+//
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Varchar;
+use schema::offers;
+use std::io::Write;
+
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, AsExpression, FromSqlRow)]
+#[sql_type = "Varchar"]
+pub enum OfferParty {
+    Buyer,
+    Seller,
+}
+
+impl ToSql<Varchar, Pg> for OfferParty {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        match *self {
+            OfferParty::Buyer => out.write_all(b"buyer")?,
+            OfferParty::Seller => out.write_all(b"seller")?,
+        }
+
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Pg> for OfferParty {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match not_none!(bytes) {
+            b"buyer" => Ok(OfferParty::Buyer),
+            b"seller" => Ok(OfferParty::Seller),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, AsExpression, FromSqlRow)]
+#[sql_type = "Varchar"]
+pub enum OfferStatus {
+    Open,
+    Accepted,
+    Rejected,
+    Countered,
+    Expired,
+}
+
+impl ToSql<Varchar, Pg> for OfferStatus {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        match *self {
+            OfferStatus::Open => out.write_all(b"open")?,
+            OfferStatus::Accepted => out.write_all(b"accepted")?,
+            OfferStatus::Rejected => out.write_all(b"rejected")?,
+            OfferStatus::Countered => out.write_all(b"countered")?,
+            OfferStatus::Expired => out.write_all(b"expired")?,
+        }
+
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Pg> for OfferStatus {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match not_none!(bytes) {
+            b"open" => Ok(OfferStatus::Open),
+            b"accepted" => Ok(OfferStatus::Accepted),
+            b"rejected" => Ok(OfferStatus::Rejected),
+            b"countered" => Ok(OfferStatus::Countered),
+            b"expired" => Ok(OfferStatus::Expired),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
+
+#[derive(Serialize, Identifiable, Clone, Queryable, Debug)]
+#[table_name = "offers"]
+pub struct Offer {
+    pub id: i32,
+    pub deal_id: i32,
+    /// Offer this one counters
+    pub counter_to: Option<i32>,
+    pub author_id: Option<i32>,
+    pub party: OfferParty,
+    /// Amount in whole dollars
+    pub amount: i32,
+    pub contingencies: Vec<String>,
+    pub expires: Option<chrono::NaiveDateTime>,
+    pub status: OfferStatus,
+    pub created: chrono::NaiveDateTime,
+    pub updated: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "offers"]
+pub struct NewOffer {
+    pub deal_id: i32,
+    pub counter_to: Option<i32>,
+    pub author_id: Option<i32>,
+    pub party: OfferParty,
+    pub amount: i32,
+    pub contingencies: Vec<String>,
+    pub expires: Option<chrono::NaiveDateTime>,
+    pub status: OfferStatus,
+    pub created: chrono::NaiveDateTime,
+    pub updated: chrono::NaiveDateTime,
+}
+
+#[derive(Deserialize)]
+pub struct OfferInput {
+    /// Amount in whole dollars
+    pub amount: i32,
+    #[serde(default)]
+    pub contingencies: Vec<String>,
+    pub expires: Option<chrono::NaiveDateTime>,
+    /// Side to make the offer for. Only admins who aren't on the deal set
+    /// this; for everyone else it follows from their role.
+    pub party: Option<OfferParty>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct RespondToOfferInput {
+    /// Side responding, for admins who aren't on the deal
+    pub party: Option<OfferParty>,
+}
@@ -0,0 +1,235 @@
+//
+// pdf.rs
+//
+// Minimal PDF writer for printable output: pages of text in the standard
+// Helvetica fonts plus filled rectangles. Text is written in
+// WinAnsiEncoding, so it can use Latin-1 and common typographic marks.
+// Coordinates are in points from the bottom left corner of the page.
+//
+use std::io::Write;
+
+/// US Letter, in points
+pub const PAGE_WIDTH: f64 = 612.0;
+pub const PAGE_HEIGHT: f64 = 792.0;
+
+/// Average Helvetica glyph width as a fraction of the font size, used to
+/// estimate line lengths
+const AVERAGE_GLYPH_WIDTH: f64 = 0.5;
+
+#[derive(Clone, Copy)]
+pub enum Font {
+    Regular,
+    Bold,
+}
+
+impl Font {
+    fn resource_name(&self) -> &'static str {
+        match *self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Page {
+    content: String,
+}
+
+impl Page {
+    pub fn new() -> Self {
+        Page::default()
+    }
+
+    /// Draw a single line of text with its baseline at `y`
+    pub fn text(&mut self, x: f64, y: f64, size: f64, font: Font, text: &str) {
+        self.content.push_str(&format!(
+            "BT /{} {:.1} Tf {:.2} {:.2} Td ({}) Tj ET\n",
+            font.resource_name(),
+            size,
+            x,
+            y,
+            escape(text)
+        ));
+    }
+
+    /// Draw a filled black rectangle
+    pub fn rect(&mut self, x: f64, y: f64, width: f64, height: f64) {
+        self.content.push_str(&format!(
+            "{:.2} {:.2} {:.2} {:.2} re f\n",
+            x, y, width, height
+        ));
+    }
+}
+
+#[derive(Default)]
+pub struct Document {
+    pages: Vec<Page>,
+}
+
+impl Document {
+    pub fn new() -> Self {
+        Document::default()
+    }
+
+    pub fn add_page(&mut self, page: Page) {
+        self.pages.push(page);
+    }
+
+    pub fn append(&mut self, other: Document) {
+        self.pages.extend(other.pages);
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // Objects 1-4 are the catalog, page tree and fonts; each page then
+        // takes two objects, the page and its content stream.
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_owned(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                (0..self.pages.len())
+                    .map(|i| format!("{} 0 R", 5 + i * 2))
+                    .collect::<Vec<String>>()
+                    .join(" "),
+                self.pages.len()
+            ),
+            font_object("Helvetica"),
+            font_object("Helvetica-Bold"),
+        ];
+
+        for (i, page) in self.pages.iter().enumerate() {
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                6 + i * 2
+            ));
+            objects.push(format!(
+                "<< /Length {} >>\nstream\n{}endstream",
+                page.content.len(),
+                page.content
+            ));
+        }
+
+        let mut out = Vec::new();
+        let mut offsets = Vec::new();
+        let _ = write!(out, "%PDF-1.4\n");
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            let _ = write!(out, "{} 0 obj\n{}\nendobj\n", i + 1, object);
+        }
+
+        let xref_offset = out.len();
+        let _ = write!(out, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            let _ = write!(out, "{:010} 00000 n \n", offset);
+        }
+        let _ = write!(
+            out,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref_offset
+        );
+
+        out
+    }
+}
+
+/// Estimated width of a line of text in points
+pub fn text_width(text: &str, size: f64) -> f64 {
+    text.chars().count() as f64 * size * AVERAGE_GLYPH_WIDTH
+}
+
+/// Break text into lines no wider than `width`, keeping explicit newlines.
+/// Words too long for a line of their own, like long URLs, are broken
+/// wherever the line ends.
+pub fn wrap(text: &str, size: f64, width: f64) -> Vec<String> {
+    let max_chars = ((width / (size * AVERAGE_GLYPH_WIDTH)) as usize).max(1);
+    let mut lines = Vec::new();
+
+    for source_line in text.lines() {
+        let mut line = String::new();
+        for word in source_line.split_whitespace() {
+            let mut word = word.chars().collect::<Vec<char>>();
+            if !line.is_empty() && line.chars().count() + 1 + word.len() > max_chars {
+                lines.push(line);
+                line = String::new();
+            }
+            while word.len() > max_chars {
+                let rest = word.split_off(max_chars);
+                lines.push(word.into_iter().collect());
+                word = rest;
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.extend(word);
+        }
+        lines.push(line);
+    }
+
+    lines
+}
+
+fn font_object(name: &str) -> String {
+    format!(
+        "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+        name
+    )
+}
+
+/// Escape a string for a PDF text literal. Characters outside ASCII are
+/// written as octal escapes of their WinAnsiEncoding byte, and replaced when
+/// the encoding doesn't have them.
+fn escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '(' => "\\(".to_owned(),
+            ')' => "\\)".to_owned(),
+            '\\' => "\\\\".to_owned(),
+            c if c.is_ascii() && !c.is_ascii_control() => c.to_string(),
+            c => match win_ansi(c) {
+                Some(byte) => format!("\\{:03o}", byte),
+                None => "?".to_owned(),
+            },
+        })
+        .collect()
+}
+
+/// The WinAnsiEncoding byte for a character outside ASCII. That's Latin-1,
+/// plus the marks Windows-1252 puts in place of its control characters.
+fn win_ansi(c: char) -> Option<u8> {
+    let byte = match c {
+        '\u{a0}'..='\u{ff}' => c as u32 as u8,
+        '€' => 0x80,
+        '‚' => 0x82,
+        'ƒ' => 0x83,
+        '„' => 0x84,
+        '…' => 0x85,
+        '†' => 0x86,
+        '‡' => 0x87,
+        'ˆ' => 0x88,
+        '‰' => 0x89,
+        'Š' => 0x8a,
+        '‹' => 0x8b,
+        'Œ' => 0x8c,
+        'Ž' => 0x8e,
+        '‘' => 0x91,
+        '’' => 0x92,
+        '“' => 0x93,
+        '”' => 0x94,
+        '•' => 0x95,
+        '–' => 0x96,
+        '—' => 0x97,
+        '˜' => 0x98,
+        '™' => 0x99,
+        'š' => 0x9a,
+        '›' => 0x9b,
+        'œ' => 0x9c,
+        'ž' => 0x9e,
+        'Ÿ' => 0x9f,
+        _ => return None,
+    };
+    Some(byte)
+}
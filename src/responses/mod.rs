@@ -0,0 +1,236 @@
+//
+// responses/mod.rs
+//
+pub mod types;
+
+use self::types::*;
+use accounts::types::{CurrentUser, CurrentUser::*};
+use db::Conn;
+use deals;
+use deals::types::{Deal, DealStatus};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use notifications;
+use notifications::types::NotificationKind;
+use result::{Error, Payload, Response};
+use serde_json::json;
+use validator::Validate;
+use webhooks;
+use webhooks::types::WebhookEvent;
+
+/// Responses anyone with a deal's access code can submit in an hour
+const MAX_RESPONSES_PER_HOUR: i64 = 5;
+
+/// Who is recording a response
+#[derive(Clone, Copy)]
+enum Respondent {
+    /// The seller, with the deal's access code, and signed in if they have
+    /// an account
+    Seller(Option<i32>),
+    /// An admin entering it on the seller's behalf
+    Admin(i32),
+}
+
+///
+/// Helpers
+///
+
+/// Status a deal moves to when the seller responds, if it changes. Deals
+/// under contract stay there.
+fn next_status(current: DealStatus, response: ResponseType) -> Option<DealStatus> {
+    let next = match (current, response) {
+        (DealStatus::UnderContract, _) => DealStatus::UnderContract,
+        (_, ResponseType::Interested) | (_, ResponseType::CallMe) => DealStatus::SellerInterested,
+        (_, ResponseType::NotInterested) => DealStatus::SellerDeclined,
+    };
+    match next == current {
+        true => None,
+        false => Some(next),
+    }
+}
+
+/// Once a deal is claimed only its seller can respond with the access code,
+/// and nobody can respond with it too often
+fn check_seller(conn: &PgConnection, deal: &Deal, user_id: Option<i32>) -> Result<(), Error> {
+    use schema::seller_responses;
+
+    match (deal.seller_id, user_id) {
+        (Some(_), None) => return Err(Error::ApiKeyError),
+        (Some(seller), Some(user)) if seller != user => return Err(Error::AccessDenied),
+        _ => {}
+    }
+
+    let since = chrono::Utc::now().naive_utc() - chrono::Duration::hours(1);
+    let recent = seller_responses::table
+        .filter(seller_responses::deal_id.eq(deal.id))
+        .filter(seller_responses::entered_by.is_null())
+        .filter(seller_responses::created.gt(since))
+        .count()
+        .get_result::<i64>(conn)?;
+    match recent >= MAX_RESPONSES_PER_HOUR {
+        true => Err(Error::RateLimited),
+        false => Ok(()),
+    }
+}
+
+fn validate_input(input: &SellerResponseInput) -> Result<(), Error> {
+    input.validate()?;
+
+    if let Some(price) = input.asking_price {
+        if price <= 0 {
+            return Err(Error::from_custom_validation(
+                "invalid_price",
+                "asking_price",
+                "Must be a positive amount",
+            ));
+        }
+    }
+
+    match input.contact_method {
+        Some(ContactMethod::Mail) | None => Ok(()),
+        Some(_) if input.contact.as_ref().map_or(true, |c| c.trim().is_empty()) => {
+            Err(Error::from_custom_validation(
+                "contact_required",
+                "contact",
+                "Phone number or email is required",
+            ))
+        }
+        Some(_) => Ok(()),
+    }
+}
+
+/// Save a response and advance the deal's status with it
+fn record(
+    conn: &PgConnection,
+    deal_id: i32,
+    respondent: Respondent,
+    input: SellerResponseInput,
+) -> Result<SellerResponse, Error> {
+    use schema::{deals, seller_responses};
+
+    let (entered_by, actor) = match respondent {
+        Respondent::Seller(user_id) => (None, user_id),
+        Respondent::Admin(user_id) => (Some(user_id), Some(user_id)),
+    };
+
+    conn.transaction::<_, Error, _>(|| {
+        let deal = deals::table
+            .find(deal_id)
+            .for_update()
+            .first::<Deal>(conn)?;
+        if let Respondent::Seller(user_id) = respondent {
+            check_seller(conn, &deal, user_id)?;
+        }
+
+        let response = diesel::insert_into(seller_responses::table)
+            .values(&NewSellerResponse {
+                deal_id: deal.id,
+                response: input.response,
+                asking_price: input.asking_price,
+                contact_method: input.contact_method,
+                contact: input.contact.map(|c| c.trim().to_owned()),
+                message: input.message,
+                entered_by,
+                created: chrono::Utc::now().naive_utc(),
+            })
+            .get_result::<SellerResponse>(conn)?;
+
+        let updated_deal = match next_status(deal.status, response.response) {
+            Some(status) => ::deals::set_status(conn, &deal, status, actor)?,
+            None => deal.clone(),
+        };
+
+        let answer = match response.response {
+            ResponseType::Interested => "is interested",
+            ResponseType::NotInterested => "is not interested",
+            ResponseType::CallMe => "asked for a call",
+        };
+        notifications::notify(
+            conn,
+            &deal,
+            &deal.buyer_id.into_iter().collect::<Vec<i32>>(),
+            NotificationKind::SellerResponded,
+            format!("The seller of {} {}", deal.title, answer),
+        )?;
+        webhooks::trigger(
+            conn,
+            WebhookEvent::SellerResponded,
+            json!({ "deal": updated_deal, "response": response }),
+        )?;
+
+        Ok(response)
+    })
+}
+
+///
+/// Public API
+///
+
+/// Submit a response as the seller, using the deal's access code
+///
+/// This is public, like the rest of the access code flow, until the deal is
+/// claimed. After that only the seller who claimed it can respond.
+pub fn submit_response(
+    code: String,
+    user: Option<CurrentUser>,
+    conn: Conn,
+    input: SellerResponseInput,
+) -> Response<SellerResponse> {
+    let Conn(conn) = conn;
+
+    validate_input(&input)?;
+    let deal = deals::find_by_access_code(&conn, &code)?;
+    let user_id = user.and_then(|u| u.id());
+    let r = record(&conn, deal.id, Respondent::Seller(user_id), input)?;
+
+    Ok(Payload {
+        data: r,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Enter a response on the seller's behalf, e.g. after a phone call
+pub fn create_response(
+    deal_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: SellerResponseInput,
+) -> Response<SellerResponse> {
+    let user = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    validate_input(&input)?;
+    let r = record(&conn, deal_id, Respondent::Admin(user.id), input)?;
+
+    Ok(Payload {
+        data: r,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Get a deal's responses, newest first
+pub fn get_responses(deal_id: i32, user: CurrentUser, conn: Conn) -> Response<Vec<SellerResponse>> {
+    use schema::seller_responses;
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let r = seller_responses::table
+        .filter(seller_responses::deal_id.eq(deal_id))
+        .order_by(seller_responses::created.desc())
+        .load::<SellerResponse>(&conn)?;
+
+    Ok(Payload {
+        data: r,
+        success: true,
+        ..Default::default()
+    })
+}
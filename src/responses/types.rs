@@ -0,0 +1,116 @@
+//
+// responses/types.rs
+//
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Varchar;
+use schema::seller_responses;
+use std::io::Write;
+use validator::Validate;
+
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, AsExpression, FromSqlRow)]
+#[sql_type = "Varchar"]
+pub enum ResponseType {
+    Interested,
+    NotInterested,
+    CallMe,
+}
+
+impl ToSql<Varchar, Pg> for ResponseType {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        match *self {
+            ResponseType::Interested => out.write_all(b"interested")?,
+            ResponseType::NotInterested => out.write_all(b"not_interested")?,
+            ResponseType::CallMe => out.write_all(b"call_me")?,
+        }
+
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Pg> for ResponseType {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match not_none!(bytes) {
+            b"interested" => Ok(ResponseType::Interested),
+            b"not_interested" => Ok(ResponseType::NotInterested),
+            b"call_me" => Ok(ResponseType::CallMe),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, AsExpression, FromSqlRow)]
+#[sql_type = "Varchar"]
+pub enum ContactMethod {
+    Phone,
+    Text,
+    Email,
+    Mail,
+}
+
+impl ToSql<Varchar, Pg> for ContactMethod {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        match *self {
+            ContactMethod::Phone => out.write_all(b"phone")?,
+            ContactMethod::Text => out.write_all(b"text")?,
+            ContactMethod::Email => out.write_all(b"email")?,
+            ContactMethod::Mail => out.write_all(b"mail")?,
+        }
+
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Pg> for ContactMethod {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match not_none!(bytes) {
+            b"phone" => Ok(ContactMethod::Phone),
+            b"text" => Ok(ContactMethod::Text),
+            b"email" => Ok(ContactMethod::Email),
+            b"mail" => Ok(ContactMethod::Mail),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
+
+#[derive(Serialize, Identifiable, Clone, Queryable, Debug)]
+#[table_name = "seller_responses"]
+pub struct SellerResponse {
+    pub id: i32,
+    pub deal_id: i32,
+    pub response: ResponseType,
+    pub asking_price: Option<i32>,
+    pub contact_method: Option<ContactMethod>,
+    pub contact: Option<String>,
+    pub message: Option<String>,
+    /// The admin who entered the response, `None` when the seller did
+    pub entered_by: Option<i32>,
+    pub created: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "seller_responses"]
+pub struct NewSellerResponse {
+    pub deal_id: i32,
+    pub response: ResponseType,
+    pub asking_price: Option<i32>,
+    pub contact_method: Option<ContactMethod>,
+    pub contact: Option<String>,
+    pub message: Option<String>,
+    pub entered_by: Option<i32>,
+    pub created: chrono::NaiveDateTime,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct SellerResponseInput {
+    pub response: ResponseType,
+    /// Asking price in whole dollars
+    pub asking_price: Option<i32>,
+    pub contact_method: Option<ContactMethod>,
+    /// Phone number or email for the contact method
+    #[validate(length(min = "1", max = "255", message = "Cannot be blank"))]
+    pub contact: Option<String>,
+    #[validate(length(max = "5000", message = "Message is too long"))]
+    pub message: Option<String>,
+}
@@ -2,6 +2,8 @@
 // error.rs
 //
 use self::Error::*;
+use diesel::result::DatabaseErrorKind::{ForeignKeyViolation, UniqueViolation};
+use diesel::result::Error::{DatabaseError, NotFound};
 use std::borrow::Cow;
 use validator::{ValidationError as ExtValidationError, ValidationErrors};
 
@@ -14,6 +16,8 @@ pub struct PageInfo {
 #[derive(Serialize)]
 pub struct ValidationError {
     pub field: String,
+    /// Why the field was rejected, like `length` or `email_taken`
+    pub code: String,
     pub message: String,
 }
 
@@ -21,6 +25,9 @@ pub struct ValidationError {
 pub struct Payload<T> {
     pub data: T,
     pub success: bool,
+    /// Names the kind of error, and never changes once released, so clients
+    /// can switch on it rather than on `error_message`
+    pub error_code: Option<String>,
     pub error_message: Option<String>,
     pub validation_errors: Option<Vec<ValidationError>>,
     pub page_info: Option<PageInfo>,
@@ -34,8 +41,15 @@ pub enum Error {
     BcryptError(bcrypt::BcryptError),
     DieselError(diesel::result::Error),
     InvalidInput(validator::ValidationErrors),
+    /// Input that is valid, but clashes with what is already stored
+    Conflict(validator::ValidationErrors),
     JsonError(serde_json::Error),
+    GeocodingError(String),
+    StorageError(String),
+    MailError(String),
     ServiceUnavailable,
+    /// Too many attempts at something in too short a time
+    RateLimited,
     ApiKeyError,
     AccessDenied,
 }
@@ -66,10 +80,49 @@ impl From<validator::ValidationErrors> for Error {
 
 impl Error {
     pub fn from_custom_validation(code: &'static str, field: &'static str, message: &str) -> Self {
-        let mut errors = ValidationErrors::new();
-        let mut error = ExtValidationError::new(code.clone());
-        error.message = Some(Cow::from(message.to_owned()));
-        errors.add(field.clone(), error);
-        Error::InvalidInput(errors)
+        Error::InvalidInput(field_error(code, field, message))
     }
+
+    pub fn from_conflict(code: &'static str, field: &'static str, message: &str) -> Self {
+        Error::Conflict(field_error(code, field, message))
+    }
+
+    /// The stable `error_code` for the kind of error
+    pub fn code(&self) -> &'static str {
+        self.describe().0
+    }
+
+    /// A message for the kind of error, safe to show to anyone
+    pub fn message(&self) -> &'static str {
+        self.describe().1
+    }
+
+    fn describe(&self) -> (&'static str, &'static str) {
+        match *self {
+            AccessDenied => ("access_denied", "Access denied"),
+            ApiKeyError => ("unauthorized", "A valid API key is required"),
+            DieselError(NotFound) => ("not_found", "Not found"),
+            DieselError(DatabaseError(UniqueViolation, _))
+            | DieselError(DatabaseError(ForeignKeyViolation, _)) => {
+                ("conflict", "Conflicts with existing data")
+            }
+            DieselError(_) => ("database_error", "Database error"),
+            InvalidInput(_) => ("validation_failed", "Invalid input"),
+            Conflict(_) => ("conflict", "Conflicts with existing data"),
+            BcryptError(_) | JsonError(_) => ("internal_error", "Internal error"),
+            GeocodingError(_) => ("geocoding_failed", "Geocoding failed"),
+            StorageError(_) => ("storage_failed", "File storage failed"),
+            MailError(_) => ("mail_failed", "Sending mail failed"),
+            ServiceUnavailable => ("service_unavailable", "Service unavailable"),
+            RateLimited => ("rate_limited", "Too many requests, try again later"),
+        }
+    }
+}
+
+fn field_error(code: &'static str, field: &'static str, message: &str) -> ValidationErrors {
+    let mut errors = ValidationErrors::new();
+    let mut error = ExtValidationError::new(code);
+    error.message = Some(Cow::from(message.to_owned()));
+    errors.add(field, error);
+    errors
 }
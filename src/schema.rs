@@ -1,3 +1,92 @@
+table! {
+    checklist_template_items (id) {
+        id -> Int4,
+        template_id -> Int4,
+        position -> Int4,
+        title -> Varchar,
+        due_in_days -> Nullable<Int4>,
+        assignee_id -> Nullable<Int4>,
+    }
+}
+
+table! {
+    checklist_templates (id) {
+        id -> Int4,
+        name -> Varchar,
+        status -> Varchar,
+        created_by -> Nullable<Int4>,
+        created -> Timestamp,
+        updated -> Timestamp,
+    }
+}
+
+table! {
+    daily_stats (day) {
+        day -> Date,
+        deals_created -> Int4,
+        mailers_sent -> Int4,
+        mailer_scans -> Int4,
+        seller_responses -> Int4,
+        offers_accepted -> Int4,
+        created -> Timestamp,
+    }
+}
+
+table! {
+    deal_documents (id) {
+        id -> Int4,
+        deal_id -> Int4,
+        uploaded_by -> Nullable<Int4>,
+        filename -> Varchar,
+        content_type -> Varchar,
+        size_bytes -> Int4,
+        sha256 -> Varchar,
+        storage_key -> Varchar,
+        created -> Timestamp,
+    }
+}
+
+table! {
+    deal_note_revisions (id) {
+        id -> Int4,
+        note_id -> Int4,
+        body -> Text,
+        visibility -> Varchar,
+        edited_by -> Nullable<Int4>,
+        created -> Timestamp,
+    }
+}
+
+table! {
+    deal_notes (id) {
+        id -> Int4,
+        deal_id -> Int4,
+        parent_id -> Nullable<Int4>,
+        author_id -> Nullable<Int4>,
+        body -> Text,
+        visibility -> Varchar,
+        created -> Timestamp,
+        updated -> Timestamp,
+        deleted -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    deal_tasks (id) {
+        id -> Int4,
+        deal_id -> Int4,
+        template_item_id -> Nullable<Int4>,
+        title -> Varchar,
+        assignee_id -> Nullable<Int4>,
+        due_date -> Nullable<Date>,
+        completed -> Nullable<Timestamp>,
+        completed_by -> Nullable<Int4>,
+        created_by -> Nullable<Int4>,
+        created -> Timestamp,
+        updated -> Timestamp,
+    }
+}
+
 table! {
     deals (id) {
         id -> Int4,
@@ -9,6 +98,25 @@ table! {
         created -> Timestamp,
         updated -> Timestamp,
         title -> Varchar,
+        access_code_rotated -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    emails (id) {
+        id -> Int4,
+        user_id -> Nullable<Int4>,
+        to_address -> Varchar,
+        template -> Varchar,
+        subject -> Varchar,
+        html_body -> Text,
+        text_body -> Text,
+        status -> Varchar,
+        error -> Nullable<Text>,
+        sent -> Nullable<Timestamp>,
+        created -> Timestamp,
+        updated -> Timestamp,
+        attempts -> Int4,
     }
 }
 
@@ -19,6 +127,126 @@ table! {
         created -> Timestamp,
         updated -> Timestamp,
         google_address -> Nullable<Jsonb>,
+        street_number -> Nullable<Varchar>,
+        route -> Nullable<Varchar>,
+        city -> Nullable<Varchar>,
+        state -> Nullable<Varchar>,
+        postal_code -> Nullable<Varchar>,
+        country -> Nullable<Varchar>,
+        place_id -> Nullable<Varchar>,
+        canonical_address -> Nullable<Varchar>,
+        lat -> Nullable<Float8>,
+        lng -> Nullable<Float8>,
+    }
+}
+
+table! {
+    jobs (id) {
+        id -> Int4,
+        kind -> Varchar,
+        payload -> Jsonb,
+        status -> Varchar,
+        attempts -> Int4,
+        max_attempts -> Int4,
+        run_at -> Timestamp,
+        locked_at -> Nullable<Timestamp>,
+        last_error -> Nullable<Text>,
+        created -> Timestamp,
+        updated -> Timestamp,
+    }
+}
+
+table! {
+    mailer_batches (id) {
+        id -> Int4,
+        created_by -> Nullable<Int4>,
+        deal_count -> Int4,
+        skipped_count -> Int4,
+        created -> Timestamp,
+        pdf -> Nullable<Bytea>,
+        manifest -> Text,
+        rendered -> Nullable<Timestamp>,
+        incomplete -> Jsonb,
+    }
+}
+
+table! {
+    mailer_scans (id) {
+        id -> Int4,
+        deal_id -> Int4,
+        mailer_id -> Nullable<Int4>,
+        created -> Timestamp,
+    }
+}
+
+table! {
+    mailer_template_versions (id) {
+        id -> Int4,
+        template_id -> Int4,
+        version -> Int4,
+        body -> Text,
+        created_by -> Nullable<Int4>,
+        created -> Timestamp,
+    }
+}
+
+table! {
+    mailer_templates (id) {
+        id -> Int4,
+        name -> Varchar,
+        is_default -> Bool,
+        archived -> Bool,
+        created_by -> Nullable<Int4>,
+        created -> Timestamp,
+        updated -> Timestamp,
+    }
+}
+
+table! {
+    mailers (id) {
+        id -> Int4,
+        deal_id -> Int4,
+        sent_by -> Nullable<Int4>,
+        created -> Timestamp,
+        batch_id -> Nullable<Int4>,
+        template_version_id -> Nullable<Int4>,
+    }
+}
+
+table! {
+    notification_preferences (user_id, kind) {
+        user_id -> Int4,
+        kind -> Varchar,
+        email -> Bool,
+        updated -> Timestamp,
+    }
+}
+
+table! {
+    notifications (id) {
+        id -> Int4,
+        user_id -> Int4,
+        deal_id -> Nullable<Int4>,
+        kind -> Varchar,
+        message -> Text,
+        read -> Nullable<Timestamp>,
+        created -> Timestamp,
+    }
+}
+
+table! {
+    offers (id) {
+        id -> Int4,
+        deal_id -> Int4,
+        counter_to -> Nullable<Int4>,
+        author_id -> Nullable<Int4>,
+        party -> Varchar,
+        amount -> Int4,
+        contingencies -> Array<Text>,
+        expires -> Nullable<Timestamp>,
+        status -> Varchar,
+        created -> Timestamp,
+        updated -> Timestamp,
     }
 }
 
@@ -32,6 +260,29 @@ table! {
     }
 }
 
+table! {
+    scheduled_runs (name) {
+        name -> Varchar,
+        last_run -> Timestamp,
+        last_error -> Nullable<Text>,
+        updated -> Timestamp,
+    }
+}
+
+table! {
+    seller_responses (id) {
+        id -> Int4,
+        deal_id -> Int4,
+        response -> Varchar,
+        asking_price -> Nullable<Int4>,
+        contact_method -> Nullable<Varchar>,
+        contact -> Nullable<Varchar>,
+        message -> Nullable<Text>,
+        entered_by -> Nullable<Int4>,
+        created -> Timestamp,
+    }
+}
+
 table! {
     sessions (id) {
         id -> Int4,
@@ -53,14 +304,96 @@ table! {
     }
 }
 
+table! {
+    webhook_deliveries (id) {
+        id -> Int4,
+        webhook_id -> Int4,
+        event_type -> Varchar,
+        payload -> Jsonb,
+        status -> Varchar,
+        attempts -> Int4,
+        response_status -> Nullable<Int4>,
+        response_body -> Nullable<Text>,
+        error -> Nullable<Text>,
+        delivered -> Nullable<Timestamp>,
+        created -> Timestamp,
+        updated -> Timestamp,
+    }
+}
+
+table! {
+    webhooks (id) {
+        id -> Int4,
+        url -> Varchar,
+        event_types -> Array<Text>,
+        secret -> Varchar,
+        active -> Bool,
+        created_by -> Nullable<Int4>,
+        created -> Timestamp,
+        updated -> Timestamp,
+    }
+}
+
+joinable!(checklist_template_items -> checklist_templates (template_id));
+joinable!(checklist_template_items -> users (assignee_id));
+joinable!(checklist_templates -> users (created_by));
+joinable!(deal_documents -> deals (deal_id));
+joinable!(deal_documents -> users (uploaded_by));
+joinable!(deal_note_revisions -> deal_notes (note_id));
+joinable!(deal_note_revisions -> users (edited_by));
+joinable!(deal_notes -> deals (deal_id));
+joinable!(deal_notes -> users (author_id));
+joinable!(deal_tasks -> checklist_template_items (template_item_id));
+joinable!(deal_tasks -> deals (deal_id));
 joinable!(deals -> houses (house_id));
+joinable!(emails -> users (user_id));
+joinable!(mailer_batches -> users (created_by));
+joinable!(mailer_scans -> deals (deal_id));
+joinable!(mailer_scans -> mailers (mailer_id));
+joinable!(mailer_template_versions -> mailer_templates (template_id));
+joinable!(mailer_template_versions -> users (created_by));
+joinable!(mailer_templates -> users (created_by));
+joinable!(mailers -> deals (deal_id));
+joinable!(mailers -> mailer_batches (batch_id));
+joinable!(mailers -> mailer_template_versions (template_version_id));
+joinable!(mailers -> users (sent_by));
+joinable!(notification_preferences -> users (user_id));
+joinable!(notifications -> deals (deal_id));
+joinable!(notifications -> users (user_id));
+joinable!(offers -> deals (deal_id));
+joinable!(offers -> users (author_id));
 joinable!(profiles -> users (uid));
+joinable!(seller_responses -> deals (deal_id));
+joinable!(seller_responses -> users (entered_by));
 joinable!(sessions -> users (uid));
+joinable!(webhook_deliveries -> webhooks (webhook_id));
+joinable!(webhooks -> users (created_by));
 
 allow_tables_to_appear_in_same_query!(
+    checklist_template_items,
+    checklist_templates,
+    daily_stats,
+    deal_documents,
+    deal_note_revisions,
+    deal_notes,
+    deal_tasks,
     deals,
+    emails,
     houses,
+    jobs,
+    mailer_batches,
+    mailer_scans,
+    mailer_template_versions,
+    mailer_templates,
+    mailers,
+    notification_preferences,
+    notifications,
+    offers,
     profiles,
+    scheduled_runs,
+    seller_responses,
     sessions,
     users,
+    webhook_deliveries,
+    webhooks,
 );
@@ -0,0 +1,62 @@
+//
+// storage/local.rs
+//
+use result::Error;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use storage::Storage;
+
+/// Storage in a directory on the local filesystem
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        LocalStorage { root: root.into() }
+    }
+
+    /// Path for a key, refusing keys that would escape the root
+    fn path(&self, key: &str) -> Result<PathBuf, Error> {
+        let relative = Path::new(key);
+        if relative.components().any(|c| match c {
+            Component::Normal(_) => false,
+            _ => true,
+        }) {
+            return Err(Error::StorageError(format!("Invalid key {}", key)));
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+fn storage_error(key: &str, e: io::Error) -> Error {
+    Error::StorageError(format!("{}: {}", key, e))
+}
+
+impl Storage for LocalStorage {
+    fn put(&self, key: &str, _content_type: &str, bytes: &[u8]) -> Result<(), Error> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| storage_error(key, e))?;
+        }
+
+        // Write beside the final path and rename, so a reader never sees a
+        // partial file
+        let partial = path.with_extension("partial");
+        fs::write(&partial, bytes).map_err(|e| storage_error(key, e))?;
+        fs::rename(&partial, &path).map_err(|e| storage_error(key, e))
+    }
+
+    fn get(&self, key: &str) -> Result<Vec<u8>, Error> {
+        fs::read(self.path(key)?).map_err(|e| storage_error(key, e))
+    }
+
+    fn delete(&self, key: &str) -> Result<(), Error> {
+        match fs::remove_file(self.path(key)?) {
+            Ok(()) => Ok(()),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(storage_error(key, e)),
+        }
+    }
+}
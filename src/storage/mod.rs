@@ -0,0 +1,56 @@
+//
+// storage/mod.rs
+//
+pub mod local;
+pub mod s3;
+
+use self::local::LocalStorage;
+use self::s3::S3Storage;
+use dotenv::dotenv;
+use result::Error;
+use std::env;
+
+/// Stores uploaded files by key
+pub trait Storage: Send + Sync {
+    fn put(&self, key: &str, content_type: &str, bytes: &[u8]) -> Result<(), Error>;
+
+    fn get(&self, key: &str) -> Result<Vec<u8>, Error>;
+
+    /// Remove a file. Removing a missing file is not an error.
+    fn delete(&self, key: &str) -> Result<(), Error>;
+}
+
+/// State container for file storage
+pub struct FileStorage(pub Box<dyn Storage>);
+
+/// Build the storage selected by `STORAGE`.
+///
+/// `local` keeps files under `STORAGE_PATH`, `uploads` by default. `s3`
+/// needs `S3_ENDPOINT`, `S3_BUCKET`, `S3_ACCESS_KEY` and `S3_SECRET_KEY`,
+/// plus `S3_REGION` when it isn't `us-east-1`. Any S3-compatible service
+/// works, such as MinIO for local testing. Without `STORAGE`, local
+/// storage is used.
+pub fn from_env() -> Result<Box<dyn Storage>, String> {
+    dotenv().ok();
+
+    let kind = env::var("STORAGE").unwrap_or_else(|_| "local".to_owned());
+
+    match kind.as_str() {
+        "local" => Ok(Box::new(LocalStorage::new(
+            env::var("STORAGE_PATH").unwrap_or_else(|_| "uploads".to_owned()),
+        ))),
+        "s3" => {
+            let var = |name: &str| {
+                env::var(name).map_err(|_| format!("{} must be set to use s3 storage", name))
+            };
+            Ok(Box::new(S3Storage::new(
+                var("S3_ENDPOINT")?,
+                var("S3_BUCKET")?,
+                env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_owned()),
+                var("S3_ACCESS_KEY")?,
+                var("S3_SECRET_KEY")?,
+            )))
+        }
+        other => Err(format!("Unknown storage {}", other)),
+    }
+}
@@ -0,0 +1,172 @@
+//
+// storage/s3.rs
+//
+// S3-compatible storage. Requests use path-style URLs (`endpoint/bucket/key`),
+// which MinIO and other stand-ins support, and are signed with AWS
+// signature version 4.
+//
+use hmac::{Hmac, Mac};
+use reqwest::{Method, StatusCode, Url};
+use result::Error;
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use storage::Storage;
+
+const SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+
+/// Storage in an S3 bucket
+pub struct S3Storage {
+    endpoint: String,
+    bucket: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+    client: reqwest::Client,
+}
+
+impl S3Storage {
+    pub fn new(
+        endpoint: String,
+        bucket: String,
+        region: String,
+        access_key: String,
+        secret_key: String,
+    ) -> Self {
+        S3Storage {
+            endpoint: endpoint.trim_end_matches('/').to_owned(),
+            bucket,
+            region,
+            access_key,
+            secret_key,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Send a signed request for a key
+    fn send(
+        &self,
+        method: Method,
+        key: &str,
+        content_type: Option<&str>,
+        body: Vec<u8>,
+    ) -> Result<reqwest::Response, Error> {
+        let url = Url::parse(&format!(
+            "{}/{}/{}",
+            self.endpoint,
+            self.bucket,
+            encode_key(key)
+        ))
+        .map_err(|e| Error::StorageError(e.to_string()))?;
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or(""), port),
+            None => url.host_str().unwrap_or("").to_owned(),
+        };
+
+        let now = chrono::Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = hex::encode(Sha256::digest(&body));
+
+        let canonical_request = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method.as_str(),
+            url.path(),
+            host,
+            payload_hash,
+            amz_date,
+            SIGNED_HEADERS,
+            payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let signing_key = [date.as_str(), self.region.as_str(), "s3", "aws4_request"]
+            .iter()
+            .fold(
+                format!("AWS4{}", self.secret_key).into_bytes(),
+                |key, part| hmac(&key, part.as_bytes()),
+            );
+        let signature = hex::encode(hmac(&signing_key, string_to_sign.as_bytes()));
+
+        let mut req = self
+            .client
+            .request(method, url)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", payload_hash)
+            .header(
+                "Authorization",
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                    self.access_key, scope, SIGNED_HEADERS, signature
+                ),
+            );
+        if let Some(content_type) = content_type {
+            req = req.header("Content-Type", content_type);
+        }
+
+        req.body(body)
+            .send()
+            .map_err(|e| Error::StorageError(e.to_string()))
+    }
+}
+
+impl Storage for S3Storage {
+    fn put(&self, key: &str, content_type: &str, bytes: &[u8]) -> Result<(), Error> {
+        let res = self.send(Method::PUT, key, Some(content_type), bytes.to_vec())?;
+        check_status(key, res).map(|_| ())
+    }
+
+    fn get(&self, key: &str) -> Result<Vec<u8>, Error> {
+        let res = self.send(Method::GET, key, None, Vec::new())?;
+        let mut res = check_status(key, res)?;
+
+        let mut bytes = Vec::new();
+        res.read_to_end(&mut bytes)
+            .map_err(|e| Error::StorageError(e.to_string()))?;
+        Ok(bytes)
+    }
+
+    fn delete(&self, key: &str) -> Result<(), Error> {
+        let res = self.send(Method::DELETE, key, None, Vec::new())?;
+        match res.status() {
+            StatusCode::NOT_FOUND => Ok(()),
+            _ => check_status(key, res).map(|_| ()),
+        }
+    }
+}
+
+fn check_status(key: &str, mut res: reqwest::Response) -> Result<reqwest::Response, Error> {
+    if res.status().is_success() {
+        Ok(res)
+    } else {
+        Err(Error::StorageError(format!(
+            "{}: {} {}",
+            key,
+            res.status(),
+            res.text().unwrap_or_default()
+        )))
+    }
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_varkey(key).expect("HMAC takes keys of any length");
+    mac.input(data);
+    mac.result().code().to_vec()
+}
+
+/// Percent-encode a key for the URL path, keeping the `/` between segments
+fn encode_key(key: &str) -> String {
+    key.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
@@ -0,0 +1,300 @@
+//
+// tasks/checklists.rs
+//
+// Checklist templates: sets of tasks added to a deal when it enters a
+// status
+//
+use accounts::types::{CurrentUser, CurrentUser::*};
+use db::Conn;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use result::{Error, Payload, Response};
+use std::collections::HashMap;
+use tasks::types::*;
+use validator::Validate;
+
+/// Most items a single checklist can have
+const MAX_ITEMS: usize = 100;
+
+///
+/// Helpers
+///
+
+fn validate_input(input: &ChecklistInput) -> Result<(), Error> {
+    input.validate()?;
+
+    if input.items.len() > MAX_ITEMS {
+        return Err(Error::from_custom_validation(
+            "too_many_items",
+            "items",
+            "Checklists can have at most 100 items",
+        ));
+    }
+    let mut ids = input
+        .items
+        .iter()
+        .filter_map(|i| i.id)
+        .collect::<Vec<i32>>();
+    ids.sort();
+    if ids.windows(2).any(|w| w[0] == w[1]) {
+        return Err(Error::from_custom_validation(
+            "duplicate_item",
+            "items",
+            "Each item can only be listed once",
+        ));
+    }
+    for item in &input.items {
+        item.validate()?;
+        if item.due_in_days.map_or(false, |d| d < 0) {
+            return Err(Error::from_custom_validation(
+                "invalid_due_in_days",
+                "items",
+                "Due days cannot be negative",
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Make a checklist's items the input's, in order. Items given with an id
+/// are updated in place rather than replaced, since the tasks applied from
+/// them point at that id, and it's what keeps a deal from getting the same
+/// task twice.
+fn save_items(
+    conn: &PgConnection,
+    template: &ChecklistTemplate,
+    input: &ChecklistInput,
+) -> Result<Vec<ChecklistItem>, Error> {
+    use schema::checklist_template_items::dsl::*;
+
+    let existing = checklist_template_items
+        .filter(template_id.eq(template.id))
+        .select(id)
+        .load::<i32>(conn)?;
+    let kept = input
+        .items
+        .iter()
+        .filter_map(|i| i.id)
+        .collect::<Vec<i32>>();
+    if let Some(unknown) = kept.iter().find(|k| !existing.contains(k)) {
+        return Err(Error::from_custom_validation(
+            "unknown_item",
+            "items",
+            &format!("Item {} isn't on this checklist", unknown),
+        ));
+    }
+
+    diesel::delete(
+        checklist_template_items
+            .filter(template_id.eq(template.id))
+            .filter(id.ne_all(&kept)),
+    )
+    .execute(conn)?;
+
+    input
+        .items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let item_title = item.title.trim().to_owned();
+            let saved = match item.id {
+                Some(item_id) => diesel::update(checklist_template_items.find(item_id))
+                    .set((
+                        position.eq(i as i32),
+                        title.eq(item_title),
+                        due_in_days.eq(item.due_in_days),
+                        assignee_id.eq(item.assignee_id),
+                    ))
+                    .get_result::<ChecklistItem>(conn),
+                None => diesel::insert_into(checklist_template_items)
+                    .values(&NewChecklistItem {
+                        template_id: template.id,
+                        position: i as i32,
+                        title: item_title,
+                        due_in_days: item.due_in_days,
+                        assignee_id: item.assignee_id,
+                    })
+                    .get_result::<ChecklistItem>(conn),
+            };
+            saved.map_err(|e| Error::from(e))
+        })
+        .collect()
+}
+
+///
+/// Public API
+///
+
+/// Get checklists with their items
+pub fn get_checklists(user: CurrentUser, conn: Conn) -> Response<Vec<ChecklistWithItems>> {
+    use schema::{checklist_template_items, checklist_templates};
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let templates = checklist_templates::table
+        .order_by(checklist_templates::name)
+        .load::<ChecklistTemplate>(&conn)?;
+    let template_ids = templates.iter().map(|t| t.id).collect::<Vec<i32>>();
+
+    let mut items = HashMap::new();
+    for item in checklist_template_items::table
+        .filter(checklist_template_items::template_id.eq_any(&template_ids))
+        .order_by(checklist_template_items::position)
+        .load::<ChecklistItem>(&conn)?
+    {
+        items
+            .entry(item.template_id)
+            .or_insert_with(Vec::new)
+            .push(item);
+    }
+
+    let c = templates
+        .into_iter()
+        .map(|template| ChecklistWithItems {
+            items: items.remove(&template.id).unwrap_or_default(),
+            template,
+        })
+        .collect();
+
+    Ok(Payload {
+        data: c,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Get a checklist with its items
+pub fn get_checklist(
+    checklist_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+) -> Response<ChecklistWithItems> {
+    use schema::{checklist_template_items, checklist_templates};
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let template = checklist_templates::table
+        .find(checklist_id)
+        .first::<ChecklistTemplate>(&conn)?;
+    let items = checklist_template_items::table
+        .filter(checklist_template_items::template_id.eq(template.id))
+        .order_by(checklist_template_items::position)
+        .load::<ChecklistItem>(&conn)?;
+
+    Ok(Payload {
+        data: ChecklistWithItems { template, items },
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Create a checklist
+pub fn create_checklist(
+    user: CurrentUser,
+    conn: Conn,
+    input: ChecklistInput,
+) -> Response<ChecklistWithItems> {
+    use schema::checklist_templates;
+
+    let user = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    validate_input(&input)?;
+
+    let c = conn.transaction::<_, Error, _>(|| {
+        let template = diesel::insert_into(checklist_templates::table)
+            .values(&NewChecklistTemplate {
+                name: input.name.trim().to_owned(),
+                status: input.status,
+                created_by: Some(user.id),
+                created: chrono::Utc::now().naive_utc(),
+                updated: chrono::Utc::now().naive_utc(),
+            })
+            .get_result::<ChecklistTemplate>(&conn)?;
+        let items = save_items(&conn, &template, &input)?;
+
+        Ok(ChecklistWithItems { template, items })
+    })?;
+
+    Ok(Payload {
+        data: c,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Update a checklist, replacing its items
+///
+/// Tasks already added to deals are left as they are.
+pub fn update_checklist(
+    checklist_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: ChecklistInput,
+) -> Response<ChecklistWithItems> {
+    use schema::checklist_templates::dsl::*;
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    validate_input(&input)?;
+
+    let c = conn.transaction::<_, Error, _>(|| {
+        let template = diesel::update(checklist_templates.find(checklist_id))
+            .set((
+                name.eq(input.name.trim()),
+                status.eq(input.status),
+                updated.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .get_result::<ChecklistTemplate>(&conn)?;
+        let items = save_items(&conn, &template, &input)?;
+
+        Ok(ChecklistWithItems { template, items })
+    })?;
+
+    Ok(Payload {
+        data: c,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Delete a checklist
+///
+/// Tasks already added to deals are left as they are.
+pub fn delete_checklist(
+    checklist_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+) -> Response<ChecklistTemplate> {
+    use schema::checklist_templates::dsl::*;
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let c = diesel::delete(checklist_templates.find(checklist_id))
+        .get_result::<ChecklistTemplate>(&conn)?;
+
+    Ok(Payload {
+        data: c,
+        success: true,
+        ..Default::default()
+    })
+}
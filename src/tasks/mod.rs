@@ -0,0 +1,284 @@
+//
+// tasks/mod.rs
+//
+pub mod checklists;
+pub mod types;
+
+use self::types::*;
+use accounts::types::{CurrentUser, CurrentUser::*};
+use db::Conn;
+use deals::types::{Deal, DealStatus};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use result::{Error, Payload, Response};
+use validator::Validate;
+
+/// Most tasks a single listing returns
+const MAX_TASKS: i64 = 500;
+
+/// Days a deal can sit in `MailerSent` before its buyer is reminded
+const FOLLOW_UP_AFTER_DAYS: i64 = 14;
+
+/// Title of the reminder task added to stuck deals
+const FOLLOW_UP_TITLE: &str = "Follow up on unanswered mailer";
+
+///
+/// Helpers
+///
+
+/// Add the tasks of every checklist for the deal's current status.
+///
+/// Called whenever a deal enters a status. Items already applied to the
+/// deal are skipped, so re-entering a status doesn't add them twice.
+pub fn apply_checklists(conn: &PgConnection, deal: &Deal) -> Result<usize, Error> {
+    use schema::{checklist_template_items, checklist_templates, deal_tasks};
+
+    let items = checklist_template_items::table
+        .inner_join(checklist_templates::table)
+        .filter(checklist_templates::status.eq(deal.status))
+        .select(checklist_template_items::all_columns)
+        .order_by((
+            checklist_template_items::template_id,
+            checklist_template_items::position,
+        ))
+        .load::<ChecklistItem>(conn)?;
+    if items.is_empty() {
+        return Ok(0);
+    }
+
+    let now = chrono::Utc::now().naive_utc();
+    let tasks = items
+        .iter()
+        .map(|item| NewDealTask {
+            deal_id: deal.id,
+            template_item_id: Some(item.id),
+            title: item.title.clone(),
+            assignee_id: item.assignee_id.or(deal.buyer_id),
+            due_date: item
+                .due_in_days
+                .map(|days| now.date() + chrono::Duration::days(days as i64)),
+            created_by: None,
+            created: now,
+            updated: now,
+        })
+        .collect::<Vec<NewDealTask>>();
+
+    diesel::insert_into(deal_tasks::table)
+        .values(&tasks)
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .map_err(|e| Error::from(e))
+}
+
+/// Remind buyers of deals stuck in `MailerSent` to follow up, with a task
+/// due today. A deal gets at most one reminder per `FOLLOW_UP_AFTER_DAYS`.
+pub fn add_follow_up_reminders(conn: &PgConnection) -> Result<usize, Error> {
+    use schema::{deal_tasks, deals};
+
+    let now = chrono::Utc::now().naive_utc();
+    let cutoff = now - chrono::Duration::days(FOLLOW_UP_AFTER_DAYS);
+    let reminded = deal_tasks::table
+        .select(deal_tasks::deal_id)
+        .filter(deal_tasks::title.eq(FOLLOW_UP_TITLE))
+        .filter(deal_tasks::created.gt(cutoff));
+    let stuck = deals::table
+        .filter(deals::status.eq(DealStatus::MailerSent))
+        .filter(deals::updated.lt(cutoff))
+        .filter(diesel::dsl::not(deals::id.eq_any(reminded)))
+        .load::<Deal>(conn)?;
+    if stuck.is_empty() {
+        return Ok(0);
+    }
+
+    let reminders = stuck
+        .iter()
+        .map(|deal| NewDealTask {
+            deal_id: deal.id,
+            template_item_id: None,
+            title: FOLLOW_UP_TITLE.to_owned(),
+            assignee_id: deal.buyer_id,
+            due_date: Some(now.date()),
+            created_by: None,
+            created: now,
+            updated: now,
+        })
+        .collect::<Vec<NewDealTask>>();
+
+    diesel::insert_into(deal_tasks::table)
+        .values(&reminders)
+        .execute(conn)
+        .map_err(|e| Error::from(e))
+}
+
+///
+/// Public API
+///
+
+/// Get tasks, open ones by default, soonest due first
+///
+/// Users who aren't admins only see their own tasks.
+pub fn get_tasks(query: TasksQuery, user: CurrentUser, conn: Conn) -> Response<Vec<DealTask>> {
+    use schema::deal_tasks::dsl::*;
+
+    let (user, is_admin) = match user {
+        Admin(user) => (user, true),
+        Authenticated(user) => (user, false),
+        Anonymous => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let assignee = match (query.assignee, is_admin) {
+        (_, false) | (Some(Assignee::Me), true) => Some(user.id),
+        (Some(Assignee::User(uid)), true) => Some(uid),
+        (None, true) => None,
+    };
+
+    let mut q = deal_tasks.into_boxed();
+    if let Some(uid) = assignee {
+        q = q.filter(assignee_id.eq(uid));
+    }
+    if let Some(DateParam(date)) = query.due_before {
+        q = q.filter(due_date.lt(date));
+    }
+    if let Some(did) = query.deal_id {
+        q = q.filter(deal_id.eq(did));
+    }
+    q = match query.completed {
+        Some(true) => q.filter(completed.is_not_null()),
+        _ => q.filter(completed.is_null()),
+    };
+
+    let t = q
+        .order_by((due_date.asc(), id.asc()))
+        .limit(MAX_TASKS)
+        .load::<DealTask>(&conn)?;
+
+    Ok(Payload {
+        data: t,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Add a task to a deal
+pub fn create_task(
+    did: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: CreateTaskInput,
+) -> Response<DealTask> {
+    use schema::{deal_tasks, deals};
+
+    let user = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    input.validate()?;
+
+    let deal = deals::table.find(did).first::<Deal>(&conn)?;
+    let t = diesel::insert_into(deal_tasks::table)
+        .values(&NewDealTask {
+            deal_id: deal.id,
+            template_item_id: None,
+            title: input.title.trim().to_owned(),
+            assignee_id: input.assignee_id,
+            due_date: input.due_date,
+            created_by: Some(user.id),
+            created: chrono::Utc::now().naive_utc(),
+            updated: chrono::Utc::now().naive_utc(),
+        })
+        .get_result::<DealTask>(&conn)?;
+
+    Ok(Payload {
+        data: t,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Update a task
+///
+/// Admins can change anything. The assignee can only mark it completed
+/// or not.
+pub fn update_task(
+    task_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: UpdateTaskInput,
+) -> Response<DealTask> {
+    use schema::deal_tasks::dsl::*;
+
+    let (user, is_admin) = match user {
+        Admin(user) => (user, true),
+        Authenticated(user) => (user, false),
+        Anonymous => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    input.validate()?;
+
+    let task = deal_tasks.find(task_id).first::<DealTask>(&conn)?;
+    if !is_admin && task.assignee_id != Some(user.id) {
+        return Err(Error::AccessDenied);
+    }
+
+    let (new_completed, new_completed_by) = match input.completed {
+        Some(true) if task.completed.is_none() => {
+            (Some(chrono::Utc::now().naive_utc()), Some(user.id))
+        }
+        Some(false) => (None, None),
+        _ => (task.completed, task.completed_by),
+    };
+
+    let t = if is_admin {
+        diesel::update(&task)
+            .set((
+                title.eq(input
+                    .title
+                    .as_ref()
+                    .map(|t| t.trim().to_owned())
+                    .unwrap_or_else(|| task.title.clone())),
+                assignee_id.eq(input.assignee_id.unwrap_or(task.assignee_id)),
+                due_date.eq(input.due_date.unwrap_or(task.due_date)),
+                completed.eq(new_completed),
+                completed_by.eq(new_completed_by),
+                updated.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .get_result::<DealTask>(&conn)?
+    } else {
+        diesel::update(&task)
+            .set((
+                completed.eq(new_completed),
+                completed_by.eq(new_completed_by),
+                updated.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .get_result::<DealTask>(&conn)?
+    };
+
+    Ok(Payload {
+        data: t,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Delete a task
+pub fn delete_task(task_id: i32, user: CurrentUser, conn: Conn) -> Response<DealTask> {
+    use schema::deal_tasks::dsl::*;
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let t = diesel::delete(deal_tasks.find(task_id)).get_result::<DealTask>(&conn)?;
+
+    Ok(Payload {
+        data: t,
+        success: true,
+        ..Default::default()
+    })
+}
@@ -0,0 +1,184 @@
+//
+// tasks/types.rs
+//
+use deals::types::DealStatus;
+use rocket::http::RawStr;
+use rocket::request::FromFormValue;
+use schema::{checklist_template_items, checklist_templates, deal_tasks};
+use serde::{Deserialize, Deserializer};
+use validator::Validate;
+
+#[derive(Serialize, Identifiable, Clone, Queryable, Debug)]
+#[table_name = "deal_tasks"]
+pub struct DealTask {
+    pub id: i32,
+    pub deal_id: i32,
+    /// Checklist item the task came from
+    pub template_item_id: Option<i32>,
+    pub title: String,
+    pub assignee_id: Option<i32>,
+    pub due_date: Option<chrono::NaiveDate>,
+    pub completed: Option<chrono::NaiveDateTime>,
+    pub completed_by: Option<i32>,
+    pub created_by: Option<i32>,
+    pub created: chrono::NaiveDateTime,
+    pub updated: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "deal_tasks"]
+pub struct NewDealTask {
+    pub deal_id: i32,
+    pub template_item_id: Option<i32>,
+    pub title: String,
+    pub assignee_id: Option<i32>,
+    pub due_date: Option<chrono::NaiveDate>,
+    pub created_by: Option<i32>,
+    pub created: chrono::NaiveDateTime,
+    pub updated: chrono::NaiveDateTime,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CreateTaskInput {
+    #[validate(length(min = "1", max = "255", message = "Cannot be blank"))]
+    pub title: String,
+    pub assignee_id: Option<i32>,
+    pub due_date: Option<chrono::NaiveDate>,
+}
+
+/// Fields left out are kept. Send `null` for `assignee_id` or `due_date` to
+/// clear them.
+#[derive(Deserialize, Validate)]
+pub struct UpdateTaskInput {
+    #[validate(length(min = "1", max = "255", message = "Cannot be blank"))]
+    pub title: Option<String>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub assignee_id: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub due_date: Option<Option<chrono::NaiveDate>>,
+    pub completed: Option<bool>,
+}
+
+/// Tells a field that's left out (`None`) from one set to `null`
+/// (`Some(None)`)
+fn double_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// `me` or a user id
+#[derive(Debug, Clone, Copy)]
+pub enum Assignee {
+    Me,
+    User(i32),
+}
+
+impl<'v> FromFormValue<'v> for Assignee {
+    type Error = &'v RawStr;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, &'v RawStr> {
+        match form_value.as_str() {
+            "me" => Ok(Assignee::Me),
+            v => v.parse::<i32>().map(Assignee::User).map_err(|_| form_value),
+        }
+    }
+}
+
+/// A date as `YYYY-MM-DD`
+#[derive(Debug, Clone, Copy)]
+pub struct DateParam(pub chrono::NaiveDate);
+
+impl<'v> FromFormValue<'v> for DateParam {
+    type Error = &'v RawStr;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, &'v RawStr> {
+        chrono::NaiveDate::parse_from_str(form_value.as_str(), "%Y-%m-%d")
+            .map(DateParam)
+            .map_err(|_| form_value)
+    }
+}
+
+#[derive(FromForm, Debug, Default)]
+pub struct TasksQuery {
+    pub assignee: Option<Assignee>,
+    /// Only tasks due before this date
+    pub due_before: Option<DateParam>,
+    /// Open tasks by default
+    pub completed: Option<bool>,
+    pub deal_id: Option<i32>,
+}
+
+#[derive(Serialize, Identifiable, Clone, Queryable, Debug)]
+#[table_name = "checklist_templates"]
+pub struct ChecklistTemplate {
+    pub id: i32,
+    pub name: String,
+    /// Deal status that applies the checklist
+    pub status: DealStatus,
+    pub created_by: Option<i32>,
+    pub created: chrono::NaiveDateTime,
+    pub updated: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "checklist_templates"]
+pub struct NewChecklistTemplate {
+    pub name: String,
+    pub status: DealStatus,
+    pub created_by: Option<i32>,
+    pub created: chrono::NaiveDateTime,
+    pub updated: chrono::NaiveDateTime,
+}
+
+#[derive(Serialize, Identifiable, Clone, Queryable, Debug)]
+#[table_name = "checklist_template_items"]
+pub struct ChecklistItem {
+    pub id: i32,
+    pub template_id: i32,
+    pub position: i32,
+    pub title: String,
+    /// Days after the checklist is applied that the task is due
+    pub due_in_days: Option<i32>,
+    /// `None` assigns the task to the deal's buyer
+    pub assignee_id: Option<i32>,
+}
+
+#[derive(Insertable)]
+#[table_name = "checklist_template_items"]
+pub struct NewChecklistItem {
+    pub template_id: i32,
+    pub position: i32,
+    pub title: String,
+    pub due_in_days: Option<i32>,
+    pub assignee_id: Option<i32>,
+}
+
+#[derive(Serialize)]
+pub struct ChecklistWithItems {
+    pub template: ChecklistTemplate,
+    pub items: Vec<ChecklistItem>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct ChecklistItemInput {
+    /// The existing item this is, when editing a checklist. Items keep their
+    /// ids, so deals they were applied to don't get them again.
+    pub id: Option<i32>,
+    #[validate(length(min = "1", max = "255", message = "Cannot be blank"))]
+    pub title: String,
+    pub due_in_days: Option<i32>,
+    pub assignee_id: Option<i32>,
+}
+
+/// Saving a checklist replaces its items with these. Existing items are
+/// kept by id, and any left out are removed.
+#[derive(Deserialize, Validate)]
+pub struct ChecklistInput {
+    #[validate(length(min = "1", max = "255", message = "Cannot be blank"))]
+    pub name: String,
+    pub status: DealStatus,
+    pub items: Vec<ChecklistItemInput>,
+}
@@ -0,0 +1,77 @@
+//
+// template.rs
+//
+// Plain text templates with `{{ name }}` placeholders.
+//
+use result::Error;
+use std::collections::HashMap;
+
+/// Names of the placeholders used in a template, in order
+pub fn variables(body: &str) -> Result<Vec<String>, Error> {
+    let mut names = Vec::new();
+    let mut rest = body;
+
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let end = match after.find("}}") {
+            Some(end) => end,
+            None => {
+                return Err(Error::from_custom_validation(
+                    "unclosed_variable",
+                    "body",
+                    "Template has an unclosed {{",
+                ));
+            }
+        };
+        names.push(after[..end].trim().to_owned());
+        rest = &after[end + 2..];
+    }
+
+    Ok(names)
+}
+
+/// Make sure a template only uses known placeholders
+pub fn validate(body: &str, known: &[&str]) -> Result<(), Error> {
+    let mut unknown = variables(body)?
+        .into_iter()
+        .filter(|name| !known.contains(&name.as_str()))
+        .collect::<Vec<String>>();
+    unknown.sort();
+    unknown.dedup();
+
+    if unknown.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::from_custom_validation(
+            "unknown_variable",
+            "body",
+            &format!("Unknown variables: {}", unknown.join(", ")),
+        ))
+    }
+}
+
+/// Fill in a template's placeholders. Unknown placeholders render empty.
+pub fn render(body: &str, values: &HashMap<&str, String>) -> String {
+    let mut out = String::new();
+    let mut rest = body;
+
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                if let Some(value) = values.get(after[..end].trim()) {
+                    out.push_str(value);
+                }
+                rest = &after[end + 2..];
+            }
+            None => {
+                out.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+
+    out
+}
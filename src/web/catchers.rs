@@ -0,0 +1,49 @@
+//
+// web/catchers.rs
+//
+// Rocket answers some requests itself: ones no route matches, ones a guard
+// turns away, and bodies that don't parse. These send the same payload as
+// the API's own errors.
+//
+use result::ErrorPayload;
+use rocket_contrib::json::Json;
+use web::error::error_payload;
+
+#[catch(400)]
+pub fn bad_request() -> Json<ErrorPayload> {
+    Json(error_payload("bad_request", "Bad request"))
+}
+
+#[catch(401)]
+pub fn unauthorized() -> Json<ErrorPayload> {
+    Json(error_payload("unauthorized", "A valid API key is required"))
+}
+
+#[catch(403)]
+pub fn forbidden() -> Json<ErrorPayload> {
+    Json(error_payload("access_denied", "Access denied"))
+}
+
+#[catch(404)]
+pub fn not_found() -> Json<ErrorPayload> {
+    Json(error_payload("not_found", "Not found"))
+}
+
+/// Bodies that are JSON, but not the fields the route takes
+#[catch(422)]
+pub fn unprocessable_entity() -> Json<ErrorPayload> {
+    Json(error_payload(
+        "invalid_body",
+        "Request body doesn't have the expected fields",
+    ))
+}
+
+#[catch(500)]
+pub fn internal_error() -> Json<ErrorPayload> {
+    Json(error_payload("internal_error", "Internal error"))
+}
+
+#[catch(503)]
+pub fn service_unavailable() -> Json<ErrorPayload> {
+    Json(error_payload("service_unavailable", "Service unavailable"))
+}
@@ -2,7 +2,9 @@ use accounts::types::CurrentUser;
 use db::Conn;
 use deals;
 use deals::types::*;
+use geocoding::Geocoding;
 use rocket::request::Form;
+use rocket::State;
 use rocket_contrib::json::Json;
 use web::types::ApiResponse;
 
@@ -21,9 +23,10 @@ pub fn get_deals(
 pub fn create_deal(
     user: CurrentUser,
     conn: Conn,
+    geocoding: State<Geocoding>,
     input: Json<CreateDealAndHouseInput>,
 ) -> ApiResponse<Deal> {
-    deals::create_deal(user, conn, input.into_inner()).map(|r| Json(r))
+    deals::create_deal(user, conn, &*geocoding.0, input.into_inner()).map(|r| Json(r))
 }
 
 /// Update deal
@@ -41,12 +44,24 @@ pub fn update_deal(
     deals::update_deal(deal_id, user, conn, input.into_inner()).map(|r| Json(r))
 }
 
-// /// View deals with houses
-// #[get("/views/deals-with-houses?<query..>")]
-// pub fn deals_with_houses(
-//     query: Option<Form<ViewDealsWithHousesQuery>>,
-//     user: CurrentUser,
-//     conn: Conn,
-// ) -> ApiResponse<Vec<Deal>> {
-//     deals::deals_with_houses(query.map(|r| r.into_inner()), user, conn).map(|r| Json(r))
-// }
+/// View deals with houses
+#[get("/views/deals-with-houses?<query..>")]
+pub fn deals_with_houses(
+    query: Option<Form<ViewDealsWithHousesQuery>>,
+    user: CurrentUser,
+    conn: Conn,
+) -> ApiResponse<Vec<DealWithHouse>> {
+    deals::deals_with_houses(query.map(|r| r.into_inner()), user, conn).map(|r| Json(r))
+}
+
+/// Get the public view of a deal from its access code
+#[get("/claim/<code>")]
+pub fn get_claim(code: String, conn: Conn) -> ApiResponse<DealClaim> {
+    deals::get_claim(code, conn).map(|r| Json(r))
+}
+
+/// Claim a deal as its seller
+#[post("/claim/<code>")]
+pub fn claim_deal(code: String, user: CurrentUser, conn: Conn) -> ApiResponse<Deal> {
+    deals::claim_deal(code, user, conn).map(|r| Json(r))
+}
@@ -0,0 +1,82 @@
+use accounts::types::CurrentUser;
+use db::Conn;
+use documents;
+use documents::types::*;
+use result::Error;
+use rocket::http::ContentType;
+use rocket::request::Form;
+use rocket::{Data, State};
+use rocket_contrib::json::Json;
+use std::io::Read;
+use storage::FileStorage;
+use web::types::{ApiResponse, Attachment, AttachmentResponse};
+
+/// Get a deal's documents
+#[get("/deals/<deal_id>/documents")]
+pub fn get_documents(
+    deal_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+) -> ApiResponse<Vec<DealDocument>> {
+    documents::get_documents(deal_id, user, conn).map(|r| Json(r))
+}
+
+/// Upload a document to a deal
+///
+/// The body is the file itself, with its type as the request's
+/// `Content-Type` and its name in the query string.
+#[post("/deals/<deal_id>/documents?<query..>", data = "<data>")]
+pub fn upload_document(
+    deal_id: i32,
+    query: Form<UploadDocumentQuery>,
+    content_type: Option<&ContentType>,
+    user: CurrentUser,
+    conn: Conn,
+    storage: State<FileStorage>,
+    data: Data,
+) -> ApiResponse<DealDocument> {
+    // Read one byte past the limit so oversized files can be rejected
+    let mut bytes = Vec::new();
+    data.open()
+        .take(documents::MAX_DOCUMENT_SIZE as u64 + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|_| Error::ServiceUnavailable)?;
+
+    let input = UploadDocumentInput {
+        filename: query.into_inner().filename,
+        content_type: content_type.map(|c| c.to_string()).unwrap_or_default(),
+        bytes,
+    };
+    documents::upload_document(deal_id, user, conn, &*storage.0, input).map(|r| Json(r))
+}
+
+/// Download a document
+#[get("/deals/<deal_id>/documents/<document_id>/download")]
+pub fn download_document(
+    deal_id: i32,
+    document_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    storage: State<FileStorage>,
+) -> AttachmentResponse {
+    documents::download_document(deal_id, document_id, user, conn, &*storage.0).map(
+        |(document, bytes)| Attachment {
+            content_type: ContentType::parse_flexible(&document.content_type)
+                .unwrap_or(ContentType::Binary),
+            filename: document.filename,
+            bytes,
+        },
+    )
+}
+
+/// Delete a document
+#[delete("/deals/<deal_id>/documents/<document_id>")]
+pub fn delete_document(
+    deal_id: i32,
+    document_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    storage: State<FileStorage>,
+) -> ApiResponse<DealDocument> {
+    documents::delete_document(deal_id, document_id, user, conn, &*storage.0).map(|r| Json(r))
+}
@@ -0,0 +1,24 @@
+use accounts::types::CurrentUser;
+use db::Conn;
+use email;
+use email::types::*;
+use rocket::request::Form;
+use rocket_contrib::json::Json;
+use web::types::ApiResponse;
+
+/// Get sent and pending emails
+#[get("/emails?<query..>")]
+pub fn get_emails(
+    query: Option<Form<EmailsQuery>>,
+    user: CurrentUser,
+    conn: Conn,
+) -> ApiResponse<Vec<Email>> {
+    let query = query.map(|q| q.into_inner()).unwrap_or_default();
+    email::get_emails(query, user, conn).map(|r| Json(r))
+}
+
+/// Get an email
+#[get("/emails/<email_id>")]
+pub fn get_email(email_id: i32, user: CurrentUser, conn: Conn) -> ApiResponse<Email> {
+    email::get_email(email_id, user, conn).map(|r| Json(r))
+}
@@ -0,0 +1,31 @@
+use db::Conn;
+use events;
+use events::types::StreamToken;
+use events::EventHub;
+use rocket::State;
+use rocket_contrib::json::Json;
+use web::guards::{ApiKey, LastEventId, StreamUser};
+use web::types::{ApiResponse, EventStreamResponse};
+
+/// Stream deal changes and notifications for the current user as
+/// server-sent events
+///
+/// Each response ends once it has sent some events, or after 25 seconds
+/// without any, and the browser reconnects with `Last-Event-ID` to carry
+/// on. Streams wait on one of Rocket's workers, so each process serves at
+/// most `EVENT_STREAMS` of them at once and answers 503 beyond that.
+#[get("/events/stream")]
+pub fn stream(
+    user: StreamUser,
+    last_event_id: LastEventId,
+    hub: State<EventHub>,
+) -> EventStreamResponse {
+    let StreamUser(user) = user;
+    hub.subscribe(&user, last_event_id.0)
+}
+
+/// Get a token to open event streams with, good for ten minutes
+#[post("/events/token")]
+pub fn create_token(key: ApiKey, conn: Conn) -> ApiResponse<StreamToken> {
+    events::create_token(key.0, conn).map(|r| Json(r))
+}
@@ -0,0 +1,20 @@
+use accounts::types::CurrentUser;
+use db::Conn;
+use graphql;
+use graphql::types::GraphQLInput;
+use rocket::http::Status;
+use rocket::response::status;
+use rocket_contrib::json::Json;
+use web::types::GraphQLResponse;
+
+/// Run a GraphQL query
+///
+/// Requests that can't run at all, like ones that don't parse, answer 400.
+/// Errors in single fields come back in `errors` beside the rest of `data`.
+#[post("/graphql", format = "application/json", data = "<input>")]
+pub fn graphql(user: CurrentUser, conn: Conn, input: Json<GraphQLInput>) -> GraphQLResponse {
+    graphql::execute(input.into_inner(), user, conn).map(|r| {
+        let res_status = if r.ok { Status::Ok } else { Status::BadRequest };
+        status::Custom(res_status, Json(r.body))
+    })
+}
@@ -0,0 +1,60 @@
+use accounts::types::CurrentUser;
+use db::Conn;
+use houses;
+use houses::types::*;
+use rocket::request::Form;
+use rocket_contrib::json::Json;
+use web::types::ApiResponse;
+
+/// Get all houses, or the houses in an area
+#[get("/houses?<query..>")]
+pub fn get_houses(
+    query: Option<Form<HousesQuery>>,
+    user: CurrentUser,
+    conn: Conn,
+) -> ApiResponse<Vec<HouseSearchResult>> {
+    houses::get_houses(query.map(|r| r.into_inner()), user, conn).map(|r| Json(r))
+}
+
+/// Get house by id, with its deals
+#[get("/houses/<house_id>")]
+pub fn get_house(house_id: i32, user: CurrentUser, conn: Conn) -> ApiResponse<HouseWithDeals> {
+    houses::get_house(house_id, user, conn).map(|r| Json(r))
+}
+
+/// Update house
+#[put("/houses/<house_id>", format = "application/json", data = "<input>")]
+pub fn update_house(
+    house_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: Json<UpdateHouseInput>,
+) -> ApiResponse<House> {
+    houses::update_house(house_id, user, conn, input.into_inner()).map(|r| Json(r))
+}
+
+/// Merge duplicate houses into this one
+#[post(
+    "/houses/<house_id>/merge",
+    format = "application/json",
+    data = "<input>"
+)]
+pub fn merge_houses(
+    house_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: Json<MergeHousesInput>,
+) -> ApiResponse<HouseWithDeals> {
+    houses::merge_houses(house_id, user, conn, input.into_inner()).map(|r| Json(r))
+}
+
+/// Delete house
+#[delete("/houses/<house_id>?<force>")]
+pub fn delete_house(
+    house_id: i32,
+    force: Option<bool>,
+    user: CurrentUser,
+    conn: Conn,
+) -> ApiResponse<House> {
+    houses::delete_house(house_id, force.unwrap_or(false), user, conn).map(|r| Json(r))
+}
@@ -0,0 +1,24 @@
+use accounts::types::CurrentUser;
+use db::Conn;
+use jobs;
+use jobs::types::*;
+use rocket::request::Form;
+use rocket_contrib::json::Json;
+use web::types::ApiResponse;
+
+/// Get background jobs
+#[get("/jobs?<query..>")]
+pub fn get_jobs(
+    query: Option<Form<JobsQuery>>,
+    user: CurrentUser,
+    conn: Conn,
+) -> ApiResponse<Vec<Job>> {
+    let query = query.map(|q| q.into_inner()).unwrap_or_default();
+    jobs::get_jobs(query, user, conn).map(|r| Json(r))
+}
+
+/// Retry a dead job
+#[post("/jobs/<job_id>/retry")]
+pub fn retry_job(job_id: i32, user: CurrentUser, conn: Conn) -> ApiResponse<Job> {
+    jobs::retry_job(job_id, user, conn).map(|r| Json(r))
+}
@@ -0,0 +1,154 @@
+use accounts::types::CurrentUser;
+use db::Conn;
+use mailers;
+use mailers::types::*;
+use mailers::{labels, scans, templates};
+use result::Error;
+use rocket::http::ContentType;
+use rocket::request::Form;
+use rocket::response::{Content, Redirect};
+use rocket_contrib::json::Json;
+use web::types::{ApiResponse, FileResponse};
+
+/// Generate a deal's mailer and mark it as sent
+#[post("/deals/<deal_id>/mailer", data = "<input>")]
+pub fn send_mailer(
+    deal_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: Option<Json<SendMailerInput>>,
+) -> FileResponse {
+    let input = input.map(|i| i.into_inner()).unwrap_or_default();
+    mailers::send_mailer(deal_id, user, conn, input).map(|pdf| Content(ContentType::PDF, pdf))
+}
+
+/// Landing page for a mailer's short URL and QR code
+#[get("/m/<code>")]
+pub fn scan_mailer(code: String, conn: Conn) -> Result<Redirect, Error> {
+    scans::record_scan(code, conn).map(|url| Redirect::to(url))
+}
+
+/// Get scans of a deal's mailers
+#[get("/deals/<deal_id>/scans")]
+pub fn deal_scans(deal_id: i32, user: CurrentUser, conn: Conn) -> ApiResponse<DealScanReport> {
+    scans::deal_scans(deal_id, user, conn).map(|r| Json(r))
+}
+
+/// Create a batch of mailers
+#[post("/mailer-batches", format = "application/json", data = "<input>")]
+pub fn create_batch(
+    user: CurrentUser,
+    conn: Conn,
+    input: Json<CreateMailerBatchInput>,
+) -> ApiResponse<MailerBatchResult> {
+    mailers::create_batch(user, conn, input.into_inner()).map(|r| Json(r))
+}
+
+/// Get mailer batches
+#[get("/mailer-batches")]
+pub fn get_batches(user: CurrentUser, conn: Conn) -> ApiResponse<Vec<MailerBatch>> {
+    mailers::get_batches(user, conn).map(|r| Json(r))
+}
+
+/// Get a mailer batch
+#[get("/mailer-batches/<batch_id>")]
+pub fn get_batch(batch_id: i32, user: CurrentUser, conn: Conn) -> ApiResponse<MailerBatchDetail> {
+    mailers::get_batch(batch_id, user, conn).map(|r| Json(r))
+}
+
+/// Get a batch's combined PDF
+#[get("/mailer-batches/<batch_id>/pdf")]
+pub fn batch_pdf(batch_id: i32, user: CurrentUser, conn: Conn) -> FileResponse {
+    mailers::batch_pdf(batch_id, user, conn).map(|pdf| Content(ContentType::PDF, pdf))
+}
+
+/// Get scans of a batch's mailers
+#[get("/mailer-batches/<batch_id>/scans")]
+pub fn batch_scans(batch_id: i32, user: CurrentUser, conn: Conn) -> ApiResponse<BatchScanReport> {
+    scans::batch_scans(batch_id, user, conn).map(|r| Json(r))
+}
+
+/// Get a batch's CSV manifest
+#[get("/mailer-batches/<batch_id>/manifest.csv")]
+pub fn batch_manifest(batch_id: i32, user: CurrentUser, conn: Conn) -> FileResponse {
+    mailers::batch_manifest(batch_id, user, conn)
+        .map(|csv| Content(ContentType::CSV, csv.into_bytes()))
+}
+
+/// Get mailer templates
+#[get("/mailer-templates")]
+pub fn get_templates(user: CurrentUser, conn: Conn) -> ApiResponse<Vec<MailerTemplateWithVersion>> {
+    templates::get_templates(user, conn).map(|r| Json(r))
+}
+
+/// Create a mailer template
+#[post("/mailer-templates", format = "application/json", data = "<input>")]
+pub fn create_template(
+    user: CurrentUser,
+    conn: Conn,
+    input: Json<CreateMailerTemplateInput>,
+) -> ApiResponse<MailerTemplateWithVersion> {
+    templates::create_template(user, conn, input.into_inner()).map(|r| Json(r))
+}
+
+/// Get a mailer template with its versions
+#[get("/mailer-templates/<template_id>")]
+pub fn get_template(
+    template_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+) -> ApiResponse<MailerTemplateDetail> {
+    templates::get_template(template_id, user, conn).map(|r| Json(r))
+}
+
+/// Update a mailer template
+#[put(
+    "/mailer-templates/<template_id>",
+    format = "application/json",
+    data = "<input>"
+)]
+pub fn update_template(
+    template_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: Json<UpdateMailerTemplateInput>,
+) -> ApiResponse<MailerTemplateWithVersion> {
+    templates::update_template(template_id, user, conn, input.into_inner()).map(|r| Json(r))
+}
+
+/// Archive a mailer template
+#[delete("/mailer-templates/<template_id>")]
+pub fn archive_template(
+    template_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+) -> ApiResponse<MailerTemplate> {
+    templates::archive_template(template_id, user, conn).map(|r| Json(r))
+}
+
+/// Preview a mailer template against a deal
+#[get("/mailer-templates/<template_id>/preview?<query..>")]
+pub fn preview_template(
+    template_id: i32,
+    query: Form<MailerPreviewQuery>,
+    user: CurrentUser,
+    conn: Conn,
+) -> FileResponse {
+    templates::preview_template(template_id, query.into_inner(), user, conn)
+        .map(|pdf| Content(ContentType::PDF, pdf))
+}
+
+/// Export address labels as a label sheet PDF or a CSV address list
+#[get("/deals/export/labels?<query..>")]
+pub fn export_labels(
+    query: Option<Form<LabelExportQuery>>,
+    user: CurrentUser,
+    conn: Conn,
+) -> FileResponse {
+    let query = query.map(|q| q.into_inner()).unwrap_or_default();
+    let content_type = match query.format.unwrap_or_default() {
+        ExportFormat::Pdf => ContentType::PDF,
+        ExportFormat::Csv => ContentType::CSV,
+    };
+    labels::export_labels(query, user, conn).map(|bytes| Content(content_type, bytes))
+}
@@ -1,2 +1,17 @@
 pub mod accounts;
 pub mod deal;
+pub mod document;
+pub mod email;
+pub mod event;
+pub mod graphql;
+pub mod house;
+pub mod job;
+pub mod mailer;
+pub mod note;
+pub mod notification;
+pub mod offer;
+pub mod openapi;
+pub mod response;
+pub mod stats;
+pub mod task;
+pub mod webhook;
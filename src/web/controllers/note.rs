@@ -0,0 +1,65 @@
+use accounts::types::CurrentUser;
+use db::Conn;
+use notes;
+use notes::types::*;
+use rocket_contrib::json::Json;
+use web::types::ApiResponse;
+
+/// Get a deal's notes
+#[get("/deals/<deal_id>/notes")]
+pub fn get_notes(deal_id: i32, user: CurrentUser, conn: Conn) -> ApiResponse<Vec<DealNote>> {
+    notes::get_notes(deal_id, user, conn).map(|r| Json(r))
+}
+
+/// Add a note to a deal
+#[post(
+    "/deals/<deal_id>/notes",
+    format = "application/json",
+    data = "<input>"
+)]
+pub fn create_note(
+    deal_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: Json<CreateNoteInput>,
+) -> ApiResponse<DealNote> {
+    notes::create_note(deal_id, user, conn, input.into_inner()).map(|r| Json(r))
+}
+
+/// Edit a note
+#[put(
+    "/deals/<deal_id>/notes/<note_id>",
+    format = "application/json",
+    data = "<input>"
+)]
+pub fn update_note(
+    deal_id: i32,
+    note_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: Json<UpdateNoteInput>,
+) -> ApiResponse<DealNote> {
+    notes::update_note(deal_id, note_id, user, conn, input.into_inner()).map(|r| Json(r))
+}
+
+/// Delete a note
+#[delete("/deals/<deal_id>/notes/<note_id>")]
+pub fn delete_note(
+    deal_id: i32,
+    note_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+) -> ApiResponse<DealNote> {
+    notes::delete_note(deal_id, note_id, user, conn).map(|r| Json(r))
+}
+
+/// Get a note's edit history
+#[get("/deals/<deal_id>/notes/<note_id>/history")]
+pub fn get_note_history(
+    deal_id: i32,
+    note_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+) -> ApiResponse<DealNoteHistory> {
+    notes::get_note_history(deal_id, note_id, user, conn).map(|r| Json(r))
+}
@@ -0,0 +1,50 @@
+use accounts::types::CurrentUser;
+use db::Conn;
+use notifications;
+use notifications::types::*;
+use rocket::request::Form;
+use rocket_contrib::json::Json;
+use web::types::ApiResponse;
+
+/// Get the current user's notifications
+#[get("/notifications?<query..>")]
+pub fn get_notifications(
+    query: Option<Form<NotificationsQuery>>,
+    user: CurrentUser,
+    conn: Conn,
+) -> ApiResponse<NotificationList> {
+    let query = query.map(|q| q.into_inner()).unwrap_or_default();
+    notifications::get_notifications(query, user, conn).map(|r| Json(r))
+}
+
+/// Mark a notification read
+#[post("/notifications/<notification_id>/read")]
+pub fn mark_read(notification_id: i32, user: CurrentUser, conn: Conn) -> ApiResponse<Notification> {
+    notifications::mark_read(notification_id, user, conn).map(|r| Json(r))
+}
+
+/// Mark every notification read
+#[post("/notifications/read-all")]
+pub fn mark_all_read(user: CurrentUser, conn: Conn) -> ApiResponse<usize> {
+    notifications::mark_all_read(user, conn).map(|r| Json(r))
+}
+
+/// Get the current user's notification email preferences
+#[get("/notifications/preferences")]
+pub fn get_preferences(user: CurrentUser, conn: Conn) -> ApiResponse<Vec<NotificationPreference>> {
+    notifications::get_preferences(user, conn).map(|r| Json(r))
+}
+
+/// Update the current user's notification email preferences
+#[put(
+    "/notifications/preferences",
+    format = "application/json",
+    data = "<input>"
+)]
+pub fn update_preferences(
+    user: CurrentUser,
+    conn: Conn,
+    input: Json<Vec<NotificationPreference>>,
+) -> ApiResponse<Vec<NotificationPreference>> {
+    notifications::update_preferences(user, conn, input.into_inner()).map(|r| Json(r))
+}
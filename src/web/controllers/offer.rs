@@ -0,0 +1,69 @@
+use accounts::types::CurrentUser;
+use db::Conn;
+use offers;
+use offers::types::*;
+use rocket_contrib::json::Json;
+use web::types::ApiResponse;
+
+/// Get a deal's offers
+#[get("/deals/<deal_id>/offers")]
+pub fn get_offers(deal_id: i32, user: CurrentUser, conn: Conn) -> ApiResponse<Vec<Offer>> {
+    offers::get_offers(deal_id, user, conn).map(|r| Json(r))
+}
+
+/// Make an offer on a deal
+#[post(
+    "/deals/<deal_id>/offers",
+    format = "application/json",
+    data = "<input>"
+)]
+pub fn make_offer(
+    deal_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: Json<OfferInput>,
+) -> ApiResponse<Offer> {
+    offers::make_offer(deal_id, user, conn, input.into_inner()).map(|r| Json(r))
+}
+
+/// Counter an offer
+#[post(
+    "/deals/<deal_id>/offers/<offer_id>/counter",
+    format = "application/json",
+    data = "<input>"
+)]
+pub fn counter_offer(
+    deal_id: i32,
+    offer_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: Json<OfferInput>,
+) -> ApiResponse<Offer> {
+    offers::counter_offer(deal_id, offer_id, user, conn, input.into_inner()).map(|r| Json(r))
+}
+
+/// Accept an offer
+#[post("/deals/<deal_id>/offers/<offer_id>/accept", data = "<input>")]
+pub fn accept_offer(
+    deal_id: i32,
+    offer_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: Option<Json<RespondToOfferInput>>,
+) -> ApiResponse<Offer> {
+    let input = input.map(|i| i.into_inner()).unwrap_or_default();
+    offers::accept_offer(deal_id, offer_id, user, conn, input).map(|r| Json(r))
+}
+
+/// Reject an offer
+#[post("/deals/<deal_id>/offers/<offer_id>/reject", data = "<input>")]
+pub fn reject_offer(
+    deal_id: i32,
+    offer_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: Option<Json<RespondToOfferInput>>,
+) -> ApiResponse<Offer> {
+    let input = input.map(|i| i.into_inner()).unwrap_or_default();
+    offers::reject_offer(deal_id, offer_id, user, conn, input).map(|r| Json(r))
+}
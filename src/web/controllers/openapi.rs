@@ -0,0 +1,11 @@
+use rocket::http::ContentType;
+use rocket::response::Content;
+
+/// Generated from the routes and their types by `build.rs`
+const DOCUMENT: &str = include_str!(concat!(env!("OUT_DIR"), "/openapi.json"));
+
+/// Describe the API as an OpenAPI 3 document
+#[get("/openapi.json")]
+pub fn openapi() -> Content<&'static str> {
+    Content(ContentType::JSON, DOCUMENT)
+}
@@ -0,0 +1,48 @@
+use accounts::types::CurrentUser;
+use db::Conn;
+use responses;
+use responses::types::*;
+use rocket_contrib::json::Json;
+use web::types::ApiResponse;
+
+/// Submit a seller response with a deal's access code
+///
+/// Once the deal is claimed, the seller has to be signed in.
+#[post(
+    "/claim/<code>/response",
+    format = "application/json",
+    data = "<input>"
+)]
+pub fn submit_response(
+    code: String,
+    user: Option<CurrentUser>,
+    conn: Conn,
+    input: Json<SellerResponseInput>,
+) -> ApiResponse<SellerResponse> {
+    responses::submit_response(code, user, conn, input.into_inner()).map(|r| Json(r))
+}
+
+/// Enter a seller response on a deal
+#[post(
+    "/deals/<deal_id>/responses",
+    format = "application/json",
+    data = "<input>"
+)]
+pub fn create_response(
+    deal_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: Json<SellerResponseInput>,
+) -> ApiResponse<SellerResponse> {
+    responses::create_response(deal_id, user, conn, input.into_inner()).map(|r| Json(r))
+}
+
+/// Get a deal's seller responses
+#[get("/deals/<deal_id>/responses")]
+pub fn get_responses(
+    deal_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+) -> ApiResponse<Vec<SellerResponse>> {
+    responses::get_responses(deal_id, user, conn).map(|r| Json(r))
+}
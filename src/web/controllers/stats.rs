@@ -0,0 +1,18 @@
+use accounts::types::CurrentUser;
+use db::Conn;
+use housekeeping::stats;
+use housekeeping::types::*;
+use rocket::request::Form;
+use rocket_contrib::json::Json;
+use web::types::ApiResponse;
+
+/// Get daily activity counts
+#[get("/stats/daily?<query..>")]
+pub fn get_daily_stats(
+    query: Option<Form<DailyStatsQuery>>,
+    user: CurrentUser,
+    conn: Conn,
+) -> ApiResponse<Vec<DailyStats>> {
+    let query = query.map(|q| q.into_inner()).unwrap_or_default();
+    stats::get_daily_stats(query, user, conn).map(|r| Json(r))
+}
@@ -0,0 +1,102 @@
+use accounts::types::CurrentUser;
+use db::Conn;
+use rocket::request::Form;
+use rocket_contrib::json::Json;
+use tasks;
+use tasks::checklists;
+use tasks::types::*;
+use web::types::ApiResponse;
+
+/// Get tasks
+#[get("/tasks?<query..>")]
+pub fn get_tasks(
+    query: Option<Form<TasksQuery>>,
+    user: CurrentUser,
+    conn: Conn,
+) -> ApiResponse<Vec<DealTask>> {
+    let query = query.map(|q| q.into_inner()).unwrap_or_default();
+    tasks::get_tasks(query, user, conn).map(|r| Json(r))
+}
+
+/// Add a task to a deal
+#[post(
+    "/deals/<deal_id>/tasks",
+    format = "application/json",
+    data = "<input>"
+)]
+pub fn create_task(
+    deal_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: Json<CreateTaskInput>,
+) -> ApiResponse<DealTask> {
+    tasks::create_task(deal_id, user, conn, input.into_inner()).map(|r| Json(r))
+}
+
+/// Update a task
+#[put("/tasks/<task_id>", format = "application/json", data = "<input>")]
+pub fn update_task(
+    task_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: Json<UpdateTaskInput>,
+) -> ApiResponse<DealTask> {
+    tasks::update_task(task_id, user, conn, input.into_inner()).map(|r| Json(r))
+}
+
+/// Delete a task
+#[delete("/tasks/<task_id>")]
+pub fn delete_task(task_id: i32, user: CurrentUser, conn: Conn) -> ApiResponse<DealTask> {
+    tasks::delete_task(task_id, user, conn).map(|r| Json(r))
+}
+
+/// Get checklists
+#[get("/checklists")]
+pub fn get_checklists(user: CurrentUser, conn: Conn) -> ApiResponse<Vec<ChecklistWithItems>> {
+    checklists::get_checklists(user, conn).map(|r| Json(r))
+}
+
+/// Get a checklist
+#[get("/checklists/<checklist_id>")]
+pub fn get_checklist(
+    checklist_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+) -> ApiResponse<ChecklistWithItems> {
+    checklists::get_checklist(checklist_id, user, conn).map(|r| Json(r))
+}
+
+/// Create a checklist
+#[post("/checklists", format = "application/json", data = "<input>")]
+pub fn create_checklist(
+    user: CurrentUser,
+    conn: Conn,
+    input: Json<ChecklistInput>,
+) -> ApiResponse<ChecklistWithItems> {
+    checklists::create_checklist(user, conn, input.into_inner()).map(|r| Json(r))
+}
+
+/// Update a checklist
+#[put(
+    "/checklists/<checklist_id>",
+    format = "application/json",
+    data = "<input>"
+)]
+pub fn update_checklist(
+    checklist_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: Json<ChecklistInput>,
+) -> ApiResponse<ChecklistWithItems> {
+    checklists::update_checklist(checklist_id, user, conn, input.into_inner()).map(|r| Json(r))
+}
+
+/// Delete a checklist
+#[delete("/checklists/<checklist_id>")]
+pub fn delete_checklist(
+    checklist_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+) -> ApiResponse<ChecklistTemplate> {
+    checklists::delete_checklist(checklist_id, user, conn).map(|r| Json(r))
+}
@@ -0,0 +1,70 @@
+use accounts::types::CurrentUser;
+use db::Conn;
+use rocket_contrib::json::Json;
+use web::types::ApiResponse;
+use webhooks;
+use webhooks::types::*;
+
+/// Get webhook subscriptions
+#[get("/webhooks")]
+pub fn get_webhooks(user: CurrentUser, conn: Conn) -> ApiResponse<Vec<Webhook>> {
+    webhooks::get_webhooks(user, conn).map(|r| Json(r))
+}
+
+/// Subscribe a URL to events
+#[post("/webhooks", format = "application/json", data = "<input>")]
+pub fn create_webhook(
+    user: CurrentUser,
+    conn: Conn,
+    input: Json<CreateWebhookInput>,
+) -> ApiResponse<CreatedWebhook> {
+    webhooks::create_webhook(user, conn, input.into_inner()).map(|r| Json(r))
+}
+
+/// Get a webhook subscription
+#[get("/webhooks/<webhook_id>")]
+pub fn get_webhook(webhook_id: i32, user: CurrentUser, conn: Conn) -> ApiResponse<Webhook> {
+    webhooks::get_webhook(webhook_id, user, conn).map(|r| Json(r))
+}
+
+/// Update a webhook subscription
+#[put(
+    "/webhooks/<webhook_id>",
+    format = "application/json",
+    data = "<input>"
+)]
+pub fn update_webhook(
+    webhook_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: Json<UpdateWebhookInput>,
+) -> ApiResponse<Webhook> {
+    webhooks::update_webhook(webhook_id, user, conn, input.into_inner()).map(|r| Json(r))
+}
+
+/// Delete a webhook subscription
+#[delete("/webhooks/<webhook_id>")]
+pub fn delete_webhook(webhook_id: i32, user: CurrentUser, conn: Conn) -> ApiResponse<Webhook> {
+    webhooks::delete_webhook(webhook_id, user, conn).map(|r| Json(r))
+}
+
+/// Get a webhook subscription's deliveries
+#[get("/webhooks/<webhook_id>/deliveries")]
+pub fn get_deliveries(
+    webhook_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+) -> ApiResponse<Vec<WebhookDelivery>> {
+    webhooks::get_deliveries(webhook_id, user, conn).map(|r| Json(r))
+}
+
+/// Send a delivery again
+#[post("/webhooks/<webhook_id>/deliveries/<delivery_id>/redeliver")]
+pub fn redeliver(
+    webhook_id: i32,
+    delivery_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+) -> ApiResponse<WebhookDelivery> {
+    webhooks::redeliver(webhook_id, delivery_id, user, conn).map(|r| Json(r))
+}
@@ -20,18 +20,28 @@ impl Fairing for CORS {
     }
 
     fn on_response(&self, request: &Request, response: &mut Response) {
-        if request.method() == Method::Options || response.content_type() == Some(ContentType::JSON)
+        let content_type = response.content_type();
+        if request.method() == Method::Options
+            || content_type == Some(ContentType::JSON)
+            || content_type == Some(ContentType::PDF)
+            || content_type == Some(ContentType::CSV)
+            || content_type == Some(ContentType::new("text", "event-stream"))
+            || response.headers().contains("Content-Disposition")
         {
             response.set_header(Header::new("Access-Control-Allow-Origin", "*"));
             response.set_header(Header::new(
                 "Access-Control-Allow-Methods",
-                "POST, GET, PUT, OPTIONS",
+                "POST, GET, PUT, DELETE, OPTIONS",
             ));
             response.set_header(Header::new(
                 "Access-Control-Allow-Headers",
                 "Content-Type, X-API-KEY",
             ));
             response.set_header(Header::new("Access-Control-Allow-Credentials", "true"));
+            response.set_header(Header::new(
+                "Access-Control-Expose-Headers",
+                "Content-Disposition, Deprecation, Sunset, Link",
+            ));
         }
 
         if request.method() == Method::Options {
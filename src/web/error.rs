@@ -4,82 +4,66 @@ use rocket::response;
 use rocket::response::status;
 use rocket::response::Responder;
 use rocket_contrib::json::Json;
-use serde_json::json;
 
 use result::Error::*;
 use result::{Error, ErrorPayload, ValidationError};
+use web::guards::SignedIn;
 
 impl<'r> Responder<'r> for Error {
     fn respond_to(self, req: &Request) -> response::Result<'r> {
         // Log the error
         println!("{:?}", self);
 
-        let (res_status, payload) = match self {
-            AccessDenied => access_denied(),
-            ApiKeyError => validation_error("api_key", "Api key is invalid"),
-            DieselError(e) => match e {
-                diesel::result::Error::NotFound => not_found(),
-                _ => unavailable(),
-            },
-
-            // Validation errors
-            InvalidInput(validation_errors) => format_validation_errors(validation_errors),
-            _ => unavailable(),
+        let (code, message) = match self {
+            // Someone who hasn't signed in may be let in once they do
+            AccessDenied if !signed_in(req) => ("unauthorized", "Sign in to continue"),
+            _ => (self.code(), self.message()),
         };
+        let mut payload = error_payload(code, message);
+        match self {
+            InvalidInput(e) | Conflict(e) => {
+                payload.validation_errors = Some(format_validation_errors(e))
+            }
+            _ => {}
+        }
 
-        status::Custom(res_status, payload).respond_to(req)
+        status::Custom(status_of(code), Json(payload)).respond_to(req)
     }
 }
 
-fn access_denied() -> (Status, Json<serde_json::Value>) {
-    (
-        Status::Forbidden,
-        Json(json!(ErrorPayload {
-            error_message: Some("Access deined".to_string()),
-            ..Default::default()
-        })),
-    )
-}
-
-fn validation_error(field: &str, message: &str) -> (Status, Json<serde_json::Value>) {
-    (
-        Status::PartialContent,
-        Json(json!(ErrorPayload {
-            validation_errors: Some(vec![ValidationError {
-                field: field.to_string(),
-                message: message.to_string(),
-            }]),
-            ..Default::default()
-        })),
-    )
+/// The payload every error is sent as
+pub fn error_payload(code: &str, message: &str) -> ErrorPayload {
+    ErrorPayload {
+        error_code: Some(code.to_string()),
+        error_message: Some(message.to_string()),
+        ..Default::default()
+    }
 }
 
-// Format multiple errors
-fn not_found() -> (Status, Json<serde_json::Value>) {
-    (
-        Status::NotFound,
-        Json(json!(ErrorPayload {
-            error_message: Some("Not found".to_string()),
-            ..Default::default()
-        })),
-    )
+/// The status an error code is sent with
+fn status_of(code: &str) -> Status {
+    match code {
+        "unauthorized" => Status::Unauthorized,
+        "access_denied" => Status::Forbidden,
+        "not_found" => Status::NotFound,
+        "conflict" => Status::Conflict,
+        "validation_failed" => Status::UnprocessableEntity,
+        "geocoding_failed" | "mail_failed" | "storage_failed" => Status::BadGateway,
+        "service_unavailable" => Status::ServiceUnavailable,
+        "rate_limited" => Status::TooManyRequests,
+        _ => Status::InternalServerError,
+    }
 }
 
-// Format multiple errors
-fn unavailable() -> (Status, Json<serde_json::Value>) {
-    (
-        Status::ServiceUnavailable,
-        Json(json!(ErrorPayload {
-            error_message: Some("Service unavailable".to_string()),
-            ..Default::default()
-        })),
-    )
+/// Whether the request's API key belongs to a user. Only known once the
+/// `CurrentUser` guard has run.
+fn signed_in(req: &Request) -> bool {
+    req.local_cache(|| SignedIn(None)).0 != Some(false)
 }
 
 // Format multiple errors
-fn format_validation_errors(e: validator::ValidationErrors) -> (Status, Json<serde_json::Value>) {
-    let errors = e
-        .field_errors()
+fn format_validation_errors(e: validator::ValidationErrors) -> Vec<ValidationError> {
+    e.field_errors()
         .iter()
         .map(|(k, v)| {
             let messages = v
@@ -90,16 +74,9 @@ fn format_validation_errors(e: validator::ValidationErrors) -> (Status, Json<ser
                 .join(", ");
             ValidationError {
                 field: k.to_string(),
+                code: v.first().map_or(String::new(), |f| f.code.to_string()),
                 message: messages,
             }
         })
-        .collect::<Vec<ValidationError>>();
-
-    (
-        Status::PartialContent,
-        Json(json!(ErrorPayload {
-            validation_errors: Some(errors),
-            ..Default::default()
-        })),
-    )
+        .collect::<Vec<ValidationError>>()
 }
@@ -1,7 +1,8 @@
 use accounts;
-use accounts::types::CurrentUser;
 use accounts::types::CurrentUser::*;
+use accounts::types::{CurrentUser, User};
 use db::{Pool, PooledConnection};
+use events;
 use result::Error;
 use rocket::http::Status;
 use rocket::request::{self, FromRequest, Request};
@@ -16,28 +17,108 @@ impl<'a, 'r> FromRequest<'a, 'r> for CurrentUser {
 
         let pool = match request.guard::<State<Pool>>() {
             Outcome::Success(s) => s,
-            _ => return Outcome::Failure((Status::BadRequest, Error::ServiceUnavailable)),
+            _ => return Outcome::Failure((Status::ServiceUnavailable, Error::ServiceUnavailable)),
         };
 
-        let conn = pool.0.get().unwrap();
+        let conn = match pool.0.get() {
+            Ok(conn) => conn,
+            Err(_) => {
+                return Outcome::Failure((Status::ServiceUnavailable, Error::ServiceUnavailable))
+            }
+        };
 
         match keys.len() {
-            0 => Outcome::Failure((Status::BadRequest, Error::ApiKeyError)),
-            1 if is_valid(keys[0]) => Outcome::Success(user_from_key(conn, keys[0].to_string())),
-            1 => Outcome::Failure((Status::BadRequest, Error::ApiKeyError)),
-            _ => Outcome::Failure((Status::BadRequest, Error::ApiKeyError)),
+            1 if is_valid(keys[0]) => {
+                Outcome::Success(user_from_key(request, conn, keys[0].to_string()))
+            }
+            _ => Outcome::Failure((Status::Unauthorized, Error::ApiKeyError)),
         }
     }
 }
 
+/// Whether the request's API key belonged to a user, once a guard has looked
+/// it up. Lets errors tell a missing sign in from missing permission.
+pub struct SignedIn(pub Option<bool>);
+
+/// The user on an event stream. Browsers can't set headers on an
+/// `EventSource`, so a short-lived token from `POST /events/token` may come
+/// as the `token` query parameter instead, keeping API keys out of URLs and
+/// the logs they end up in.
+pub struct StreamUser(pub CurrentUser);
+
+impl<'a, 'r> FromRequest<'a, 'r> for StreamUser {
+    type Error = Error;
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        if request.headers().contains("x-api-key") {
+            return request.guard::<CurrentUser>().map(StreamUser);
+        }
+
+        let token = match request.get_query_value::<String>("token") {
+            Some(Ok(token)) => token,
+            _ => return Outcome::Failure((Status::Unauthorized, Error::ApiKeyError)),
+        };
+        let pool = match request.guard::<State<Pool>>() {
+            Outcome::Success(s) => s,
+            _ => return Outcome::Failure((Status::ServiceUnavailable, Error::ServiceUnavailable)),
+        };
+        match pool.0.get() {
+            Ok(conn) => {
+                let user = events::user_from_token(&conn, &token);
+                Outcome::Success(StreamUser(current_user(request, user)))
+            }
+            Err(_) => Outcome::Failure((Status::ServiceUnavailable, Error::ServiceUnavailable)),
+        }
+    }
+}
+
+/// The request's API key, for handlers that need the key itself
+pub struct ApiKey(pub String);
+
+impl<'a, 'r> FromRequest<'a, 'r> for ApiKey {
+    type Error = Error;
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        match request.headers().get_one("x-api-key") {
+            Some(key) => Outcome::Success(ApiKey(key.to_string())),
+            None => Outcome::Failure((Status::Unauthorized, Error::ApiKeyError)),
+        }
+    }
+}
+
+/// The id of the last event a reconnecting `EventSource` saw
+pub struct LastEventId(pub Option<i64>);
+
+impl<'a, 'r> FromRequest<'a, 'r> for LastEventId {
+    type Error = Error;
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        let id = request
+            .headers()
+            .get_one("last-event-id")
+            .and_then(|id| id.trim().parse().ok());
+        Outcome::Success(LastEventId(id))
+    }
+}
+
 fn is_valid(_key: &str) -> bool {
     true
 }
 
 /// Get type of user from their session key
-fn user_from_key(conn: PooledConnection, key: String) -> CurrentUser {
-    accounts::user_from_key(conn, key).map_or(Anonymous, |u| match accounts::user_is_admin(&u) {
+fn user_from_key(request: &Request, conn: PooledConnection, key: String) -> CurrentUser {
+    current_user(request, accounts::user_from_key(conn, key))
+}
+
+fn current_user(request: &Request, user: Option<User>) -> CurrentUser {
+    let user = user.map_or(Anonymous, |u| match accounts::user_is_admin(&u) {
         true => Admin(u),
         false => Authenticated(u),
-    })
+    });
+    let signed_in = match user {
+        Anonymous => false,
+        _ => true,
+    };
+    request.local_cache(|| SignedIn(Some(signed_in)));
+    user
 }
@@ -1,39 +1,45 @@
 //
 // web.rs
 //
+pub mod catchers;
 pub mod controllers;
 pub mod cors;
 pub mod error;
 pub mod guards;
 pub mod types;
+pub mod versions;
 
 use self::controllers::*;
 use db::{create_pool, Pool};
+use events::EventHub;
+use geocoding::{self, Geocoding};
 use rocket::Rocket;
+use storage::{self, FileStorage};
 
-pub fn build() -> Rocket {
-    rocket::ignite()
+pub fn build() -> Result<Rocket, String> {
+    let rocket = rocket::ignite()
         .manage(Pool(create_pool()))
+        .manage(Geocoding(geocoding::from_env()?))
+        .manage(FileStorage(storage::from_env()?))
+        .manage(EventHub::start())
         .mount(
             "/",
-            routes![
-                cors::cors,
-                accounts::login,
-                accounts::register,
-                accounts::all_users,
-                accounts::user_by_id,
-                accounts::create_user,
-                accounts::create_profile,
-                accounts::update_profile,
-                accounts::get_profile,
-                deal::create_deal,
-                deal::get_deals,
-                deal::update_deal,
-            ],
+            routes![cors::cors, mailer::scan_mailer, openapi::openapi],
         )
-        .attach(cors::CORS())
+        .register(catchers![
+            catchers::bad_request,
+            catchers::unauthorized,
+            catchers::forbidden,
+            catchers::not_found,
+            catchers::unprocessable_entity,
+            catchers::internal_error,
+            catchers::service_unavailable,
+        ])
+        .attach(cors::CORS());
+    Ok(versions::mount(rocket))
 }
 
-pub fn launch() {
-    build().launch();
+pub fn launch() -> Result<(), String> {
+    build()?.launch();
+    Ok(())
 }
@@ -1,4 +1,68 @@
+use events::stream::{EventStream, CHUNK_SIZE};
 use result::{Error, Payload};
+use rocket::http::ContentType;
+use rocket::request::Request;
+use rocket::response::{self, status, Content, Responder, Response};
 use rocket_contrib::json::Json;
+use std::io::Cursor;
 
 pub type ApiResponse<T> = Result<Json<Payload<T>>, Error>;
+pub type FileResponse = Result<Content<Vec<u8>>, Error>;
+pub type AttachmentResponse = Result<Attachment, Error>;
+pub type EventStreamResponse = Result<EventStream, Error>;
+pub type GraphQLResponse = Result<status::Custom<Json<serde_json::Value>>, Error>;
+
+/// A file sent to be saved under its own name
+pub struct Attachment {
+    pub filename: String,
+    pub content_type: ContentType,
+    pub bytes: Vec<u8>,
+}
+
+impl<'r> Responder<'r> for Attachment {
+    fn respond_to(self, _req: &Request) -> response::Result<'r> {
+        Response::build()
+            .header(self.content_type)
+            .raw_header("Content-Disposition", content_disposition(&self.filename))
+            .sized_body(Cursor::new(self.bytes))
+            .ok()
+    }
+}
+
+/// `Content-Disposition` for a download. Old clients get an ASCII version
+/// of the name, and the rest the name itself, percent encoded as RFC 5987
+/// describes.
+fn content_disposition(filename: &str) -> String {
+    let ascii = filename
+        .chars()
+        .map(|c| match c {
+            ' '..='~' if c != '"' && c != '\\' => c,
+            _ => '_',
+        })
+        .collect::<String>();
+    let encoded = filename
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect::<String>();
+    format!(
+        "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+        ascii, encoded
+    )
+}
+
+impl<'r> Responder<'r> for EventStream {
+    fn respond_to(self, _req: &Request) -> response::Result<'r> {
+        Response::build()
+            .header(ContentType::new("text", "event-stream"))
+            .raw_header("Cache-Control", "no-cache")
+            // Stop nginx and similar proxies from buffering the stream
+            .raw_header("X-Accel-Buffering", "no")
+            .chunked_body(self, CHUNK_SIZE as u64)
+            .ok()
+    }
+}
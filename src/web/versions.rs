@@ -0,0 +1,229 @@
+//
+// web/versions.rs
+//
+// The API is served under `/v1`, `/v2` and so on, with every version side by
+// side. A version starts as a copy of the one before it, and its `changes`
+// replace the routes with the same method and path, or add new ones. A
+// breaking change to one route is then a new version listing just that
+// route, while clients on the old version keep working.
+//
+// The first version is also served at the unversioned paths it had before
+// versions existed, until `UNVERSIONED_SUNSET`.
+//
+use rocket::handler::{self, Handler};
+use rocket::{Data, Outcome, Request, Rocket, Route};
+use web::controllers::*;
+
+/// When the unversioned paths stop being served
+pub const UNVERSIONED_SUNSET: &str = "Fri, 01 Nov 2019 00:00:00 GMT";
+
+pub struct Version {
+    /// Served under `/<name>`
+    pub name: &'static str,
+    /// Routes added or changed since the version before
+    pub changes: fn() -> Vec<Route>,
+    /// When the version stops being served. Versions with one are
+    /// deprecated.
+    pub sunset: Option<&'static str>,
+}
+
+/// Every version, oldest first
+pub const VERSIONS: &[Version] = &[Version {
+    name: "v1",
+    changes: v1,
+    sunset: None,
+}];
+
+/// A route that answers with `Deprecation` and `Sunset` headers, and a link
+/// to the same path in the version replacing it
+#[derive(Clone)]
+struct Deprecated {
+    handler: Box<dyn Handler>,
+    sunset: &'static str,
+    /// Prefix the route is served under
+    base: String,
+    /// Prefix of the version replacing it
+    successor: Option<String>,
+}
+
+impl Handler for Deprecated {
+    fn handle<'r>(&self, req: &'r Request, data: Data) -> handler::Outcome<'r> {
+        let mut outcome = self.handler.handle(req, data);
+        if let Outcome::Success(ref mut response) = outcome {
+            response.set_raw_header("Deprecation", "true");
+            response.set_raw_header("Sunset", self.sunset);
+            if let Some(ref successor) = self.successor {
+                let uri = req.uri().to_string();
+                let link = format!(
+                    "<{}{}>; rel=\"successor-version\"",
+                    successor,
+                    &uri[self.base.len()..]
+                );
+                response.set_raw_header("Link", link);
+            }
+        }
+        outcome
+    }
+}
+
+///
+/// Versions
+///
+
+fn v1() -> Vec<Route> {
+    routes![
+        accounts::login,
+        accounts::register,
+        accounts::all_users,
+        accounts::user_by_id,
+        accounts::create_user,
+        accounts::create_profile,
+        accounts::update_profile,
+        accounts::get_profile,
+        deal::create_deal,
+        deal::get_deals,
+        deal::update_deal,
+        deal::deals_with_houses,
+        deal::get_claim,
+        deal::claim_deal,
+        house::get_houses,
+        house::get_house,
+        house::update_house,
+        house::merge_houses,
+        house::delete_house,
+        mailer::send_mailer,
+        mailer::deal_scans,
+        mailer::create_batch,
+        mailer::get_batches,
+        mailer::get_batch,
+        mailer::batch_pdf,
+        mailer::batch_manifest,
+        mailer::batch_scans,
+        mailer::export_labels,
+        response::submit_response,
+        response::create_response,
+        response::get_responses,
+        offer::get_offers,
+        offer::make_offer,
+        offer::counter_offer,
+        offer::accept_offer,
+        offer::reject_offer,
+        note::get_notes,
+        note::create_note,
+        note::update_note,
+        note::delete_note,
+        note::get_note_history,
+        document::get_documents,
+        document::upload_document,
+        document::download_document,
+        document::delete_document,
+        task::get_tasks,
+        task::create_task,
+        task::update_task,
+        task::delete_task,
+        task::get_checklists,
+        task::get_checklist,
+        task::create_checklist,
+        task::update_checklist,
+        task::delete_checklist,
+        mailer::get_templates,
+        mailer::create_template,
+        mailer::get_template,
+        mailer::update_template,
+        mailer::archive_template,
+        mailer::preview_template,
+        job::get_jobs,
+        job::retry_job,
+        stats::get_daily_stats,
+        email::get_emails,
+        email::get_email,
+        notification::get_notifications,
+        notification::mark_read,
+        notification::mark_all_read,
+        notification::get_preferences,
+        notification::update_preferences,
+        event::stream,
+        event::create_token,
+        graphql::graphql,
+        webhook::get_webhooks,
+        webhook::create_webhook,
+        webhook::get_webhook,
+        webhook::update_webhook,
+        webhook::delete_webhook,
+        webhook::get_deliveries,
+        webhook::redeliver,
+    ]
+}
+
+///
+/// Helpers
+///
+
+/// A version's routes: the earlier version's, with `changes` replacing the
+/// ones they share a method and path with
+fn revise(earlier: &[Route], changes: Vec<Route>) -> Vec<Route> {
+    let mut routes = earlier
+        .iter()
+        .filter(|r| {
+            !changes
+                .iter()
+                .any(|c| c.method == r.method && c.uri.path() == r.uri.path())
+        })
+        .cloned()
+        .collect::<Vec<Route>>();
+    routes.extend(changes);
+    routes
+}
+
+fn deprecate(
+    routes: &[Route],
+    sunset: &'static str,
+    base: &str,
+    successor: Option<String>,
+) -> Vec<Route> {
+    routes
+        .iter()
+        .map(|r| {
+            let mut route = r.clone();
+            route.handler = Box::new(Deprecated {
+                handler: r.handler.clone(),
+                sunset,
+                base: base.to_owned(),
+                successor: successor.clone(),
+            });
+            route
+        })
+        .collect()
+}
+
+///
+/// Public API
+///
+
+/// Mount every version, and the first one at its unversioned paths
+pub fn mount(rocket: Rocket) -> Rocket {
+    let mut rocket = rocket;
+    let mut routes = Vec::new();
+
+    for (i, version) in VERSIONS.iter().enumerate() {
+        routes = revise(&routes, (version.changes)());
+        let base = format!("/{}", version.name);
+        let served = match version.sunset {
+            Some(sunset) => {
+                let successor = VERSIONS.get(i + 1).map(|v| format!("/{}", v.name));
+                deprecate(&routes, sunset, &base, successor)
+            }
+            None => routes.clone(),
+        };
+        rocket = rocket.mount(&base, served);
+    }
+
+    let first = &VERSIONS[0];
+    let aliases = deprecate(
+        &(first.changes)(),
+        UNVERSIONED_SUNSET,
+        "",
+        Some(format!("/{}", first.name)),
+    );
+    rocket.mount("/", aliases)
+}
@@ -0,0 +1,447 @@
+//
+// webhooks/mod.rs
+//
+// Each delivery is a JSON POST signed with the subscription's secret. The
+// `X-Dwello-Signature` header holds `sha256=` and the hex HMAC-SHA256 of
+// the `X-Dwello-Timestamp` header, a `.` and the body, so receivers can
+// check the body and reject replays.
+//
+pub mod types;
+
+use self::types::*;
+use accounts::types::{CurrentUser, CurrentUser::*};
+use db::Conn;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use hmac::{Hmac, Mac};
+use jobs;
+use jobs::types::JobPayload;
+use rand::Rng;
+use result::{Error, Payload, Response};
+use serde_json::json;
+use sha2::Sha256;
+use std::io::Read;
+use std::time::Duration;
+use validator::Validate;
+
+/// How long a receiver has to answer
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Most of a response's body kept in the delivery log
+const MAX_RESPONSE_BODY: usize = 2048;
+
+/// Length of generated secrets
+const SECRET_LENGTH: usize = 32;
+
+/// Times a delivery is tried before it's marked failed
+const MAX_ATTEMPTS: i32 = 8;
+
+/// Most deliveries a single listing returns
+const MAX_DELIVERIES: i64 = 100;
+
+///
+/// Helpers
+///
+
+fn new_secret() -> String {
+    let mut rng = rand::thread_rng();
+    (0..SECRET_LENGTH)
+        .map(|_| rng.sample(rand::distributions::Alphanumeric))
+        .collect()
+}
+
+fn validate_event_types(event_types: &[String]) -> Result<(), Error> {
+    let unknown = event_types
+        .iter()
+        .filter(|t| !WebhookEvent::ALL.iter().any(|e| e.name() == t.as_str()))
+        .cloned()
+        .collect::<Vec<String>>();
+
+    if unknown.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::from_custom_validation(
+            "unknown_event_type",
+            "event_types",
+            &format!("Unknown event types: {}", unknown.join(", ")),
+        ))
+    }
+}
+
+/// Hex HMAC-SHA256 signature of a delivery
+fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_varkey(secret.as_bytes()).expect("HMAC takes keys of any length");
+    mac.input(timestamp.to_string().as_bytes());
+    mac.input(b".");
+    mac.input(body);
+    hex::encode(mac.result().code())
+}
+
+/// Remove every access code from a payload. Anyone with a deal's code can
+/// respond as its seller, so codes never leave the app.
+fn strip_access_codes(value: &mut serde_json::Value) {
+    match *value {
+        serde_json::Value::Object(ref mut fields) => {
+            fields.remove("access_code");
+            for field in fields.values_mut() {
+                strip_access_codes(field);
+            }
+        }
+        serde_json::Value::Array(ref mut items) => {
+            for item in items.iter_mut() {
+                strip_access_codes(item);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Queue a delivery of a payload to a subscription, without any access
+/// codes in it
+fn queue_delivery(
+    conn: &PgConnection,
+    webhook_id: i32,
+    event_type: &str,
+    mut payload: serde_json::Value,
+) -> Result<WebhookDelivery, Error> {
+    use schema::webhook_deliveries;
+
+    strip_access_codes(&mut payload);
+
+    let now = chrono::Utc::now().naive_utc();
+    let delivery = diesel::insert_into(webhook_deliveries::table)
+        .values(&NewWebhookDelivery {
+            webhook_id,
+            event_type: event_type.to_owned(),
+            payload,
+            status: DeliveryStatus::Pending,
+            created: now,
+            updated: now,
+        })
+        .get_result::<WebhookDelivery>(conn)?;
+
+    jobs::enqueue(
+        conn,
+        &JobPayload::DeliverWebhook {
+            delivery_id: delivery.id,
+        },
+    )?;
+    Ok(delivery)
+}
+
+/// Queue deliveries of an event to every active subscription that wants
+/// it. Uses the caller's connection, so nothing is sent if the caller's
+/// transaction rolls back.
+pub fn trigger(
+    conn: &PgConnection,
+    event: WebhookEvent,
+    data: serde_json::Value,
+) -> Result<usize, Error> {
+    use schema::webhooks::dsl::*;
+
+    let subscribed = webhooks
+        .filter(active.eq(true))
+        .load::<Webhook>(conn)?
+        .into_iter()
+        .filter(|w| w.event_types.is_empty() || w.event_types.iter().any(|t| t == event.name()))
+        .collect::<Vec<Webhook>>();
+
+    let body = json!({
+        "event": event.name(),
+        "created": chrono::Utc::now().naive_utc(),
+        "data": data,
+    });
+    for w in &subscribed {
+        queue_delivery(conn, w.id, event.name(), body.clone())?;
+    }
+    Ok(subscribed.len())
+}
+
+/// Send a delivery and log the attempt. Failed attempts are retried with
+/// the job queue's backoff until `MAX_ATTEMPTS`. The delivery schedules its
+/// own retries rather than failing its job, so each attempt stays in the log
+/// instead of being rolled back with the job.
+pub fn deliver(conn: &PgConnection, delivery_id: i32) -> Result<(), Error> {
+    use schema::{webhook_deliveries, webhooks};
+
+    let delivery = webhook_deliveries::table
+        .find(delivery_id)
+        .first::<WebhookDelivery>(conn)?;
+    let webhook = webhooks::table
+        .find(delivery.webhook_id)
+        .first::<Webhook>(conn)?;
+    if delivery.status != DeliveryStatus::Pending || !webhook.active {
+        return Ok(());
+    }
+
+    let body = serde_json::to_vec(&delivery.payload)?;
+    let timestamp = chrono::Utc::now().timestamp();
+    let sent = reqwest::Client::builder()
+        .timeout(DELIVERY_TIMEOUT)
+        .build()
+        .and_then(|client| {
+            client
+                .post(&webhook.url)
+                .header("Content-Type", "application/json")
+                .header("User-Agent", "Dwello-Webhooks")
+                .header("X-Dwello-Event", delivery.event_type.as_str())
+                .header("X-Dwello-Delivery", delivery.id.to_string())
+                .header("X-Dwello-Timestamp", timestamp.to_string())
+                .header(
+                    "X-Dwello-Signature",
+                    format!("sha256={}", sign(&webhook.secret, timestamp, &body)),
+                )
+                .body(body)
+                .send()
+        });
+
+    let (code, response, failure) = match sent {
+        Ok(mut res) => {
+            let mut text = String::new();
+            let _ = res
+                .by_ref()
+                .take(MAX_RESPONSE_BODY as u64)
+                .read_to_string(&mut text);
+            let failure = if res.status().is_success() {
+                None
+            } else {
+                Some(format!("Receiver answered {}", res.status()))
+            };
+            (Some(res.status().as_u16() as i32), Some(text), failure)
+        }
+        Err(e) => (None, None, Some(e.to_string())),
+    };
+
+    let now = chrono::Utc::now().naive_utc();
+    let attempts = delivery.attempts + 1;
+    let status = match failure {
+        None => DeliveryStatus::Delivered,
+        Some(_) if attempts < MAX_ATTEMPTS => DeliveryStatus::Pending,
+        Some(_) => DeliveryStatus::Failed,
+    };
+    diesel::update(webhook_deliveries::table.find(delivery.id))
+        .set((
+            webhook_deliveries::status.eq(status),
+            webhook_deliveries::attempts.eq(attempts),
+            webhook_deliveries::response_status.eq(code),
+            webhook_deliveries::response_body.eq(response),
+            webhook_deliveries::error.eq(&failure),
+            webhook_deliveries::delivered.eq(failure.as_ref().map_or(Some(now), |_| None)),
+            webhook_deliveries::updated.eq(now),
+        ))
+        .execute(conn)?;
+
+    if status == DeliveryStatus::Pending {
+        jobs::enqueue_at(
+            conn,
+            &JobPayload::DeliverWebhook { delivery_id },
+            now + jobs::backoff(attempts),
+        )?;
+    }
+    Ok(())
+}
+
+///
+/// Public API
+///
+
+/// Get webhook subscriptions
+pub fn get_webhooks(user: CurrentUser, conn: Conn) -> Response<Vec<Webhook>> {
+    use schema::webhooks::dsl::*;
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let w = webhooks.order_by(id).load::<Webhook>(&conn)?;
+
+    Ok(Payload {
+        data: w,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Get a webhook subscription
+pub fn get_webhook(webhook_id: i32, user: CurrentUser, conn: Conn) -> Response<Webhook> {
+    use schema::webhooks::dsl::*;
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let w = webhooks.find(webhook_id).first::<Webhook>(&conn)?;
+
+    Ok(Payload {
+        data: w,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Subscribe a URL to events
+pub fn create_webhook(
+    user: CurrentUser,
+    conn: Conn,
+    input: CreateWebhookInput,
+) -> Response<CreatedWebhook> {
+    use schema::webhooks;
+
+    let user = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    input.validate()?;
+    validate_event_types(&input.event_types)?;
+
+    let now = chrono::Utc::now().naive_utc();
+    let w = diesel::insert_into(webhooks::table)
+        .values(&NewWebhook {
+            url: input.url,
+            event_types: input.event_types,
+            secret: input.secret.unwrap_or_else(new_secret),
+            active: true,
+            created_by: Some(user.id),
+            created: now,
+            updated: now,
+        })
+        .get_result::<Webhook>(&conn)?;
+
+    Ok(Payload {
+        data: CreatedWebhook {
+            secret: w.secret.clone(),
+            webhook: w,
+        },
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Update a webhook subscription
+pub fn update_webhook(
+    webhook_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+    input: UpdateWebhookInput,
+) -> Response<Webhook> {
+    use schema::webhooks::dsl::*;
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    input.validate()?;
+    if let Some(ref types) = input.event_types {
+        validate_event_types(types)?;
+    }
+
+    let w = webhooks.find(webhook_id).first::<Webhook>(&conn)?;
+
+    // If the field is set, use the value
+    // If it is not set, ignore.
+    let w = diesel::update(&w)
+        .set((
+            url.eq(input.url.unwrap_or(w.url.clone())),
+            event_types.eq(input.event_types.unwrap_or(w.event_types.clone())),
+            secret.eq(input.secret.unwrap_or(w.secret.clone())),
+            active.eq(input.active.unwrap_or(w.active)),
+            updated.eq(chrono::Utc::now().naive_utc()),
+        ))
+        .get_result::<Webhook>(&conn)?;
+
+    Ok(Payload {
+        data: w,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Delete a webhook subscription and its delivery log
+pub fn delete_webhook(webhook_id: i32, user: CurrentUser, conn: Conn) -> Response<Webhook> {
+    use schema::webhooks::dsl::*;
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let w = diesel::delete(webhooks.find(webhook_id)).get_result::<Webhook>(&conn)?;
+
+    Ok(Payload {
+        data: w,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Get a subscription's deliveries, newest first
+pub fn get_deliveries(
+    webhook_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+) -> Response<Vec<WebhookDelivery>> {
+    use schema::webhook_deliveries;
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let d = webhook_deliveries::table
+        .filter(webhook_deliveries::webhook_id.eq(webhook_id))
+        .order_by(webhook_deliveries::created.desc())
+        .limit(MAX_DELIVERIES)
+        .load::<WebhookDelivery>(&conn)?;
+
+    Ok(Payload {
+        data: d,
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Send a delivery's payload again, as a new delivery
+pub fn redeliver(
+    webhook_id: i32,
+    delivery_id: i32,
+    user: CurrentUser,
+    conn: Conn,
+) -> Response<WebhookDelivery> {
+    use schema::webhook_deliveries;
+
+    let _ = match user {
+        Admin(user) => user,
+        _ => return Err(Error::AccessDenied),
+    };
+    let Conn(conn) = conn;
+
+    let original = webhook_deliveries::table
+        .find(delivery_id)
+        .filter(webhook_deliveries::webhook_id.eq(webhook_id))
+        .first::<WebhookDelivery>(&conn)?;
+    let d = conn.transaction::<_, Error, _>(|| {
+        queue_delivery(
+            &conn,
+            original.webhook_id,
+            &original.event_type,
+            original.payload.clone(),
+        )
+    })?;
+
+    Ok(Payload {
+        data: d,
+        success: true,
+        ..Default::default()
+    })
+}
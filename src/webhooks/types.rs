@@ -0,0 +1,158 @@
+//
+// webhooks/types.rs
+//
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Varchar;
+use schema::{webhook_deliveries, webhooks};
+use std::io::Write;
+use validator::Validate;
+
+/// What a subscription can be sent
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WebhookEvent {
+    DealCreated,
+    DealStatusChanged,
+    SellerResponded,
+    OfferAccepted,
+}
+
+impl WebhookEvent {
+    pub const ALL: [WebhookEvent; 4] = [
+        WebhookEvent::DealCreated,
+        WebhookEvent::DealStatusChanged,
+        WebhookEvent::SellerResponded,
+        WebhookEvent::OfferAccepted,
+    ];
+
+    /// Name used in subscriptions and sent with each delivery
+    pub fn name(&self) -> &'static str {
+        match *self {
+            WebhookEvent::DealCreated => "deal.created",
+            WebhookEvent::DealStatusChanged => "deal.status_changed",
+            WebhookEvent::SellerResponded => "seller.responded",
+            WebhookEvent::OfferAccepted => "offer.accepted",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, AsExpression, FromSqlRow)]
+#[sql_type = "Varchar"]
+pub enum DeliveryStatus {
+    /// Waiting for its first attempt, or for a retry
+    Pending,
+    Delivered,
+    /// Every attempt failed
+    Failed,
+}
+
+impl ToSql<Varchar, Pg> for DeliveryStatus {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        match *self {
+            DeliveryStatus::Pending => out.write_all(b"pending")?,
+            DeliveryStatus::Delivered => out.write_all(b"delivered")?,
+            DeliveryStatus::Failed => out.write_all(b"failed")?,
+        }
+
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Pg> for DeliveryStatus {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match not_none!(bytes) {
+            b"pending" => Ok(DeliveryStatus::Pending),
+            b"delivered" => Ok(DeliveryStatus::Delivered),
+            b"failed" => Ok(DeliveryStatus::Failed),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
+
+#[derive(Serialize, Identifiable, Clone, Queryable, Debug)]
+#[table_name = "webhooks"]
+pub struct Webhook {
+    pub id: i32,
+    pub url: String,
+    /// Events to send, by name. Empty sends every event.
+    pub event_types: Vec<String>,
+    /// Key deliveries are signed with. Only sent when the subscription is
+    /// created.
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub active: bool,
+    pub created_by: Option<i32>,
+    pub created: chrono::NaiveDateTime,
+    pub updated: chrono::NaiveDateTime,
+}
+
+/// A new subscription, with the secret it signs deliveries with
+#[derive(Serialize)]
+pub struct CreatedWebhook {
+    pub webhook: Webhook,
+    pub secret: String,
+}
+
+#[derive(Insertable)]
+#[table_name = "webhooks"]
+pub struct NewWebhook {
+    pub url: String,
+    pub event_types: Vec<String>,
+    pub secret: String,
+    pub active: bool,
+    pub created_by: Option<i32>,
+    pub created: chrono::NaiveDateTime,
+    pub updated: chrono::NaiveDateTime,
+}
+
+#[derive(Serialize, Identifiable, Clone, Queryable, Debug)]
+#[table_name = "webhook_deliveries"]
+pub struct WebhookDelivery {
+    pub id: i32,
+    pub webhook_id: i32,
+    pub event_type: String,
+    /// Body as sent
+    pub payload: serde_json::Value,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    pub response_status: Option<i32>,
+    /// Start of the last response's body
+    pub response_body: Option<String>,
+    pub error: Option<String>,
+    pub delivered: Option<chrono::NaiveDateTime>,
+    pub created: chrono::NaiveDateTime,
+    pub updated: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "webhook_deliveries"]
+pub struct NewWebhookDelivery {
+    pub webhook_id: i32,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    pub status: DeliveryStatus,
+    pub created: chrono::NaiveDateTime,
+    pub updated: chrono::NaiveDateTime,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CreateWebhookInput {
+    #[validate(url(message = "Must be a URL"))]
+    pub url: String,
+    #[serde(default)]
+    pub event_types: Vec<String>,
+    /// Generated when left out
+    #[validate(length(min = "16", message = "Must be at least 16 characters"))]
+    pub secret: Option<String>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct UpdateWebhookInput {
+    #[validate(url(message = "Must be a URL"))]
+    pub url: Option<String>,
+    pub event_types: Option<Vec<String>>,
+    #[validate(length(min = "16", message = "Must be at least 16 characters"))]
+    pub secret: Option<String>,
+    pub active: Option<bool>,
+}